@@ -0,0 +1,10 @@
+DROP TABLE IF EXISTS api_tokens;
+DROP TABLE IF EXISTS job_projects;
+DROP TABLE IF EXISTS jobs;
+DROP TABLE IF EXISTS error_log;
+DROP TABLE IF EXISTS unsafe_sites;
+DROP TABLE IF EXISTS project_crates;
+DROP TABLE IF EXISTS project_stats_categories;
+DROP TABLE IF EXISTS project_stats;
+DROP TABLE IF EXISTS projects;
+DROP TABLE IF EXISTS providers;
\ No newline at end of file
@@ -0,0 +1,172 @@
+-- The schema of ../migrations, in SQLite. The dates are ISO 8601 text.
+CREATE TABLE IF NOT EXISTS providers
+(
+    id  integer      primary key,
+    url varchar(255) not null unique
+);
+
+CREATE TABLE IF NOT EXISTS projects
+(
+    id                    integer      primary key,
+    provider_id           int          not null,
+    namespace             varchar(255) not null,
+    name                  varchar(255) not null unique,
+    update_interval_hours int          not null default 168,
+    last_run_at           text,
+    next_run_at           text         not null default (datetime('now')),
+    CONSTRAINT fk_provider FOREIGN KEY (provider_id) REFERENCES providers (id)
+);
+
+CREATE INDEX IF NOT EXISTS projects_next_run_at_idx ON projects (next_run_at);
+
+CREATE TABLE IF NOT EXISTS project_stats
+(
+    id                      integer     primary key,
+    project_id              int         not null,
+    code_lines              int         not null,
+    comment_lines           int         not null default 0,
+    doc_comment_lines       int         not null default 0,
+    blank_lines             int         not null default 0,
+    unsafe_lines            int         not null,
+    unsafe_blocks           int         not null default 0,
+    unsafe_functions        int         not null default 0,
+    unsafe_impls            int         not null default 0,
+    unsafe_traits           int         not null default 0,
+    extern_blocks           int         not null default 0,
+    no_mangle_items         int         not null default 0,
+    unsafe_block_lines      int         not null default 0,
+    documented_unsafe       int         not null default 0,
+    undocumented_unsafe     int         not null default 0,
+    production_code_lines   int         not null default 0,
+    production_unsafe_lines int         not null default 0,
+    unsafe_code_lint        varchar(16) not null default 'allow',
+    commit_sha              varchar(40),
+    commit_branch           varchar(255),
+    commit_date             text,
+    historical              boolean     not null default false,
+    created_at              text        not null default (date('now')),
+    updated_at              text        not null default (date('now')),
+    CONSTRAINT fk_project FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE
+);
+
+-- A current row is updated while unsafe_lines stays the same, a backfilled one is kept per commit.
+CREATE UNIQUE INDEX IF NOT EXISTS project_stats_current_idx
+    ON project_stats (project_id, unsafe_lines) WHERE NOT historical;
+CREATE UNIQUE INDEX IF NOT EXISTS project_stats_historical_idx
+    ON project_stats (project_id, commit_sha) WHERE historical;
+
+CREATE TABLE IF NOT EXISTS project_stats_categories
+(
+    project_stats_id    int         not null,
+    category            varchar(32) not null,
+    code_lines          int         not null,
+    comment_lines       int         not null,
+    doc_comment_lines   int         not null,
+    blank_lines         int         not null,
+    unsafe_lines        int         not null,
+    unsafe_blocks       int         not null,
+    unsafe_functions    int         not null,
+    unsafe_impls        int         not null,
+    unsafe_traits       int         not null,
+    extern_blocks       int         not null,
+    no_mangle_items     int         not null,
+    unsafe_block_lines  int         not null,
+    documented_unsafe   int         not null default 0,
+    undocumented_unsafe int         not null default 0,
+    PRIMARY KEY (project_stats_id, category),
+    CONSTRAINT fk_project_stats FOREIGN KEY (project_stats_id) REFERENCES project_stats (id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS project_crates
+(
+    id                      integer      primary key,
+    project_id              int          not null,
+    name                    varchar(255) not null,
+    path                    varchar(255) not null,
+    code_lines              int          not null,
+    comment_lines           int          not null,
+    doc_comment_lines       int          not null,
+    blank_lines             int          not null,
+    unsafe_lines            int          not null,
+    unsafe_blocks           int          not null,
+    unsafe_functions        int          not null,
+    unsafe_impls            int          not null,
+    unsafe_traits           int          not null,
+    extern_blocks           int          not null,
+    no_mangle_items         int          not null,
+    unsafe_block_lines      int          not null,
+    documented_unsafe       int          not null default 0,
+    undocumented_unsafe     int          not null default 0,
+    production_code_lines   int          not null,
+    production_unsafe_lines int          not null,
+    unsafe_code_lint        varchar(16)  not null default 'allow',
+    created_at              text         not null default (date('now')),
+    updated_at              text         not null default (date('now')),
+    UNIQUE (project_id, name),
+    CONSTRAINT fk_project FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS unsafe_sites
+(
+    id         integer       primary key,
+    project_id int           not null,
+    commit_sha varchar(40),
+    file_path  varchar(1024) not null,
+    start_line int           not null,
+    end_line   int           not null,
+    kind       varchar(32)   not null,
+    item_path  varchar(1024) not null,
+    category   varchar(32)   not null,
+    documented boolean,
+    CONSTRAINT fk_project FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS unsafe_sites_project_id_idx ON unsafe_sites (project_id);
+
+CREATE TABLE IF NOT EXISTS error_log
+(
+    id         integer primary key,
+    error      text    not null,
+    created_at text    not null default (date('now'))
+);
+
+CREATE TABLE IF NOT EXISTS jobs
+(
+    id          integer     primary key,
+    kind           varchar(32) not null,
+    status         varchar(16) not null default 'queued',
+    backfill_every int,
+    backfill_limit int,
+    created_at     text        not null default (datetime('now')),
+    started_at     text,
+    finished_at    text
+);
+
+CREATE TABLE IF NOT EXISTS job_projects
+(
+    id          integer     primary key,
+    job_id      int         not null,
+    project_id  int         not null,
+    status      varchar(16) not null default 'queued',
+    attempts    int         not null default 0,
+    error       text,
+    run_after   text        not null default (datetime('now')),
+    started_at  text,
+    finished_at text,
+    CONSTRAINT fk_job FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE,
+    CONSTRAINT fk_project FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE,
+    CONSTRAINT job_projects_job_id_project_id_key UNIQUE (job_id, project_id)
+);
+
+CREATE INDEX IF NOT EXISTS job_projects_status_run_after_idx ON job_projects (status, run_after);
+
+CREATE TABLE IF NOT EXISTS api_tokens
+(
+    id           integer      primary key,
+    name         varchar(255) not null unique,
+    token_hash   char(64)     not null unique,
+    scope        varchar(16)  not null,
+    created_at   text         not null default (datetime('now')),
+    last_used_at text,
+    revoked_at   text
+);
\ No newline at end of file
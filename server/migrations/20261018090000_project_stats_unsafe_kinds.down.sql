@@ -0,0 +1,8 @@
+alter table project_stats
+    drop column if exists unsafe_blocks,
+    drop column if exists unsafe_functions,
+    drop column if exists unsafe_impls,
+    drop column if exists unsafe_traits,
+    drop column if exists extern_blocks,
+    drop column if exists no_mangle_items,
+    drop column if exists unsafe_block_lines;
\ No newline at end of file
@@ -0,0 +1,8 @@
+alter table project_stats
+    add column if not exists unsafe_blocks      int not null default 0,
+    add column if not exists unsafe_functions   int not null default 0,
+    add column if not exists unsafe_impls       int not null default 0,
+    add column if not exists unsafe_traits      int not null default 0,
+    add column if not exists extern_blocks      int not null default 0,
+    add column if not exists no_mangle_items    int not null default 0,
+    add column if not exists unsafe_block_lines int not null default 0;
\ No newline at end of file
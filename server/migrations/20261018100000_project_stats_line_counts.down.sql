@@ -0,0 +1,4 @@
+alter table project_stats
+    drop column if exists comment_lines,
+    drop column if exists doc_comment_lines,
+    drop column if exists blank_lines;
\ No newline at end of file
@@ -0,0 +1,4 @@
+alter table project_stats
+    add column if not exists comment_lines     int not null default 0,
+    add column if not exists doc_comment_lines int not null default 0,
+    add column if not exists blank_lines       int not null default 0;
\ No newline at end of file
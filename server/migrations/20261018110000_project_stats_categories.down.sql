@@ -0,0 +1,6 @@
+drop table if exists project_stats_categories;
+
+alter table project_stats
+    drop column if exists id,
+    drop column if exists production_code_lines,
+    drop column if exists production_unsafe_lines;
\ No newline at end of file
@@ -0,0 +1,24 @@
+alter table project_stats
+    add column if not exists id                      serial primary key,
+    add column if not exists production_code_lines   int not null default 0,
+    add column if not exists production_unsafe_lines int not null default 0;
+
+CREATE TABLE IF NOT EXISTS project_stats_categories
+(
+    project_stats_id   int         not null,
+    category           varchar(32) not null,
+    code_lines         int         not null,
+    comment_lines      int         not null,
+    doc_comment_lines  int         not null,
+    blank_lines        int         not null,
+    unsafe_lines       int         not null,
+    unsafe_blocks      int         not null,
+    unsafe_functions   int         not null,
+    unsafe_impls       int         not null,
+    unsafe_traits      int         not null,
+    extern_blocks      int         not null,
+    no_mangle_items    int         not null,
+    unsafe_block_lines int         not null,
+    PRIMARY KEY (project_stats_id, category),
+    CONSTRAINT fk_project_stats FOREIGN KEY (project_stats_id) REFERENCES project_stats (id) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1 @@
+drop table if exists project_crates;
\ No newline at end of file
@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS project_crates
+(
+    id                      serial       primary key,
+    project_id              int          not null,
+    name                    varchar(255) not null,
+    path                    varchar(255) not null,
+    code_lines              int          not null,
+    comment_lines           int          not null,
+    doc_comment_lines       int          not null,
+    blank_lines             int          not null,
+    unsafe_lines            int          not null,
+    unsafe_blocks           int          not null,
+    unsafe_functions        int          not null,
+    unsafe_impls            int          not null,
+    unsafe_traits           int          not null,
+    extern_blocks           int          not null,
+    no_mangle_items         int          not null,
+    unsafe_block_lines      int          not null,
+    production_code_lines   int          not null,
+    production_unsafe_lines int          not null,
+    created_at              DATE         not null default CURRENT_DATE,
+    updated_at              DATE         not null default CURRENT_DATE,
+    UNIQUE (project_id, name),
+    CONSTRAINT fk_project FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1 @@
+drop table if exists unsafe_sites;
\ No newline at end of file
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS unsafe_sites
+(
+    id         serial        primary key,
+    project_id int           not null,
+    commit_sha varchar(40),
+    file_path  varchar(1024) not null,
+    start_line int           not null,
+    end_line   int           not null,
+    kind       varchar(32)   not null,
+    item_path  varchar(1024) not null,
+    category   varchar(32)   not null,
+    CONSTRAINT fk_project FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS unsafe_sites_project_id_idx ON unsafe_sites (project_id);
\ No newline at end of file
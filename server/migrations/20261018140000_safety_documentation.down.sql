@@ -0,0 +1,14 @@
+alter table unsafe_sites
+    drop column if exists documented;
+
+alter table project_crates
+    drop column if exists documented_unsafe,
+    drop column if exists undocumented_unsafe;
+
+alter table project_stats_categories
+    drop column if exists documented_unsafe,
+    drop column if exists undocumented_unsafe;
+
+alter table project_stats
+    drop column if exists documented_unsafe,
+    drop column if exists undocumented_unsafe;
\ No newline at end of file
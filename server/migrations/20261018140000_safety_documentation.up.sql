@@ -0,0 +1,14 @@
+alter table project_stats
+    add column if not exists documented_unsafe   int not null default 0,
+    add column if not exists undocumented_unsafe int not null default 0;
+
+alter table project_stats_categories
+    add column if not exists documented_unsafe   int not null default 0,
+    add column if not exists undocumented_unsafe int not null default 0;
+
+alter table project_crates
+    add column if not exists documented_unsafe   int not null default 0,
+    add column if not exists undocumented_unsafe int not null default 0;
+
+alter table unsafe_sites
+    add column if not exists documented boolean;
\ No newline at end of file
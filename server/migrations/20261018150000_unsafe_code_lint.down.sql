@@ -0,0 +1,5 @@
+alter table project_crates
+    drop column if exists unsafe_code_lint;
+
+alter table project_stats
+    drop column if exists unsafe_code_lint;
\ No newline at end of file
@@ -0,0 +1,5 @@
+alter table project_stats
+    add column if not exists unsafe_code_lint varchar(16) not null default 'allow';
+
+alter table project_crates
+    add column if not exists unsafe_code_lint varchar(16) not null default 'allow';
\ No newline at end of file
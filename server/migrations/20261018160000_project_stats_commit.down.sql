@@ -0,0 +1,4 @@
+alter table project_stats
+    drop column if exists commit_sha,
+    drop column if exists commit_branch,
+    drop column if exists commit_date;
\ No newline at end of file
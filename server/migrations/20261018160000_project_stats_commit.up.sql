@@ -0,0 +1,4 @@
+alter table project_stats
+    add column if not exists commit_sha    varchar(40),
+    add column if not exists commit_branch varchar(255),
+    add column if not exists commit_date   timestamptz;
\ No newline at end of file
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS job_projects;
+DROP TABLE IF EXISTS jobs;
\ No newline at end of file
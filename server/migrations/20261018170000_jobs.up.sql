@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS jobs
+(
+    id          serial      primary key,
+    kind        varchar(32) not null,
+    status      varchar(16) not null default 'queued',
+    created_at  timestamptz not null default now(),
+    started_at  timestamptz,
+    finished_at timestamptz
+);
+
+CREATE TABLE IF NOT EXISTS job_projects
+(
+    id          serial      primary key,
+    job_id      int         not null,
+    project_id  int         not null,
+    status      varchar(16) not null default 'queued',
+    attempts    int         not null default 0,
+    error       text,
+    run_after   timestamptz not null default now(),
+    started_at  timestamptz,
+    finished_at timestamptz,
+    CONSTRAINT fk_job FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE,
+    CONSTRAINT fk_project FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE,
+    CONSTRAINT job_projects_job_id_project_id_key UNIQUE (job_id, project_id)
+);
+
+CREATE INDEX IF NOT EXISTS job_projects_status_run_after_idx ON job_projects (status, run_after);
\ No newline at end of file
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS projects_next_run_at_idx;
+
+alter table projects
+    drop column if exists update_interval_hours,
+    drop column if exists last_run_at,
+    drop column if exists next_run_at;
\ No newline at end of file
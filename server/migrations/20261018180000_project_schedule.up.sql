@@ -0,0 +1,6 @@
+alter table projects
+    add column if not exists update_interval_hours int         not null default 168,
+    add column if not exists last_run_at           timestamptz,
+    add column if not exists next_run_at           timestamptz not null default now();
+
+CREATE INDEX IF NOT EXISTS projects_next_run_at_idx ON projects (next_run_at);
\ No newline at end of file
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS api_tokens
+(
+    id           serial       primary key,
+    name         varchar(255) not null unique,
+    token_hash   char(64)     not null unique,
+    scope        varchar(16)  not null,
+    created_at   timestamptz  not null default now(),
+    last_used_at timestamptz,
+    revoked_at   timestamptz
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+alter table project_stats
+    drop constraint if exists fk_project,
+    add constraint fk_project foreign key (project_id) references projects (id);
\ No newline at end of file
@@ -0,0 +1,8 @@
+drop index if exists project_stats_historical_idx;
+drop index if exists project_stats_current_idx;
+
+-- The backfilled rows do not fit the former key.
+delete from project_stats where historical;
+alter table project_stats
+    drop column if exists historical,
+    add constraint project_stats_project_id_unsafe_lines_key unique (project_id, unsafe_lines);
\ No newline at end of file
@@ -0,0 +1,9 @@
+alter table project_stats
+    add column if not exists historical boolean not null default false,
+    drop constraint if exists project_stats_project_id_unsafe_lines_key;
+
+-- A current row is updated while unsafe_lines stays the same, a backfilled one is kept per commit.
+CREATE UNIQUE INDEX IF NOT EXISTS project_stats_current_idx
+    ON project_stats (project_id, unsafe_lines) WHERE NOT historical;
+CREATE UNIQUE INDEX IF NOT EXISTS project_stats_historical_idx
+    ON project_stats (project_id, commit_sha) WHERE historical;
\ No newline at end of file
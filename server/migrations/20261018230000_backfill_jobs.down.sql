@@ -0,0 +1,3 @@
+alter table jobs
+    drop column if exists backfill_every,
+    drop column if exists backfill_limit;
\ No newline at end of file
@@ -0,0 +1,3 @@
+alter table jobs
+    add column if not exists backfill_every int,
+    add column if not exists backfill_limit int;
\ No newline at end of file
@@ -0,0 +1 @@
+-- The construct counts are kept by kind, unsafe_lines stays the count of lines.
@@ -0,0 +1,30 @@
+-- unsafe_lines counts the lines of the unsafe blocks again, the analyzer stored the count of
+-- unsafe constructs in it. The constructs stay counted by kind.
+update project_stats_categories set unsafe_lines = unsafe_block_lines;
+
+-- The production unsafe lines of a crate are corrected by its next update.
+update project_crates set unsafe_lines = unsafe_block_lines;
+
+drop index if exists project_stats_current_idx;
+
+-- The rows of the former tools counted lines already and have no construct counts.
+update project_stats
+set unsafe_lines            = unsafe_block_lines,
+    production_unsafe_lines = coalesce((select c.unsafe_block_lines
+                                        from project_stats_categories c
+                                        where c.project_stats_id = project_stats.id
+                                          and c.category = 'source'), 0)
+where unsafe_lines = unsafe_blocks + unsafe_functions + unsafe_impls + unsafe_traits;
+
+-- Two current rows of a project may now have the same unsafe lines, the newest is kept.
+delete from project_stats ps
+where not ps.historical
+  and exists (select 1
+              from project_stats newer
+              where not newer.historical
+                and newer.project_id = ps.project_id
+                and newer.unsafe_lines = ps.unsafe_lines
+                and newer.id > ps.id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS project_stats_current_idx
+    ON project_stats (project_id, unsafe_lines) WHERE NOT historical;
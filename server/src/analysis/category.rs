@@ -0,0 +1,113 @@
+use std::path::Path;
+
+/// Where a piece of code lives in a project. Only `Source` ships to users.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// Library and binary source code.
+    Source,
+    /// Integration tests and `#[cfg(test)]`/`#[test]` code.
+    Tests,
+    Benches,
+    Examples,
+    BuildScripts,
+    /// Third party code copied into the repository.
+    Vendored,
+}
+
+impl Category {
+    pub const ALL: [Category; 6] = [
+        Category::Source,
+        Category::Tests,
+        Category::Benches,
+        Category::Examples,
+        Category::BuildScripts,
+        Category::Vendored,
+    ];
+
+    pub fn asStr(&self) -> &'static str {
+        return match self {
+            Category::Source => "source",
+            Category::Tests => "tests",
+            Category::Benches => "benches",
+            Category::Examples => "examples",
+            Category::BuildScripts => "build_scripts",
+            Category::Vendored => "vendored",
+        };
+    }
+
+    /// Categorizes a file by its path relative to the project root.
+    /// The `#[cfg(test)]` code of source files is handled by the analyzer.
+    pub fn fromPath(projectDir: &Path, file: &Path) -> Category {
+        let relative = file.strip_prefix(projectDir).unwrap_or(file);
+        let dirs: Vec<&str> = relative
+            .parent()
+            .map(|v| v.iter().filter_map(|v| v.to_str()).collect())
+            .unwrap_or_default();
+
+        if dirs
+            .iter()
+            .any(|v| matches!(*v, "vendor" | "vendored" | "third_party" | "third-party"))
+        {
+            return Category::Vendored;
+        }
+        if relative.file_name().is_some_and(|v| v == "build.rs")
+            && file.with_file_name("Cargo.toml").is_file()
+        {
+            return Category::BuildScripts;
+        }
+        for dir in dirs {
+            match dir {
+                "tests" => return Category::Tests,
+                "benches" => return Category::Benches,
+                "examples" => return Category::Examples,
+                _ => {}
+            }
+        }
+        return Category::Source;
+    }
+}
+
+impl std::str::FromStr for Category {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return Category::ALL
+            .into_iter()
+            .find(|v| v.asStr() == value)
+            .ok_or_else(|| format!("Unknown category: {value}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testCategoryFromPath() {
+        let projectDir = Path::new("/tmp/foo");
+        let category = |file: &str| Category::fromPath(projectDir, &projectDir.join(file));
+        assert_eq!(category("src/lib.rs"), Category::Source);
+        assert_eq!(category("crates/bar/src/main.rs"), Category::Source);
+        assert_eq!(category("tests/it.rs"), Category::Tests);
+        assert_eq!(category("crates/bar/tests/common/mod.rs"), Category::Tests);
+        assert_eq!(category("benches/bench.rs"), Category::Benches);
+        assert_eq!(category("examples/demo/tests/x.rs"), Category::Examples);
+        assert_eq!(category("vendor/libc/src/lib.rs"), Category::Vendored);
+        assert_eq!(category("third_party/tests/a.rs"), Category::Vendored);
+        // There is no Cargo.toml next to it.
+        assert_eq!(category("build.rs"), Category::Source);
+        // Only directories are taken into account.
+        assert_eq!(category("src/tests.rs"), Category::Source);
+    }
+
+    #[test]
+    fn testCategoryFromStr() {
+        for category in Category::ALL {
+            assert_eq!(category.asStr().parse::<Category>(), Ok(category));
+        }
+        assert!("foo".parse::<Category>().is_err());
+    }
+}
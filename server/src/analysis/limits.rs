@@ -0,0 +1,47 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Instant;
+
+/// The resources that the update of one project may use. `None` is unlimited.
+#[derive(Debug, Default, Clone)]
+pub struct AnalysisLimits {
+    /// When the clone and the analysis are abandoned.
+    pub deadline: Option<Instant>,
+    /// The most bytes that a clone or a fetch may download, and that the files of the
+    /// checked-out tree may take.
+    pub maxRepositoryBytes: Option<u64>,
+    /// The most `.rs` files that are analyzed, and the most workspace members.
+    pub maxFiles: Option<usize>,
+    /// Set once nobody waits for the work anymore. Shared by every clone of the limits.
+    pub cancelled: Arc<AtomicBool>,
+}
+
+impl AnalysisLimits {
+    /// Whether the deadline passed or the work was cancelled.
+    pub fn isExpired(&self) -> bool {
+        return self.cancelled.load(Ordering::Relaxed)
+            || self.deadline.is_some_and(|v| Instant::now() >= v);
+    }
+
+    /// Stops the clone or the analysis that uses these limits at its next check.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testCancel() {
+        let limits = AnalysisLimits::default();
+        let shared = limits.clone();
+        assert!(!shared.isExpired());
+
+        limits.cancel();
+        assert!(shared.isExpired());
+    }
+}
@@ -0,0 +1,275 @@
+//! Line counting for Rust source files.
+//!
+//! Every line falls in exactly one category:
+//! - code: the line contains anything that is not a comment or whitespace,
+//!   including a line that only continues a multi-line string literal.
+//! - doc comment: the line only holds an outer (`///`, `/** */`) or
+//!   inner (`//!`, `/*! */`) doc comment.
+//! - comment: the line only holds regular comments.
+//! - blank: the line only holds whitespace.
+
+/// The line counts of one or more source files.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, sqlx::FromRow,
+)]
+pub struct LineCounts {
+    pub code_lines: i32,
+    pub comment_lines: i32,
+    pub doc_comment_lines: i32,
+    pub blank_lines: i32,
+}
+
+impl LineCounts {
+    pub fn fromKinds(kinds: &[LineKind]) -> Self {
+        let mut counts = Self::default();
+        for kind in kinds {
+            match kind {
+                LineKind::Code => counts.code_lines += 1,
+                LineKind::Comment => counts.comment_lines += 1,
+                LineKind::DocComment => counts.doc_comment_lines += 1,
+                LineKind::Blank => counts.blank_lines += 1,
+            }
+        }
+        return counts;
+    }
+
+    pub(crate) fn add(&mut self, other: &LineCounts) {
+        self.code_lines += other.code_lines;
+        self.comment_lines += other.comment_lines;
+        self.doc_comment_lines += other.doc_comment_lines;
+        self.blank_lines += other.blank_lines;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Code,
+    Comment,
+    DocComment,
+    Blank,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Code,
+    BlockComment { depth: u32, doc: bool },
+    Str,
+    RawStr { hashes: usize },
+}
+
+pub fn countLines(source: &str) -> LineCounts {
+    return LineCounts::fromKinds(&classifyLines(source));
+}
+
+/// Returns the kind of every line of `source`, in order.
+pub fn classifyLines(source: &str) -> Vec<LineKind> {
+    let mut kinds = Vec::new();
+    let mut state = State::Code;
+
+    for line in source.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut hasCode = matches!(state, State::Str | State::RawStr { .. });
+        let mut hasComment = false;
+        let mut hasDoc = false;
+        if let State::BlockComment { doc, .. } = state {
+            hasDoc = doc;
+            hasComment = !doc;
+        }
+
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            match state {
+                State::BlockComment { depth, doc } => {
+                    if c == '/' && next == Some('*') {
+                        state = State::BlockComment {
+                            depth: depth + 1,
+                            doc,
+                        };
+                        i += 2;
+                    } else if c == '*' && next == Some('/') {
+                        state = if depth == 1 {
+                            State::Code
+                        } else {
+                            State::BlockComment {
+                                depth: depth - 1,
+                                doc,
+                            }
+                        };
+                        i += 2;
+                    } else {
+                        i += 1;
+                    }
+                }
+                State::Str => {
+                    if c == '\\' {
+                        i += 2;
+                    } else {
+                        if c == '"' {
+                            state = State::Code;
+                        }
+                        i += 1;
+                    }
+                }
+                State::RawStr { hashes } => {
+                    if c == '"'
+                        && chars[i + 1..].iter().take_while(|v| **v == '#').count() >= hashes
+                    {
+                        state = State::Code;
+                        i += 1 + hashes;
+                    } else {
+                        i += 1;
+                    }
+                }
+                State::Code => {
+                    if c.is_whitespace() {
+                        i += 1;
+                    } else if c == '/' && next == Some('/') {
+                        let rest: String = chars[i..].iter().collect();
+                        if isDocComment(&rest, "///", "////") || rest.starts_with("//!") {
+                            hasDoc = true;
+                        } else {
+                            hasComment = true;
+                        }
+                        break;
+                    } else if c == '/' && next == Some('*') {
+                        let rest: String = chars[i..].iter().collect();
+                        let doc = isDocComment(&rest, "/**", "/***") && !rest.starts_with("/**/")
+                            || rest.starts_with("/*!");
+                        if doc {
+                            hasDoc = true;
+                        } else {
+                            hasComment = true;
+                        }
+                        state = State::BlockComment { depth: 1, doc };
+                        i += 2;
+                    } else if c == '"' {
+                        hasCode = true;
+                        state = State::Str;
+                        i += 1;
+                    } else if let Some(hashes) = rawStringStart(&chars[..i], &chars[i..]) {
+                        hasCode = true;
+                        state = State::RawStr { hashes };
+                        // Skip the prefix, the hashes and the opening quote.
+                        let prefix = if c == 'r' { 1 } else { 2 };
+                        i += prefix + hashes + 1;
+                    } else if c == '\'' {
+                        hasCode = true;
+                        i += charLiteralLength(&chars[i..]);
+                    } else {
+                        hasCode = true;
+                        i += 1;
+                    }
+                }
+            }
+        }
+
+        let kind = if hasCode {
+            LineKind::Code
+        } else if hasDoc {
+            LineKind::DocComment
+        } else if hasComment {
+            LineKind::Comment
+        } else {
+            LineKind::Blank
+        };
+        kinds.push(kind);
+    }
+
+    return kinds;
+}
+
+/// `////` and `/***` start regular comments, not doc comments.
+fn isDocComment(rest: &str, docPrefix: &str, regularPrefix: &str) -> bool {
+    return rest.starts_with(docPrefix) && !rest.starts_with(regularPrefix);
+}
+
+/// Returns the number of `#` if `chars` starts a raw (byte) string literal.
+fn rawStringStart(previous: &[char], chars: &[char]) -> Option<usize> {
+    // The `r` of an identifier like `bar` does not start a literal.
+    if previous
+        .last()
+        .is_some_and(|v| v.is_alphanumeric() || *v == '_')
+    {
+        return None;
+    }
+    let rest = match chars {
+        ['r', rest @ ..] => rest,
+        ['b', 'r', rest @ ..] => rest,
+        _ => return None,
+    };
+    let hashes = rest.iter().take_while(|v| **v == '#').count();
+    if rest.get(hashes) == Some(&'"') {
+        return Some(hashes);
+    }
+    return None;
+}
+
+/// Tells a char literal (`'a'`, `'\n'`, `'"'`) apart from a lifetime (`'a`).
+fn charLiteralLength(chars: &[char]) -> usize {
+    if chars.get(1) == Some(&'\\') {
+        // Skip the escaped char, which may be a quote itself.
+        if let Some(end) = chars.iter().skip(3).position(|v| *v == '\'') {
+            return end + 4;
+        }
+    } else if chars.get(2) == Some(&'\'') {
+        return 3;
+    }
+    return 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testCountLines() {
+        let source = r##"//! Crate docs.
+
+/// Item docs.
+//// Not a doc comment.
+fn main() {
+    // A comment.
+    let x = 1; // A trailing comment.
+
+    /* A block
+       comment */
+    let s = "// not a comment";
+    let r = r#"
+/* still a string */
+"#;
+}
+"##;
+        let counts = countLines(source);
+        assert_eq!(counts.code_lines, 7);
+        assert_eq!(counts.comment_lines, 4);
+        assert_eq!(counts.doc_comment_lines, 2);
+        assert_eq!(counts.blank_lines, 2);
+    }
+
+    #[test]
+    fn testCountLinesWithBlockDocComments() {
+        let source = "/**\n * Outer docs.\n */\n/*!\n Inner docs.\n*/\n/***/\n/**/ struct Foo;";
+        let counts = countLines(source);
+        assert_eq!(counts.doc_comment_lines, 6);
+        assert_eq!(counts.comment_lines, 1);
+        assert_eq!(counts.code_lines, 1);
+    }
+
+    #[test]
+    fn testCountLinesWithNestedBlockComments() {
+        let source = "/* outer /* inner */ still a comment */\nfn foo() {}";
+        let counts = countLines(source);
+        assert_eq!(counts.comment_lines, 1);
+        assert_eq!(counts.code_lines, 1);
+    }
+
+    #[test]
+    fn testCountLinesWithCharsAndLifetimes() {
+        let source = "fn foo<'a>(x: &'a str) -> char { '\"' }\n// comment\nconst C: char = '\\'';\n// comment";
+        let counts = countLines(source);
+        assert_eq!(counts.code_lines, 2);
+        assert_eq!(counts.comment_lines, 2);
+    }
+}
@@ -0,0 +1,235 @@
+use super::workspace::readManifest;
+use std::path::{Path, PathBuf};
+
+/// The level of the `unsafe_code` lint of a crate, from the weakest to the strictest.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsafeCodeLint {
+    #[default]
+    Allow,
+    Warn,
+    Deny,
+    /// The crate provably has no unsafe code of its own, since `forbid` cannot be overridden.
+    Forbid,
+}
+
+impl UnsafeCodeLint {
+    pub const ALL: [UnsafeCodeLint; 4] = [
+        UnsafeCodeLint::Allow,
+        UnsafeCodeLint::Warn,
+        UnsafeCodeLint::Deny,
+        UnsafeCodeLint::Forbid,
+    ];
+
+    pub fn asStr(&self) -> &'static str {
+        return match self {
+            UnsafeCodeLint::Allow => "allow",
+            UnsafeCodeLint::Warn => "warn",
+            UnsafeCodeLint::Deny => "deny",
+            UnsafeCodeLint::Forbid => "forbid",
+        };
+    }
+}
+
+impl std::str::FromStr for UnsafeCodeLint {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return UnsafeCodeLint::ALL
+            .into_iter()
+            .find(|v| v.asStr() == value)
+            .ok_or_else(|| format!("Unknown lint level: {value}"));
+    }
+}
+
+/// Returns the `unsafe_code` level of the crate at `crateDir`. The crate
+/// attributes take precedence over the `[lints]` of the manifest, unless the
+/// manifest forbids unsafe code. The weakest level of the lib and bin targets wins.
+pub fn unsafeCodeLint(projectDir: &Path, crateDir: &Path) -> UnsafeCodeLint {
+    let manifest = readManifest(crateDir).unwrap_or_default();
+    let manifestLevel = manifestLint(projectDir, &manifest);
+    if manifestLevel == Some(UnsafeCodeLint::Forbid) {
+        return UnsafeCodeLint::Forbid;
+    }
+
+    let roots = crateRoots(crateDir, &manifest);
+    if roots.is_empty() {
+        return manifestLevel.unwrap_or_default();
+    }
+    return roots
+        .iter()
+        .map(|v| {
+            let source = std::fs::read_to_string(v).unwrap_or_default();
+            let attributeLevel = syn::parse_file(&source)
+                .ok()
+                .and_then(|v| attributesLint(&v.attrs));
+            return attributeLevel.or(manifestLevel).unwrap_or_default();
+        })
+        .min()
+        .unwrap_or_default();
+}
+
+/// Reads `[lints.rust]`, following `[lints] workspace = true` to the root manifest.
+fn manifestLint(projectDir: &Path, manifest: &toml::Table) -> Option<UnsafeCodeLint> {
+    let lints = manifest.get("lints")?;
+    let rustLints = if lints.get("workspace").and_then(|v| v.as_bool()) == Some(true) {
+        readManifest(projectDir)?
+            .get("workspace")?
+            .get("lints")?
+            .get("rust")?
+            .clone()
+    } else {
+        lints.get("rust")?.clone()
+    };
+    let lint = rustLints.get("unsafe_code")?;
+    let level = lint
+        .as_str()
+        .or_else(|| lint.get("level").and_then(|v| v.as_str()))?;
+    return level.parse().ok();
+}
+
+/// The inner attributes of a crate root, like `#![forbid(unsafe_code)]`.
+/// `forbid` cannot be lowered, otherwise the last attribute wins.
+fn attributesLint(attributes: &[syn::Attribute]) -> Option<UnsafeCodeLint> {
+    let mut level = None;
+    for attribute in attributes {
+        let Some(attributeLevel) = attribute
+            .path()
+            .get_ident()
+            .and_then(|v| v.to_string().parse::<UnsafeCodeLint>().ok())
+        else {
+            continue;
+        };
+        let mut isUnsafeCode = false;
+        let _ = attribute.parse_nested_meta(|meta| {
+            isUnsafeCode |= meta.path.is_ident("unsafe_code");
+            return Ok(());
+        });
+        if !isUnsafeCode {
+            continue;
+        }
+        if level == Some(UnsafeCodeLint::Forbid) {
+            continue;
+        }
+        level = Some(attributeLevel);
+    }
+    return level;
+}
+
+/// The root files of the lib and bin targets of a crate.
+fn crateRoots(crateDir: &Path, manifest: &toml::Table) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    let libPath = manifest
+        .get("lib")
+        .and_then(|v| v.get("path"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("src/lib.rs");
+    roots.push(crateDir.join(libPath));
+    roots.push(crateDir.join("src/main.rs"));
+    let bins = manifest.get("bin").and_then(|v| v.as_array());
+    for bin in bins.into_iter().flatten() {
+        if let Some(path) = bin.get("path").and_then(|v| v.as_str()) {
+            roots.push(crateDir.join(path));
+        }
+    }
+    if let Ok(entries) = std::fs::read_dir(crateDir.join("src/bin")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                roots.push(path.join("main.rs"));
+            } else if path.extension().is_some_and(|v| v == "rs") {
+                roots.push(path);
+            }
+        }
+    }
+    roots.sort();
+    roots.dedup();
+    roots.retain(|v| v.is_file());
+    return roots;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    fn attributesLevel(source: &str) -> Option<UnsafeCodeLint> {
+        return attributesLint(&syn::parse_file(source).unwrap().attrs);
+    }
+
+    #[test]
+    fn testAttributesLint() {
+        assert_eq!(
+            attributesLevel("#![forbid(unsafe_code)]"),
+            Some(UnsafeCodeLint::Forbid)
+        );
+        assert_eq!(
+            attributesLevel("#![deny(missing_docs, unsafe_code)]"),
+            Some(UnsafeCodeLint::Deny)
+        );
+        assert_eq!(
+            attributesLevel("#![deny(unsafe_code)]\n#![allow(unsafe_code)]"),
+            Some(UnsafeCodeLint::Allow)
+        );
+        assert_eq!(
+            attributesLevel("#![forbid(unsafe_code)]\n#![allow(unsafe_code)]"),
+            Some(UnsafeCodeLint::Forbid)
+        );
+        assert_eq!(attributesLevel("#![deny(missing_docs)]"), None);
+        // Outer attributes only apply to the item.
+        assert_eq!(attributesLevel("#[forbid(unsafe_code)]\nfn foo() {}"), None);
+    }
+
+    #[test]
+    fn testUnsafeCodeLint() {
+        let projectDir = std::env::temp_dir().join(format!("lints_{}", utils::getTimestamp()));
+        let _ = std::fs::remove_dir_all(&projectDir);
+        let files = [
+            (
+                "Cargo.toml",
+                "[package]\nname = \"root\"\n\n[lints]\nworkspace = true\n\n[workspace.lints.rust]\nunsafe_code = \"deny\"\n",
+            ),
+            ("src/lib.rs", "#![forbid(unsafe_code)]\n"),
+            ("src/main.rs", "fn main() {}\n"),
+            (
+                "inherited/Cargo.toml",
+                "[package]\nname = \"inherited\"\n\n[lints]\nworkspace = true\n",
+            ),
+            ("inherited/src/lib.rs", ""),
+            (
+                "forbidden/Cargo.toml",
+                "[package]\nname = \"forbidden\"\n\n[lints.rust]\nunsafe_code = { level = \"forbid\", priority = 1 }\n",
+            ),
+            ("forbidden/src/lib.rs", "#![allow(unsafe_code)]\n"),
+            ("bins/Cargo.toml", "[package]\nname = \"bins\"\n"),
+            ("bins/src/lib.rs", "#![forbid(unsafe_code)]\n"),
+            ("bins/src/bin/tool.rs", "#![warn(unsafe_code)]\n"),
+        ];
+        for (file, content) in files {
+            let path = projectDir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let level = |dir: &str| unsafeCodeLint(&projectDir, &projectDir.join(dir));
+        // The binary gets the level of the workspace.
+        assert_eq!(level(""), UnsafeCodeLint::Deny);
+        assert_eq!(level("inherited"), UnsafeCodeLint::Deny);
+        assert_eq!(level("forbidden"), UnsafeCodeLint::Forbid);
+        assert_eq!(level("bins"), UnsafeCodeLint::Warn);
+        assert_eq!(level("missing"), UnsafeCodeLint::Allow);
+        std::fs::remove_dir_all(&projectDir).unwrap();
+    }
+}
@@ -0,0 +1,1069 @@
+//! Native analysis of Rust source code.
+//!
+//! Every `.rs` file is parsed with `syn`, so comments, string literals and
+//! identifiers that merely contain the word `unsafe` are never counted.
+//! Code inside macro invocations is opaque to the parser and is not analyzed.
+
+pub mod category;
+pub mod limits;
+pub mod lines;
+pub mod lints;
+pub mod safety;
+pub mod site;
+pub mod workspace;
+
+use category::Category;
+use limits::AnalysisLimits;
+use lines::{LineCounts, LineKind};
+use lints::UnsafeCodeLint;
+use site::{ProjectUnsafeSite, UnsafeKind, UnsafeSite};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use syn::{
+    spanned::Spanned,
+    visit::{self, Visit},
+};
+
+/// Directories that never contain source code of the project itself.
+const IGNORED_DIRS: [&str; 2] = [".git", "target"];
+
+/// The unsafe constructs found in one or more source files.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, sqlx::FromRow,
+)]
+pub struct UnsafeUsage {
+    pub unsafe_blocks: i32,
+    pub unsafe_functions: i32,
+    pub unsafe_impls: i32,
+    pub unsafe_traits: i32,
+    pub extern_blocks: i32,
+    /// Items exported with `#[no_mangle]` or `#[export_name]`.
+    pub no_mangle_items: i32,
+    /// Lines spanned by unsafe blocks. Nested blocks are counted once.
+    pub unsafe_block_lines: i32,
+    /// Unsafe blocks with a `// SAFETY:` comment and unsafe functions
+    /// with a `# Safety` doc section.
+    pub documented_unsafe: i32,
+    pub undocumented_unsafe: i32,
+}
+
+impl UnsafeUsage {
+    /// The number of places where the `unsafe` keyword is used. The unsafe lines of the
+    /// stats are the `unsafe_block_lines` instead.
+    pub fn total(&self) -> i32 {
+        return self.unsafe_blocks + self.unsafe_functions + self.unsafe_impls + self.unsafe_traits;
+    }
+
+    fn add(&mut self, other: &UnsafeUsage) {
+        self.unsafe_blocks += other.unsafe_blocks;
+        self.unsafe_functions += other.unsafe_functions;
+        self.unsafe_impls += other.unsafe_impls;
+        self.unsafe_traits += other.unsafe_traits;
+        self.extern_blocks += other.extern_blocks;
+        self.no_mangle_items += other.no_mangle_items;
+        self.unsafe_block_lines += other.unsafe_block_lines;
+        self.documented_unsafe += other.documented_unsafe;
+        self.undocumented_unsafe += other.undocumented_unsafe;
+    }
+}
+
+/// The line counts and unsafe usage of one category of code.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CategoryStats {
+    #[serde(flatten)]
+    pub line_counts: LineCounts,
+    #[serde(flatten)]
+    pub unsafe_usage: UnsafeUsage,
+}
+
+impl CategoryStats {
+    fn add(&mut self, other: &CategoryStats) {
+        self.line_counts.add(&other.line_counts);
+        self.unsafe_usage.add(&other.unsafe_usage);
+    }
+}
+
+/// The result of analyzing all the Rust files of a project.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProjectAnalysis {
+    /// The totals of all the categories.
+    pub unsafe_usage: UnsafeUsage,
+    pub line_counts: LineCounts,
+    pub categories: BTreeMap<Category, CategoryStats>,
+    pub rust_files: i32,
+    /// Files that `syn` failed to parse. They are excluded from the unsafe counts.
+    pub unparsable_files: i32,
+    /// The crates of the project, with a single entry for non workspace projects.
+    pub crates: Vec<CrateAnalysis>,
+    /// Every unsafe construct of the project. Empty for the crate analyses.
+    pub unsafe_sites: Vec<ProjectUnsafeSite>,
+    /// The analyzed commit, if the project is a git repository.
+    pub commit_sha: Option<String>,
+    pub commit_branch: Option<String>,
+    /// The committer date, in RFC 3339.
+    pub commit_date: Option<String>,
+    /// The level of the `unsafe_code` lint, the weakest of all the crates.
+    pub unsafe_code_lint: UnsafeCodeLint,
+}
+
+/// The analysis of one crate of a project.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CrateAnalysis {
+    pub name: String,
+    /// The crate directory, relative to the project root.
+    pub path: String,
+    pub analysis: ProjectAnalysis,
+}
+
+impl ProjectAnalysis {
+    /// The code that ships, i.e. library and binary code that is not test code.
+    pub fn production(&self) -> CategoryStats {
+        return self
+            .categories
+            .get(&Category::Source)
+            .copied()
+            .unwrap_or_default();
+    }
+
+    fn add(&mut self, category: Category, stats: &CategoryStats) {
+        self.line_counts.add(&stats.line_counts);
+        self.unsafe_usage.add(&stats.unsafe_usage);
+        self.categories.entry(category).or_default().add(stats);
+    }
+}
+
+/// The result of analyzing a single source file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceAnalysis {
+    pub unsafe_usage: UnsafeUsage,
+    /// The unsafe usage in `#[cfg(test)]` and `#[test]` items.
+    pub test_unsafe_usage: UnsafeUsage,
+    /// The 1-based, inclusive line ranges of the test items.
+    pub test_line_ranges: Vec<(usize, usize)>,
+    /// Out of line modules declared with `#[cfg(test)] mod name;`.
+    pub test_modules: Vec<String>,
+    pub unsafe_sites: Vec<UnsafeSite>,
+    /// The unsafe sites in `#[cfg(test)]` and `#[test]` items.
+    pub test_unsafe_sites: Vec<UnsafeSite>,
+}
+
+#[derive(Default)]
+struct UnsafeVisitor<'a> {
+    sourceLines: Vec<&'a str>,
+    analysis: SourceAnalysis,
+    unsafeBlockDepth: u32,
+    testDepth: u32,
+    itemPath: Vec<String>,
+}
+
+impl UnsafeVisitor<'_> {
+    fn usage(&mut self) -> &mut UnsafeUsage {
+        if self.testDepth > 0 {
+            return &mut self.analysis.test_unsafe_usage;
+        }
+        return &mut self.analysis.unsafe_usage;
+    }
+
+    /// `documented` is `None` for the kinds that need no safety justification.
+    fn addSite(&mut self, kind: UnsafeKind, span: proc_macro2::Span, documented: Option<bool>) {
+        match documented {
+            Some(true) => self.usage().documented_unsafe += 1,
+            Some(false) => self.usage().undocumented_unsafe += 1,
+            None => {}
+        }
+        let site = UnsafeSite {
+            kind,
+            start_line: span.start().line as i32,
+            end_line: span.end().line as i32,
+            item_path: self.itemPath.join("::"),
+            documented,
+        };
+        if self.testDepth > 0 {
+            self.analysis.test_unsafe_sites.push(site);
+        } else {
+            self.analysis.unsafe_sites.push(site);
+        }
+    }
+
+    fn visitFunction(
+        &mut self,
+        attributes: &[syn::Attribute],
+        signature: &syn::Signature,
+        span: proc_macro2::Span,
+    ) {
+        if signature.unsafety.is_some() {
+            self.usage().unsafe_functions += 1;
+            let documented = safety::hasSafetySection(attributes);
+            self.addSite(UnsafeKind::UnsafeFunction, span, Some(documented));
+        }
+    }
+
+    fn visitItem(&mut self, node: &syn::Item) {
+        let name = itemName(node);
+        if let Some(name) = &name {
+            self.itemPath.push(name.clone());
+        }
+        visit::visit_item(self, node);
+        if name.is_some() {
+            self.itemPath.pop();
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UnsafeVisitor<'_> {
+    fn visit_item(&mut self, node: &'ast syn::Item) {
+        if !itemAttributes(node).iter().any(isTestAttribute) {
+            self.visitItem(node);
+            return;
+        }
+
+        if self.testDepth == 0 {
+            let span = node.span();
+            self.analysis
+                .test_line_ranges
+                .push((span.start().line, span.end().line));
+        }
+        if let syn::Item::Mod(module) = node {
+            if module.content.is_none() && self.testDepth == 0 {
+                self.analysis.test_modules.push(module.ident.to_string());
+            }
+        }
+        self.testDepth += 1;
+        self.visitItem(node);
+        self.testDepth -= 1;
+    }
+
+    fn visit_expr_unsafe(&mut self, node: &'ast syn::ExprUnsafe) {
+        let span = node.span();
+        self.usage().unsafe_blocks += 1;
+        if self.unsafeBlockDepth == 0 {
+            self.usage().unsafe_block_lines += (span.end().line - span.start().line + 1) as i32;
+        }
+        let documented = safety::hasSafetyComment(&self.sourceLines, span.start());
+        self.addSite(UnsafeKind::UnsafeBlock, span, Some(documented));
+        self.unsafeBlockDepth += 1;
+        visit::visit_expr_unsafe(self, node);
+        self.unsafeBlockDepth -= 1;
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        self.visitFunction(&node.attrs, &node.sig, node.span());
+        visit::visit_item_fn(self, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        self.itemPath.push(node.sig.ident.to_string());
+        self.visitFunction(&node.attrs, &node.sig, node.span());
+        visit::visit_impl_item_fn(self, node);
+        self.itemPath.pop();
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast syn::TraitItemFn) {
+        self.itemPath.push(node.sig.ident.to_string());
+        self.visitFunction(&node.attrs, &node.sig, node.span());
+        visit::visit_trait_item_fn(self, node);
+        self.itemPath.pop();
+    }
+
+    fn visit_foreign_item_fn(&mut self, node: &'ast syn::ForeignItemFn) {
+        self.itemPath.push(node.sig.ident.to_string());
+        self.visitFunction(&node.attrs, &node.sig, node.span());
+        visit::visit_foreign_item_fn(self, node);
+        self.itemPath.pop();
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        if node.unsafety.is_some() {
+            self.usage().unsafe_impls += 1;
+            self.addSite(UnsafeKind::UnsafeImpl, node.span(), None);
+        }
+        visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        if node.unsafety.is_some() {
+            self.usage().unsafe_traits += 1;
+            self.addSite(UnsafeKind::UnsafeTrait, node.span(), None);
+        }
+        visit::visit_item_trait(self, node);
+    }
+
+    fn visit_item_foreign_mod(&mut self, node: &'ast syn::ItemForeignMod) {
+        self.usage().extern_blocks += 1;
+        self.addSite(UnsafeKind::ExternBlock, node.span(), None);
+        visit::visit_item_foreign_mod(self, node);
+    }
+
+    fn visit_attribute(&mut self, node: &'ast syn::Attribute) {
+        if isExportAttribute(&node.meta) {
+            self.usage().no_mangle_items += 1;
+            self.addSite(UnsafeKind::NoMangleItem, node.span(), None);
+        }
+        visit::visit_attribute(self, node);
+    }
+}
+
+/// The name an item contributes to the item path of the unsafe sites in it.
+fn itemName(item: &syn::Item) -> Option<String> {
+    let ident = match item {
+        syn::Item::Const(v) => &v.ident,
+        syn::Item::Enum(v) => &v.ident,
+        syn::Item::Fn(v) => &v.sig.ident,
+        syn::Item::Impl(v) => return typeName(&v.self_ty),
+        syn::Item::Mod(v) => &v.ident,
+        syn::Item::Static(v) => &v.ident,
+        syn::Item::Struct(v) => &v.ident,
+        syn::Item::Trait(v) => &v.ident,
+        syn::Item::Union(v) => &v.ident,
+        _ => return None,
+    };
+    return Some(ident.to_string());
+}
+
+fn typeName(ty: &syn::Type) -> Option<String> {
+    return match ty {
+        syn::Type::Path(v) => v.path.segments.last().map(|v| v.ident.to_string()),
+        syn::Type::Reference(v) => typeName(&v.elem),
+        syn::Type::Ptr(v) => typeName(&v.elem),
+        syn::Type::Slice(v) => typeName(&v.elem),
+        syn::Type::Array(v) => typeName(&v.elem),
+        syn::Type::Paren(v) => typeName(&v.elem),
+        _ => None,
+    };
+}
+
+fn itemAttributes(item: &syn::Item) -> &[syn::Attribute] {
+    return match item {
+        syn::Item::Const(v) => &v.attrs,
+        syn::Item::Enum(v) => &v.attrs,
+        syn::Item::ExternCrate(v) => &v.attrs,
+        syn::Item::Fn(v) => &v.attrs,
+        syn::Item::ForeignMod(v) => &v.attrs,
+        syn::Item::Impl(v) => &v.attrs,
+        syn::Item::Macro(v) => &v.attrs,
+        syn::Item::Mod(v) => &v.attrs,
+        syn::Item::Static(v) => &v.attrs,
+        syn::Item::Struct(v) => &v.attrs,
+        syn::Item::Trait(v) => &v.attrs,
+        syn::Item::TraitAlias(v) => &v.attrs,
+        syn::Item::Type(v) => &v.attrs,
+        syn::Item::Union(v) => &v.attrs,
+        syn::Item::Use(v) => &v.attrs,
+        _ => &[],
+    };
+}
+
+/// Matches `#[test]`, `#[tokio::test]` and the like, `#[cfg(test)]` and `#[cfg(all(test, ...))]`.
+fn isTestAttribute(attribute: &syn::Attribute) -> bool {
+    let path = attribute.path();
+    if path.is_ident("cfg") {
+        return attribute
+            .parse_args::<syn::Meta>()
+            .is_ok_and(|v| isTestCfg(&v));
+    }
+    return path.segments.last().is_some_and(|v| v.ident == "test");
+}
+
+fn isTestCfg(meta: &syn::Meta) -> bool {
+    if meta.path().is_ident("test") {
+        return true;
+    }
+    if let syn::Meta::List(list) = meta {
+        if list.path.is_ident("all") {
+            let nested = list.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+            );
+            return nested.is_ok_and(|v| v.iter().any(isTestCfg));
+        }
+    }
+    return false;
+}
+
+/// Matches `#[no_mangle]`, `#[export_name = "..."]` and their `#[unsafe(...)]` forms.
+fn isExportAttribute(meta: &syn::Meta) -> bool {
+    let path = meta.path();
+    if path.is_ident("no_mangle") || path.is_ident("export_name") {
+        return true;
+    }
+    if let syn::Meta::List(list) = meta {
+        if list.path.is_ident("unsafe") {
+            if let Ok(inner) = list.parse_args::<syn::Meta>() {
+                return isExportAttribute(&inner);
+            }
+        }
+    }
+    return false;
+}
+
+pub fn analyzeSource(source: &str) -> Result<SourceAnalysis, syn::Error> {
+    let file = syn::parse_file(source)?;
+    let mut visitor = UnsafeVisitor {
+        sourceLines: source.lines().collect(),
+        ..Default::default()
+    };
+    // A `#![cfg(test)]` file is test code in its entirety.
+    if file.attrs.iter().any(isTestAttribute) {
+        visitor.testDepth += 1;
+        visitor
+            .analysis
+            .test_line_ranges
+            .push((1, source.lines().count()));
+    }
+    visitor.visit_file(&file);
+    return Ok(visitor.analysis);
+}
+
+struct SourceFile {
+    path: PathBuf,
+    crateIndex: Option<usize>,
+    category: Category,
+    lineKinds: Vec<LineKind>,
+    analysis: Option<SourceAnalysis>,
+}
+
+#[derive(Debug)]
+pub enum AnalysisError {
+    Io(std::io::Error),
+    /// The deadline of the limits passed.
+    TimedOut,
+    /// The project has more `.rs` files than the limits allow.
+    TooManyFiles {
+        limit: usize,
+    },
+    /// The checked-out files take more bytes than the limits allow.
+    TooLarge {
+        limit: u64,
+    },
+}
+
+impl std::fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            AnalysisError::Io(e) => write!(f, "{e}"),
+            AnalysisError::TimedOut => write!(f, "The analysis timed out"),
+            AnalysisError::TooManyFiles { limit } => {
+                write!(f, "The project has more than {limit} Rust files")
+            }
+            AnalysisError::TooLarge { limit } => {
+                write!(f, "The project files take more than {limit} bytes")
+            }
+        };
+    }
+}
+
+impl std::error::Error for AnalysisError {}
+
+impl From<std::io::Error> for AnalysisError {
+    fn from(e: std::io::Error) -> Self {
+        return AnalysisError::Io(e);
+    }
+}
+
+/// Analyzes every `.rs` file under `projectDir`.
+pub fn analyzeProject(projectDir: &Path) -> Result<ProjectAnalysis, AnalysisError> {
+    return analyzeProjectWithLimits(projectDir, &AnalysisLimits::default());
+}
+
+/// Like `analyzeProject`, but gives up once a limit is exceeded.
+pub fn analyzeProjectWithLimits(
+    projectDir: &Path,
+    limits: &AnalysisLimits,
+) -> Result<ProjectAnalysis, AnalysisError> {
+    let mut paths = Vec::new();
+    collectRustFiles(projectDir, &mut paths, &mut 0, limits)?;
+    paths.sort();
+
+    let crateManifests = workspace::workspaceCrates(projectDir, limits)?;
+    let mut crates: Vec<CrateAnalysis> = crateManifests
+        .iter()
+        .map(|v| CrateAnalysis {
+            name: v.name.clone(),
+            path: v.path.to_string_lossy().into_owned(),
+            analysis: ProjectAnalysis {
+                unsafe_code_lint: lints::unsafeCodeLint(projectDir, &projectDir.join(&v.path)),
+                ..Default::default()
+            },
+        })
+        .collect();
+
+    let mut analysis = ProjectAnalysis {
+        unsafe_code_lint: crates
+            .iter()
+            .map(|v| v.analysis.unsafe_code_lint)
+            .min()
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        if limits.isExpired() {
+            return Err(AnalysisError::TimedOut);
+        }
+        let crateIndex = workspace::owningCrate(projectDir, &crateManifests, &path);
+        // Non UTF-8 files cannot be valid Rust, so treat them like parse failures.
+        let source = std::fs::read_to_string(&path).ok();
+        let sourceAnalysis = source.as_deref().and_then(|v| analyzeSource(v).ok());
+        let unparsable = sourceAnalysis.is_none() as i32;
+        analysis.rust_files += 1;
+        analysis.unparsable_files += unparsable;
+        if let Some(i) = crateIndex {
+            crates[i].analysis.rust_files += 1;
+            crates[i].analysis.unparsable_files += unparsable;
+        }
+        files.push(SourceFile {
+            crateIndex,
+            category: Category::fromPath(projectDir, &path),
+            lineKinds: lines::classifyLines(source.as_deref().unwrap_or_default()),
+            analysis: sourceAnalysis,
+            path,
+        });
+    }
+
+    let testModulePaths = testModulePaths(&files);
+    for file in files {
+        let mut category = file.category;
+        if category == Category::Source && testModulePaths.iter().any(|v| file.path.starts_with(v))
+        {
+            category = Category::Tests;
+        }
+        let sourceAnalysis = file.analysis.unwrap_or_default();
+
+        let mut stats = CategoryStats {
+            line_counts: LineCounts::fromKinds(&file.lineKinds),
+            unsafe_usage: sourceAnalysis.unsafe_usage,
+        };
+        let mut testStats = CategoryStats {
+            line_counts: LineCounts::default(),
+            unsafe_usage: sourceAnalysis.test_unsafe_usage,
+        };
+        if category == Category::Source {
+            let mut sourceLines = Vec::with_capacity(file.lineKinds.len());
+            let mut testLines = Vec::new();
+            for (i, kind) in file.lineKinds.iter().enumerate() {
+                let line = i + 1;
+                let isTestLine = sourceAnalysis
+                    .test_line_ranges
+                    .iter()
+                    .any(|(start, end)| (*start..=*end).contains(&line));
+                if isTestLine {
+                    testLines.push(*kind);
+                } else {
+                    sourceLines.push(*kind);
+                }
+            }
+            stats.line_counts = LineCounts::fromKinds(&sourceLines);
+            testStats.line_counts = LineCounts::fromKinds(&testLines);
+        } else {
+            stats.unsafe_usage.add(&testStats.unsafe_usage);
+            testStats = CategoryStats::default();
+        }
+
+        let relativePath = file
+            .path
+            .strip_prefix(projectDir)
+            .unwrap_or(&file.path)
+            .to_string_lossy()
+            .into_owned();
+        let testSiteCategory = if category == Category::Source {
+            Category::Tests
+        } else {
+            category
+        };
+        for (category, sites) in [
+            (category, sourceAnalysis.unsafe_sites),
+            (testSiteCategory, sourceAnalysis.test_unsafe_sites),
+        ] {
+            analysis
+                .unsafe_sites
+                .extend(sites.into_iter().map(|site| ProjectUnsafeSite {
+                    file_path: relativePath.clone(),
+                    category,
+                    site,
+                }));
+        }
+
+        for (category, stats) in [(category, &stats), (Category::Tests, &testStats)] {
+            if *stats == CategoryStats::default() {
+                continue;
+            }
+            analysis.add(category, stats);
+            if let Some(i) = file.crateIndex {
+                crates[i].analysis.add(category, stats);
+            }
+        }
+    }
+    analysis.crates = crates;
+    analysis.unsafe_sites.sort_by(|a, b| {
+        return (&a.file_path, a.site.start_line).cmp(&(&b.file_path, b.site.start_line));
+    });
+    return Ok(analysis);
+}
+
+/// Resolves `#[cfg(test)] mod name;` declarations to the files and directories they load.
+fn testModulePaths(files: &[SourceFile]) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for file in files {
+        let Some(analysis) = &file.analysis else {
+            continue;
+        };
+        if analysis.test_modules.is_empty() {
+            continue;
+        }
+        let Some(parent) = file.path.parent() else {
+            continue;
+        };
+        let stem = file
+            .path
+            .file_stem()
+            .and_then(|v| v.to_str())
+            .unwrap_or_default();
+        let moduleDir = if matches!(stem, "lib" | "main" | "mod") {
+            parent.to_path_buf()
+        } else {
+            parent.join(stem)
+        };
+        for module in &analysis.test_modules {
+            paths.push(moduleDir.join(format!("{module}.rs")));
+            paths.push(moduleDir.join(module));
+        }
+    }
+    return paths;
+}
+
+/// Collects the `.rs` files under `dir`, while adding the size of every file to `bytes`.
+fn collectRustFiles(
+    dir: &Path,
+    files: &mut Vec<PathBuf>,
+    bytes: &mut u64,
+    limits: &AnalysisLimits,
+) -> Result<(), AnalysisError> {
+    if limits.isExpired() {
+        return Err(AnalysisError::TimedOut);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let fileType = entry.file_type()?;
+        if fileType.is_dir() {
+            let name = entry.file_name();
+            if IGNORED_DIRS.iter().any(|v| name == *v) {
+                continue;
+            }
+            collectRustFiles(&path, files, bytes, limits)?;
+            continue;
+        }
+        if !fileType.is_file() {
+            continue;
+        }
+        *bytes += entry.metadata()?.len();
+        if let Some(limit) = limits.maxRepositoryBytes.filter(|v| *bytes > *v) {
+            return Err(AnalysisError::TooLarge { limit });
+        }
+        if path.extension().is_some_and(|v| v == "rs") {
+            if let Some(limit) = limits.maxFiles.filter(|v| files.len() >= *v) {
+                return Err(AnalysisError::TooManyFiles { limit });
+            }
+            files.push(path);
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn testAnalyzeSourceCountsEveryKind() {
+        let source = r#"
+            unsafe trait Zeroable {}
+            unsafe impl Zeroable for u8 {}
+            unsafe fn read(p: *const u8) -> u8 { *p }
+            struct Foo;
+            impl Foo {
+                unsafe fn bar(&self) {}
+            }
+            trait Baz {
+                unsafe fn baz(&self);
+            }
+            fn main() {
+                let x = 1u8;
+                let y = unsafe { read(&x) };
+                unsafe {
+                    let _ = unsafe { read(&y) };
+                }
+            }
+        "#;
+        let usage = analyzeSource(source).unwrap().unsafe_usage;
+        assert_eq!(usage.unsafe_blocks, 3);
+        assert_eq!(usage.unsafe_functions, 3);
+        assert_eq!(usage.unsafe_impls, 1);
+        assert_eq!(usage.unsafe_traits, 1);
+        assert_eq!(usage.total(), 8);
+    }
+
+    #[test]
+    fn testAnalyzeSourceCountsFfi() {
+        let source = r#"
+            extern "C" {
+                fn abs(input: i32) -> i32;
+            }
+            #[no_mangle]
+            pub extern "C" fn exported() {}
+            #[export_name = "renamed"]
+            pub static VALUE: i32 = 1;
+            #[unsafe(no_mangle)]
+            pub extern "C" fn exported_2024() {}
+            #[inline]
+            fn not_exported() {}
+        "#;
+        let usage = analyzeSource(source).unwrap().unsafe_usage;
+        assert_eq!(usage.extern_blocks, 1);
+        assert_eq!(usage.no_mangle_items, 3);
+        assert_eq!(usage.total(), 0);
+    }
+
+    #[test]
+    fn testAnalyzeSourceCountsUnsafeBlockLines() {
+        let source = r#"
+fn main() {
+    let x = unsafe { foo() };
+    unsafe {
+        bar();
+        unsafe {
+            baz();
+        }
+    }
+}
+"#;
+        let usage = analyzeSource(source).unwrap().unsafe_usage;
+        assert_eq!(usage.unsafe_blocks, 3);
+        assert_eq!(usage.unsafe_block_lines, 7);
+    }
+
+    #[test]
+    fn testAnalyzeSourceIgnoresFalsePositives() {
+        let source = r#"
+            //! Crate docs mentioning unsafe code.
+            #![forbid(unsafe_code)]
+
+            /// Never call this in an unsafe way.
+            fn unsafe_cell() -> &'static str {
+                // unsafe { this is a comment }
+                let unsafe_count = 1;
+                let _ = unsafe_count;
+                /* unsafe { block comment } */
+                return "unsafe { string literal }";
+            }
+            type Callback = unsafe fn();
+        "#;
+        let usage = analyzeSource(source).unwrap().unsafe_usage;
+        assert_eq!(usage, UnsafeUsage::default());
+    }
+
+    #[test]
+    fn testAnalyzeSourceWithTrailingComment() {
+        let source =
+            "fn main() { unsafe { std::hint::unreachable_unchecked() } // SAFETY: never reached\n}";
+        let usage = analyzeSource(source).unwrap().unsafe_usage;
+        assert_eq!(usage.unsafe_blocks, 1);
+    }
+
+    #[test]
+    fn testAnalyzeSourceRecordsSites() {
+        let source = r#"mod imp {
+    pub struct Foo;
+
+    impl Foo {
+        pub unsafe fn bar(&self) {
+            unsafe {
+                baz();
+            }
+        }
+    }
+
+    unsafe impl Send for Foo {}
+}
+
+extern "C" {
+    fn baz();
+}
+
+#[no_mangle]
+pub extern "C" fn exported() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test() {
+        unsafe { super::baz() };
+    }
+}
+"#;
+        let analysis = analyzeSource(source).unwrap();
+        let site = |kind, start_line, end_line, item_path: &str, documented| UnsafeSite {
+            kind,
+            start_line,
+            end_line,
+            item_path: item_path.to_owned(),
+            documented,
+        };
+        assert_eq!(
+            analysis.unsafe_sites,
+            vec![
+                site(
+                    UnsafeKind::UnsafeFunction,
+                    5,
+                    9,
+                    "imp::Foo::bar",
+                    Some(false)
+                ),
+                site(UnsafeKind::UnsafeBlock, 6, 8, "imp::Foo::bar", Some(false)),
+                site(UnsafeKind::UnsafeImpl, 12, 12, "imp::Foo", None),
+                site(UnsafeKind::ExternBlock, 15, 17, "", None),
+                site(UnsafeKind::NoMangleItem, 19, 19, "exported", None),
+            ]
+        );
+        assert_eq!(
+            analysis.test_unsafe_sites,
+            vec![site(
+                UnsafeKind::UnsafeBlock,
+                26,
+                26,
+                "tests::test",
+                Some(false)
+            )]
+        );
+    }
+
+    #[test]
+    fn testAnalyzeSourceCountsSafetyDocumentation() {
+        let source = r#"
+/// # Safety
+///
+/// `p` must be valid for reads.
+unsafe fn read(p: *const u8) -> u8 {
+    // SAFETY: The caller guarantees that `p` is valid.
+    unsafe { *p }
+}
+
+unsafe fn undocumented(p: *const u8) -> u8 {
+    unsafe { *p }
+}
+
+#[test]
+fn test() {
+    let x = 1;
+    // SAFETY: `x` outlives the call.
+    let _ = unsafe { read(&x) };
+}
+"#;
+        let analysis = analyzeSource(source).unwrap();
+        assert_eq!(analysis.unsafe_usage.documented_unsafe, 2);
+        assert_eq!(analysis.unsafe_usage.undocumented_unsafe, 2);
+        assert_eq!(analysis.test_unsafe_usage.documented_unsafe, 1);
+        assert_eq!(analysis.test_unsafe_usage.undocumented_unsafe, 0);
+    }
+
+    fn createProjectDir(name: &str) -> PathBuf {
+        let projectDir =
+            std::env::temp_dir().join(format!("analysis_{name}_{}", utils::getTimestamp()));
+        let _ = std::fs::remove_dir_all(&projectDir);
+        return projectDir;
+    }
+
+    #[test]
+    fn testAnalyzeSourceSeparatesTestCode() {
+        let source = r#"
+unsafe fn foo() {}
+
+#[cfg(test)]
+mod tests {
+    unsafe fn bar() {}
+
+    #[test]
+    fn baz() {
+        unsafe { bar() };
+    }
+}
+
+#[cfg(all(test, feature = "foo"))]
+mod more_tests;
+
+#[cfg(not(test))]
+unsafe fn not_test() {}
+"#;
+        let analysis = analyzeSource(source).unwrap();
+        assert_eq!(analysis.unsafe_usage.unsafe_functions, 2);
+        assert_eq!(analysis.test_unsafe_usage.unsafe_functions, 1);
+        assert_eq!(analysis.test_unsafe_usage.unsafe_blocks, 1);
+        assert_eq!(analysis.test_line_ranges, vec![(4, 12), (14, 15)]);
+        assert_eq!(analysis.test_modules, vec!["more_tests"]);
+
+        let analysis = analyzeSource("#![cfg(test)]\nunsafe fn foo() {}\n").unwrap();
+        assert_eq!(analysis.unsafe_usage.total(), 0);
+        assert_eq!(analysis.test_unsafe_usage.unsafe_functions, 1);
+        assert_eq!(analysis.test_line_ranges, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn testAnalyzeProjectCategories() {
+        let projectDir = createProjectDir("categories");
+        for dir in ["src/foo", "tests", "benches", "examples", "vendor/bar/src"] {
+            std::fs::create_dir_all(projectDir.join(dir)).unwrap();
+        }
+        let files = [
+            ("Cargo.toml", ""),
+            ("build.rs", "unsafe fn build() {}\n"),
+            (
+                "src/lib.rs",
+                "unsafe fn lib() {}\n#[cfg(test)]\nmod tests {\n    unsafe fn test() {}\n}\n",
+            ),
+            ("src/foo.rs", "#[cfg(test)]\nmod tests;\n"),
+            ("src/foo/tests.rs", "unsafe fn test() {}\n"),
+            ("tests/it.rs", "unsafe fn test() {}\n"),
+            ("benches/bench.rs", "unsafe fn bench() {}\n"),
+            ("examples/example.rs", "unsafe fn example() {}\n"),
+            ("vendor/bar/src/lib.rs", "unsafe fn vendored() {}\n"),
+        ];
+        for (file, source) in files {
+            std::fs::write(projectDir.join(file), source).unwrap();
+        }
+
+        let analysis = analyzeProject(&projectDir).unwrap();
+        std::fs::remove_dir_all(&projectDir).unwrap();
+
+        let unsafeFunctions =
+            |category| analysis.categories[&category].unsafe_usage.unsafe_functions;
+        assert_eq!(unsafeFunctions(Category::Source), 1);
+        assert_eq!(unsafeFunctions(Category::Tests), 3);
+        assert_eq!(unsafeFunctions(Category::Benches), 1);
+        assert_eq!(unsafeFunctions(Category::Examples), 1);
+        assert_eq!(unsafeFunctions(Category::BuildScripts), 1);
+        assert_eq!(unsafeFunctions(Category::Vendored), 1);
+        assert_eq!(analysis.unsafe_usage.unsafe_functions, 8);
+
+        // Only the first line of lib.rs is production code.
+        assert_eq!(analysis.production().line_counts.code_lines, 1);
+        let testLines = analysis.categories[&Category::Tests].line_counts;
+        assert_eq!(testLines.code_lines, 4 + 2 + 1 + 1);
+
+        let sites: Vec<(&str, i32, Category)> = analysis
+            .unsafe_sites
+            .iter()
+            .map(|v| (v.file_path.as_str(), v.site.start_line, v.category))
+            .collect();
+        assert_eq!(
+            sites,
+            vec![
+                ("benches/bench.rs", 1, Category::Benches),
+                ("build.rs", 1, Category::BuildScripts),
+                ("examples/example.rs", 1, Category::Examples),
+                ("src/foo/tests.rs", 1, Category::Tests),
+                ("src/lib.rs", 1, Category::Source),
+                ("src/lib.rs", 4, Category::Tests),
+                ("tests/it.rs", 1, Category::Tests),
+                ("vendor/bar/src/lib.rs", 1, Category::Vendored),
+            ]
+        );
+    }
+
+    #[test]
+    fn testAnalyzeProjectCrates() {
+        let projectDir = createProjectDir("crates");
+        for dir in ["src", "crates/foo/src", "crates/foo/tests"] {
+            std::fs::create_dir_all(projectDir.join(dir)).unwrap();
+        }
+        let files = [
+            (
+                "Cargo.toml",
+                "[package]\nname = \"root\"\n[workspace]\nmembers = [\"crates/*\"]\n",
+            ),
+            ("src/lib.rs", "unsafe fn root() {}\n"),
+            (
+                "crates/foo/Cargo.toml",
+                "[package]\nname = \"foo\"\n[lints.rust]\nunsafe_code = \"forbid\"\n",
+            ),
+            ("crates/foo/src/lib.rs", "unsafe impl Send for Foo {}\n"),
+            ("crates/foo/tests/it.rs", "unsafe fn test() {}\n"),
+        ];
+        for (file, source) in files {
+            std::fs::write(projectDir.join(file), source).unwrap();
+        }
+
+        let analysis = analyzeProject(&projectDir).unwrap();
+        std::fs::remove_dir_all(&projectDir).unwrap();
+
+        assert_eq!(analysis.unsafe_usage.total(), 3);
+        assert_eq!(analysis.crates.len(), 2);
+        let root = &analysis.crates[0];
+        assert_eq!((root.name.as_str(), root.path.as_str()), ("root", ""));
+        assert_eq!(root.analysis.rust_files, 1);
+        assert_eq!(root.analysis.unsafe_usage.unsafe_functions, 1);
+        let foo = &analysis.crates[1];
+        assert_eq!(
+            (foo.name.as_str(), foo.path.as_str()),
+            ("foo", "crates/foo")
+        );
+        assert_eq!(foo.analysis.rust_files, 2);
+        assert_eq!(foo.analysis.unsafe_usage.total(), 2);
+        assert_eq!(foo.analysis.production().unsafe_usage.unsafe_impls, 1);
+        assert_eq!(foo.analysis.unsafe_code_lint, UnsafeCodeLint::Forbid);
+        assert_eq!(root.analysis.unsafe_code_lint, UnsafeCodeLint::Allow);
+        assert_eq!(analysis.unsafe_code_lint, UnsafeCodeLint::Allow);
+    }
+
+    #[test]
+    fn testAnalyzeProject() {
+        let projectDir = createProjectDir("project");
+        std::fs::create_dir_all(projectDir.join("src")).unwrap();
+        std::fs::create_dir_all(projectDir.join("target/debug")).unwrap();
+        std::fs::write(
+            projectDir.join("src/lib.rs"),
+            "// Foo.\nunsafe fn foo() {}\n",
+        )
+        .unwrap();
+        std::fs::write(projectDir.join("src/broken.rs"), "fn foo( {").unwrap();
+        std::fs::write(projectDir.join("src/notes.txt"), "unsafe { }").unwrap();
+        std::fs::write(
+            projectDir.join("target/debug/build.rs"),
+            "unsafe fn foo() {}",
+        )
+        .unwrap();
+
+        let analysis = analyzeProject(&projectDir).unwrap();
+        let limits = |maxFiles, deadline| AnalysisLimits {
+            deadline,
+            maxFiles,
+            ..Default::default()
+        };
+        let tooManyFiles = analyzeProjectWithLimits(&projectDir, &limits(Some(1), None));
+        let withinLimits = analyzeProjectWithLimits(&projectDir, &limits(Some(2), None));
+        let timedOut =
+            analyzeProjectWithLimits(&projectDir, &limits(None, Some(std::time::Instant::now())));
+        let tooLarge = AnalysisLimits {
+            maxRepositoryBytes: Some(16),
+            ..Default::default()
+        };
+        let tooLarge = analyzeProjectWithLimits(&projectDir, &tooLarge);
+        std::fs::remove_dir_all(&projectDir).unwrap();
+
+        assert!(matches!(
+            tooManyFiles,
+            Err(AnalysisError::TooManyFiles { limit: 1 })
+        ));
+        assert_eq!(withinLimits.unwrap().rust_files, 2);
+        assert!(matches!(timedOut, Err(AnalysisError::TimedOut)));
+        assert!(matches!(
+            tooLarge,
+            Err(AnalysisError::TooLarge { limit: 16 })
+        ));
+        assert_eq!(analysis.rust_files, 2);
+        assert_eq!(analysis.unparsable_files, 1);
+        assert_eq!(analysis.unsafe_usage.unsafe_functions, 1);
+        assert_eq!(analysis.unsafe_usage.total(), 1);
+        assert_eq!(analysis.line_counts.code_lines, 2);
+        assert_eq!(analysis.line_counts.comment_lines, 1);
+    }
+}
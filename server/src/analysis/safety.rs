@@ -0,0 +1,114 @@
+//! Detection of the justifications that the Rust API guidelines ask for:
+//! a `// SAFETY:` comment before every unsafe block and a `# Safety` doc
+//! section on every unsafe function.
+
+use proc_macro2::LineColumn;
+
+/// Whether the unsafe block starting at `start` is justified by a `SAFETY:`
+/// comment, either on its first line or in the comments right above it.
+pub fn hasSafetyComment(lines: &[&str], start: LineColumn) -> bool {
+    let Some(index) = start.line.checked_sub(1) else {
+        return false;
+    };
+    if lines.get(index).is_some_and(|v| isSafetyComment(v)) {
+        return true;
+    }
+    for line in lines[..index.min(lines.len())].iter().rev() {
+        let line = line.trim();
+        let isComment = line.starts_with("//") || line.starts_with("/*") || line.starts_with('*');
+        if isComment && isSafetyComment(line) {
+            return true;
+        }
+        // Attributes may sit between the comment and the statement.
+        if !isComment && !line.starts_with("#[") {
+            return false;
+        }
+    }
+    return false;
+}
+
+fn isSafetyComment(line: &str) -> bool {
+    let Some(start) = line.find("//").or_else(|| line.find("/*")) else {
+        return line.trim_start().starts_with('*') && line.to_uppercase().contains("SAFETY:");
+    };
+    return line[start..].to_uppercase().contains("SAFETY:");
+}
+
+/// Whether the doc comments in `attributes` have a `# Safety` section.
+pub fn hasSafetySection(attributes: &[syn::Attribute]) -> bool {
+    return attributes
+        .iter()
+        .filter(|v| v.path().is_ident("doc"))
+        .filter_map(|v| match &v.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) => Some(doc.value()),
+            _ => None,
+        })
+        .any(|doc| {
+            return doc.lines().any(|line| {
+                let line = line.trim();
+                return line.starts_with('#')
+                    && line
+                        .trim_start_matches('#')
+                        .trim()
+                        .eq_ignore_ascii_case("safety");
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(line: usize, column: usize) -> LineColumn {
+        return LineColumn { line, column };
+    }
+
+    #[test]
+    fn testHasSafetyComment() {
+        let source = "fn foo() {
+    // SAFETY: The pointer is valid.
+    // It is also aligned.
+    let x = unsafe { *p };
+
+    // The pointer is valid.
+    unsafe { *p };
+    let y = unsafe { *p }; // SAFETY: Still valid.
+    // SAFETY: Not about the next block.
+    let z = 1;
+    unsafe { *p };
+    /* Safety: valid */
+    #[allow(unused_unsafe)]
+    unsafe { *p };
+    let s = \"SAFETY:\"; unsafe { *p };
+}";
+        let lines: Vec<&str> = source.lines().collect();
+        assert!(hasSafetyComment(&lines, start(4, 12)));
+        assert!(!hasSafetyComment(&lines, start(7, 4)));
+        assert!(hasSafetyComment(&lines, start(8, 12)));
+        assert!(!hasSafetyComment(&lines, start(11, 4)));
+        assert!(hasSafetyComment(&lines, start(14, 4)));
+        assert!(!hasSafetyComment(&lines, start(15, 25)));
+    }
+
+    #[test]
+    fn testHasSafetySection() {
+        let function = |source: &str| syn::parse_str::<syn::ItemFn>(source).unwrap().attrs;
+        assert!(hasSafetySection(&function(
+            "/// Reads the value.\n///\n/// # Safety\n///\n/// `p` must be valid.\nunsafe fn foo(p: *const u8) {}"
+        )));
+        assert!(hasSafetySection(&function(
+            "#[doc = \"## Safety\"]\nunsafe fn foo() {}"
+        )));
+        assert!(!hasSafetySection(&function(
+            "/// Safety: `p` must be valid.\nunsafe fn foo(p: *const u8) {}"
+        )));
+        assert!(!hasSafetySection(&function("unsafe fn foo() {}")));
+    }
+}
@@ -0,0 +1,52 @@
+use super::category::Category;
+
+/// The kind of an unsafe construct.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsafeKind {
+    UnsafeBlock,
+    UnsafeFunction,
+    UnsafeImpl,
+    UnsafeTrait,
+    ExternBlock,
+    NoMangleItem,
+}
+
+impl UnsafeKind {
+    pub fn asStr(&self) -> &'static str {
+        return match self {
+            UnsafeKind::UnsafeBlock => "unsafe_block",
+            UnsafeKind::UnsafeFunction => "unsafe_function",
+            UnsafeKind::UnsafeImpl => "unsafe_impl",
+            UnsafeKind::UnsafeTrait => "unsafe_trait",
+            UnsafeKind::ExternBlock => "extern_block",
+            UnsafeKind::NoMangleItem => "no_mangle_item",
+        };
+    }
+}
+
+/// The location of an unsafe construct in a source file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UnsafeSite {
+    pub kind: UnsafeKind,
+    /// The 1-based, inclusive line range of the construct.
+    pub start_line: i32,
+    pub end_line: i32,
+    /// The enclosing items within the file, e.g. `imp::Foo::bar`.
+    pub item_path: String,
+    /// Whether an unsafe block or function has a safety justification.
+    /// `None` for the other kinds.
+    pub documented: Option<bool>,
+}
+
+/// An unsafe site of a project.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProjectUnsafeSite {
+    /// The file path, relative to the project root.
+    pub file_path: String,
+    pub category: Category,
+    #[serde(flatten)]
+    pub site: UnsafeSite,
+}
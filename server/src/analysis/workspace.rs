@@ -0,0 +1,198 @@
+use super::{limits::AnalysisLimits, AnalysisError};
+use std::path::{Component, Path, PathBuf};
+
+/// A crate of a project, as declared in its `Cargo.toml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateManifest {
+    pub name: String,
+    /// The crate directory, relative to the project root ("" for the root crate).
+    pub path: PathBuf,
+}
+
+pub(crate) fn readManifest(dir: &Path) -> Option<toml::Table> {
+    let content = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    return content.parse::<toml::Table>().ok();
+}
+
+fn packageName(manifest: &toml::Table) -> Option<String> {
+    let name = manifest.get("package")?.get("name")?.as_str()?;
+    return Some(name.to_owned());
+}
+
+fn stringArray(table: &toml::Table, key: &str) -> Vec<String> {
+    return table
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|v| {
+            v.iter()
+                .filter_map(|v| v.as_str().map(|v| v.to_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+}
+
+/// Whether a workspace member pattern stays inside the project.
+fn isRelativeMember(member: &str) -> bool {
+    return Path::new(member)
+        .components()
+        .all(|v| matches!(v, Component::Normal(_) | Component::CurDir));
+}
+
+/// Returns the crates of the project at `projectDir`: the workspace members,
+/// including the root package if there is one, or just the root package. Members
+/// that point outside of the project are ignored, and the glob matches count
+/// against the file limit.
+pub fn workspaceCrates(
+    projectDir: &Path,
+    limits: &AnalysisLimits,
+) -> Result<Vec<CrateManifest>, AnalysisError> {
+    let Some(rootManifest) = readManifest(projectDir) else {
+        return Ok(Vec::new());
+    };
+    let root = projectDir.canonicalize()?;
+
+    let mut crates = Vec::new();
+    if let Some(name) = packageName(&rootManifest) {
+        crates.push(CrateManifest {
+            name,
+            path: PathBuf::new(),
+        });
+    }
+
+    let Some(workspace) = rootManifest.get("workspace").and_then(|v| v.as_table()) else {
+        return Ok(crates);
+    };
+    let excluded: Vec<PathBuf> = stringArray(workspace, "exclude")
+        .iter()
+        .map(|v| projectDir.join(v))
+        .collect();
+    let mut matches = 0;
+    for member in stringArray(workspace, "members") {
+        if !isRelativeMember(&member) {
+            continue;
+        }
+        let pattern = projectDir.join(&member);
+        let Ok(paths) = glob::glob(&pattern.to_string_lossy()) else {
+            continue;
+        };
+        for dir in paths.flatten() {
+            if limits.isExpired() {
+                return Err(AnalysisError::TimedOut);
+            }
+            matches += 1;
+            if let Some(limit) = limits.maxFiles.filter(|v| matches > *v) {
+                return Err(AnalysisError::TooManyFiles { limit });
+            }
+            // A symlink may still lead out of the project.
+            if !dir.canonicalize().is_ok_and(|v| v.starts_with(&root)) {
+                continue;
+            }
+            if excluded.iter().any(|v| dir.starts_with(v)) {
+                continue;
+            }
+            let Some(name) = readManifest(&dir).as_ref().and_then(packageName) else {
+                continue;
+            };
+            let path = dir.strip_prefix(projectDir).unwrap_or(&dir).to_path_buf();
+            if crates.iter().all(|v| v.path != path) {
+                crates.push(CrateManifest { name, path });
+            }
+        }
+    }
+    crates.sort_by(|a, b| a.path.cmp(&b.path));
+    return Ok(crates);
+}
+
+/// Returns the index of the crate that owns `file`, i.e. the crate of the
+/// closest directory above it that has a `Cargo.toml`.
+pub fn owningCrate(projectDir: &Path, crates: &[CrateManifest], file: &Path) -> Option<usize> {
+    let relative = file.strip_prefix(projectDir).ok()?;
+    for dir in relative.ancestors().skip(1) {
+        if projectDir.join(dir).join("Cargo.toml").is_file() {
+            return crates.iter().position(|v| v.path == dir);
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn testWorkspaceCrates() {
+        let projectDir = std::env::temp_dir().join(format!("workspace_{}", utils::getTimestamp()));
+        let _ = std::fs::remove_dir_all(&projectDir);
+        let manifests = [
+            (
+                "",
+                "[package]\nname = \"root\"\n\n[workspace]\nmembers = [\"crates/*\", \"tool\"]\nexclude = [\"crates/excluded\"]\n",
+            ),
+            ("crates/foo", "[package]\nname = \"foo\"\n"),
+            ("crates/bar", "[package]\nname = \"bar\"\n"),
+            ("crates/excluded", "[package]\nname = \"excluded\"\n"),
+            ("tool", "[package]\nname = \"tool\"\n"),
+            ("vendor/baz", "[package]\nname = \"baz\"\n"),
+        ];
+        for (dir, manifest) in manifests {
+            std::fs::create_dir_all(projectDir.join(dir).join("src")).unwrap();
+            std::fs::write(projectDir.join(dir).join("Cargo.toml"), manifest).unwrap();
+        }
+
+        let crates = workspaceCrates(&projectDir, &AnalysisLimits::default()).unwrap();
+        let names: Vec<&str> = crates.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["root", "bar", "foo", "tool"]);
+        assert_eq!(crates[1].path, PathBuf::from("crates/bar"));
+
+        let owner = |file: &str| owningCrate(&projectDir, &crates, &projectDir.join(file));
+        assert_eq!(owner("src/lib.rs"), Some(0));
+        assert_eq!(owner("crates/foo/src/lib.rs"), Some(2));
+        assert_eq!(owner("crates/foo/tests/it.rs"), Some(2));
+        assert_eq!(owner("crates/excluded/src/lib.rs"), None);
+        assert_eq!(owner("vendor/baz/src/lib.rs"), None);
+        std::fs::remove_dir_all(&projectDir).unwrap();
+    }
+
+    #[test]
+    fn testWorkspaceCratesWithoutManifest() {
+        let crates = workspaceCrates(Path::new("/non/existent"), &AnalysisLimits::default());
+        assert!(crates.unwrap().is_empty());
+    }
+
+    #[test]
+    fn testWorkspaceCratesOutsideOfProject() {
+        let dir = std::env::temp_dir().join(format!("workspace_outside_{}", utils::getTimestamp()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let projectDir = dir.join("project");
+        let manifests = [
+            (
+                "project",
+                "[workspace]\nmembers = [\"../outside\", \"/etc\", \"crates/*\", \"link\"]\n",
+            ),
+            ("project/crates/foo", "[package]\nname = \"foo\"\n"),
+            ("project/crates/bar", "[package]\nname = \"bar\"\n"),
+            ("outside", "[package]\nname = \"outside\"\n"),
+        ];
+        for (path, manifest) in manifests {
+            std::fs::create_dir_all(dir.join(path)).unwrap();
+            std::fs::write(dir.join(path).join("Cargo.toml"), manifest).unwrap();
+        }
+        std::os::unix::fs::symlink(dir.join("outside"), projectDir.join("link")).unwrap();
+
+        let crates = workspaceCrates(&projectDir, &AnalysisLimits::default()).unwrap();
+        let names: Vec<&str> = crates.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["bar", "foo"]);
+
+        let limits = AnalysisLimits {
+            maxFiles: Some(2),
+            ..Default::default()
+        };
+        let result = workspaceCrates(&projectDir, &limits);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(
+            result,
+            Err(AnalysisError::TooManyFiles { limit: 2 })
+        ));
+    }
+}
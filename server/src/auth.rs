@@ -0,0 +1,129 @@
+//! Bearer API tokens for the admin endpoints. Only the SHA-256 of a token is
+//! stored, so a token is shown once, when it is created.
+
+use crate::{
+    error::AppError, models::token::ApiTokenScope, services::repository::TokenStore, AppState,
+};
+use axum::{
+    extract::State,
+    http::{header, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+const TOKEN_PREFIX: &str = "ur_";
+
+/// Returns a new random token.
+pub fn generateToken() -> String {
+    let bytes: [u8; 32] = rand::random();
+    let hex: String = bytes.iter().map(|v| format!("{v:02x}")).collect();
+    return format!("{TOKEN_PREFIX}{hex}");
+}
+
+pub fn hashToken(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    return digest.iter().map(|v| format!("{v:02x}")).collect();
+}
+
+/// Lets the request through if it has an active token with at least `scope`.
+/// Responds with 401 without a valid token, and with 403 if its scope is too narrow.
+pub async fn requireScope<B>(
+    State((appState, scope)): State<(AppState, ApiTokenScope)>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim());
+    let Some(token) = token else {
+        return AppError::Unauthorized.into_response();
+    };
+
+    let tokenScope = appState.tokenStore.useApiToken(&hashToken(token)).await;
+    return match tokenScope {
+        Ok(Some(v)) if v >= scope => next.run(request).await,
+        Ok(Some(_)) => AppError::Forbidden.into_response(),
+        Ok(None) => AppError::Unauthorized.into_response(),
+        Err(e) => AppError::from(e).into_response(),
+    };
+}
+
+/// Runs `token create <name> [update|admin]`, `token list` or `token revoke <name>`,
+/// and returns what to print.
+pub async fn tokenCommand(tokenStore: &dyn TokenStore, args: &[String]) -> Result<String, String> {
+    let args: Vec<&str> = args.iter().map(|v| v.as_str()).collect();
+    return match args[..] {
+        ["create", name] | ["create", name, _] => {
+            let scope = match args.get(2) {
+                Some(v) => v.parse()?,
+                None => ApiTokenScope::Admin,
+            };
+            let token = generateToken();
+            tokenStore
+                .createApiToken(name, scope, &hashToken(&token))
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(token)
+        }
+        ["list"] => {
+            let tokens = tokenStore.getApiTokens().await.map_err(|e| e.to_string())?;
+            let lines: Vec<String> = tokens
+                .iter()
+                .map(|v| {
+                    let status = match &v.revoked_at {
+                        Some(revokedAt) => format!("revoked at {revokedAt}"),
+                        None => "active".to_owned(),
+                    };
+                    let lastUsedAt = v.last_used_at.as_deref().unwrap_or("never");
+                    return format!("{}\t{}\t{status}\tlast used: {lastUsedAt}", v.name, v.scope);
+                })
+                .collect();
+            Ok(lines.join("\n"))
+        }
+        ["revoke", name] => match tokenStore
+            .revokeApiToken(name)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            true => Ok(format!("Revoked {name}")),
+            false => Err(format!("No active token named {name}")),
+        },
+        _ => Err(
+            "Usage: token create <name> [update|admin] | token list | token revoke <name>"
+                .to_owned(),
+        ),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testGenerateToken() {
+        let token = generateToken();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, generateToken());
+    }
+
+    #[test]
+    fn testHashToken() {
+        assert_eq!(
+            hashToken("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(hashToken("abc").len(), 64);
+    }
+
+    #[test]
+    fn testApiTokenScope() {
+        assert!(ApiTokenScope::Admin > ApiTokenScope::Update);
+        assert_eq!("update".parse(), Ok(ApiTokenScope::Update));
+        assert!("root".parse::<ApiTokenScope>().is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! The error of the handlers. Every error is answered with an RFC 7807
+//! `application/problem+json` body, and the server errors are logged by `logErrors`.
+
+use crate::{
+    analysis::AnalysisError,
+    services::{cache::CacheError, git::GitError, postgres::DatabaseError, repository::Repository},
+};
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum AppError {
+    Database(DatabaseError),
+    Cache(CacheError),
+    Git(GitError),
+    Analysis(AnalysisError),
+    /// An invalid query parameter.
+    BadRequest(String),
+    /// A body that does not deserialize.
+    JsonBody(JsonRejection),
+    /// A body that deserializes, but fails validation.
+    Validation(String),
+    NotFound(String),
+    Conflict(String),
+    Unauthorized,
+    Forbidden,
+    /// Anything else that the client cannot fix, e.g. a panicked task.
+    Internal(String),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        return match self {
+            AppError::Database(DatabaseError::Duplicate(_) | DatabaseError::Reference(_)) => {
+                StatusCode::CONFLICT
+            }
+            AppError::Database(DatabaseError::Other(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Cache(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Git(e) => match e {
+                GitError::InvalidName(_)
+                | GitError::UnknownRevision { .. }
+                | GitError::TooLarge { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+                GitError::Clone { .. } | GitError::Fetch { .. } => StatusCode::BAD_GATEWAY,
+                GitError::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+                GitError::Io(_)
+                | GitError::Open { .. }
+                | GitError::Checkout(_)
+                | GitError::Tags(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            AppError::Analysis(e) => match e {
+                AnalysisError::TooManyFiles { .. } | AnalysisError::TooLarge { .. } => {
+                    StatusCode::UNPROCESSABLE_ENTITY
+                }
+                AnalysisError::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+                AnalysisError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::JsonBody(e) => e.status(),
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden => StatusCode::FORBIDDEN,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+    }
+
+    /// What the client is told. The details of the server errors are only logged.
+    fn detail(&self) -> String {
+        return match self {
+            AppError::Database(DatabaseError::Duplicate(_)) => "It already exists".to_owned(),
+            AppError::Database(DatabaseError::Reference(_)) => {
+                "It is referenced by, or references, a missing entry".to_owned()
+            }
+            AppError::Database(DatabaseError::Other(_)) => "The database query failed".to_owned(),
+            AppError::Cache(_) => "The cache is unavailable".to_owned(),
+            AppError::Unauthorized => "A valid bearer token is required".to_owned(),
+            AppError::Forbidden => "The scope of the token is too narrow".to_owned(),
+            AppError::JsonBody(e) => e.body_text(),
+            e if e.status().is_server_error() => "The request failed".to_owned(),
+            e => e.to_string(),
+        };
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            AppError::Database(e) => write!(f, "{e}"),
+            AppError::Cache(e) => write!(f, "Cache error: {e}"),
+            AppError::Git(e) => write!(f, "{e}"),
+            AppError::Analysis(e) => write!(f, "{e}"),
+            AppError::JsonBody(e) => write!(f, "{}", e.body_text()),
+            AppError::BadRequest(v)
+            | AppError::Validation(v)
+            | AppError::NotFound(v)
+            | AppError::Conflict(v)
+            | AppError::Internal(v) => write!(f, "{v}"),
+            AppError::Unauthorized => write!(f, "Unauthorized"),
+            AppError::Forbidden => write!(f, "Forbidden"),
+        };
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<DatabaseError> for AppError {
+    fn from(e: DatabaseError) -> Self {
+        return AppError::Database(e);
+    }
+}
+
+impl From<CacheError> for AppError {
+    fn from(e: CacheError) -> Self {
+        return AppError::Cache(e);
+    }
+}
+
+impl From<GitError> for AppError {
+    fn from(e: GitError) -> Self {
+        return AppError::Git(e);
+    }
+}
+
+impl From<AnalysisError> for AppError {
+    fn from(e: AnalysisError) -> Self {
+        return AppError::Analysis(e);
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(e: JsonRejection) -> Self {
+        return AppError::JsonBody(e);
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        return AppError::Internal(format!("The task failed: {e}"));
+    }
+}
+
+/// An RFC 7807 problem details body.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The reason phrase of the status, e.g. `Not Found`.
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, detail: &str) -> Self {
+        return Self {
+            type_: "about:blank".to_owned(),
+            title: status.canonical_reason().unwrap_or_default().to_owned(),
+            status: status.as_u16(),
+            detail: detail.to_owned(),
+        };
+    }
+}
+
+/// The message of a server error, for `logErrors`.
+#[derive(Clone)]
+struct LoggedError(String);
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = serde_json::to_string(&Problem::new(status, &self.detail())).unwrap_or_default();
+        let mut response = (
+            status,
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            body,
+        )
+            .into_response();
+        if let AppError::Unauthorized = self {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+        if status.is_server_error() {
+            response
+                .extensions_mut()
+                .insert(LoggedError(self.to_string()));
+        }
+        return response;
+    }
+}
+
+/// Logs the server errors of the responses, with the request that failed.
+pub async fn logErrors<B>(
+    State(repository): State<Arc<dyn Repository>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let context = format!("{} {}", request.method(), request.uri().path());
+    let response = next.run(request).await;
+    if let Some(LoggedError(error)) = response.extensions().get::<LoggedError>() {
+        let _ = repository.logError(&format!("{context}: {error}")).await;
+    }
+    return response;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testAppErrorResponse() {
+        let response = AppError::NotFound("Project 1 does not exist".to_owned()).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+        assert!(response.extensions().get::<LoggedError>().is_none());
+
+        // The server errors are logged, and their details are not sent.
+        let error = AppError::Database(DatabaseError::Other("connection refused".to_owned()));
+        assert_eq!(error.detail(), "The database query failed");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let logged = response.extensions().get::<LoggedError>().unwrap();
+        assert_eq!(logged.0, "connection refused");
+
+        let response = AppError::Unauthorized.into_response();
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+    }
+
+    #[test]
+    fn testAppErrorStatus() {
+        let cases = [
+            (
+                AppError::Database(DatabaseError::Duplicate(String::new())),
+                StatusCode::CONFLICT,
+            ),
+            (
+                AppError::Git(GitError::TimedOut),
+                StatusCode::GATEWAY_TIMEOUT,
+            ),
+            (
+                AppError::Git(GitError::InvalidName("..".to_owned())),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                AppError::Analysis(AnalysisError::TooManyFiles { limit: 1 }),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                AppError::Analysis(AnalysisError::TooLarge { limit: 1 }),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                AppError::Validation(String::new()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (AppError::Forbidden, StatusCode::FORBIDDEN),
+        ];
+        for (error, status) in cases {
+            assert_eq!(error.status(), status, "{error}");
+        }
+    }
+}
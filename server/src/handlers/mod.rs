@@ -0,0 +1,438 @@
+pub mod v2;
+
+use crate::{
+    error::AppError,
+    models::{
+        backfill::BackfillOptions,
+        job::{JobDTO, JobEvent, JobEventKind},
+        pagination::{Pagination, ProjectStatsQuery},
+        project::ProjectStatsWithMeta,
+        project::{
+            FileSafetyCoverage, Project, ProjectCategoryStats, ProjectCrate, ProjectInput,
+            ProjectPatch, ProjectSchedule, ProjectStats, UnsafeSiteDTO,
+        },
+        provider::{Provider, ProviderInput, ProviderPatch},
+    },
+    services::{
+        cache::{invalidateProjectStats, Cache, CacheError},
+        postgres::DatabaseError,
+        repository::Repository,
+    },
+    AppState,
+};
+use axum::{
+    extract::{rejection::JsonRejection, Path, Query, State},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::{stream, Stream, StreamExt};
+use std::io::BufRead;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+pub async fn healthCheck() -> StatusCode {
+    return StatusCode::OK;
+}
+
+/// Queues an update of the stats of every project. The returned job reports the progress.
+pub async fn updateProjectsStats(
+    State(appState): State<AppState>,
+) -> Result<(StatusCode, Json<JobDTO>), AppError> {
+    let projects = appState.repository.getProjectsWithUrl().await?;
+    let projectIds: Vec<i32> = projects.iter().map(|v| v.id).collect();
+    let id = appState.jobService.enqueueUpdate(&projectIds).await?;
+    let job = appState
+        .jobStore
+        .getJobById(id)
+        .await?
+        .ok_or_else(|| AppError::Internal(format!("Job {id} vanished")))?;
+    return Ok((StatusCode::ACCEPTED, Json(job)));
+}
+
+pub async fn getJobById(
+    State(appState): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<JobDTO>, AppError> {
+    let job = appState
+        .jobStore
+        .getJobById(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Job {id} does not exist")))?;
+    return Ok(Json(job));
+}
+
+/// Streams the events of a job as Server-Sent Events until it is completed,
+/// starting with its progress so far.
+pub async fn getJobEventsById(
+    State(appState): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Sse<impl Stream<Item = Result<Event, serde_json::Error>>>, AppError> {
+    // Subscribe before reading the progress, so that no event falls in between.
+    let receiver = appState.jobService.subscribe();
+    let job = appState
+        .jobStore
+        .getJobById(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Job {id} does not exist")))?
+        .job;
+    let completed = job.status == "completed";
+    let progress = JobEvent {
+        job_id: id,
+        kind: if completed {
+            JobEventKind::Completed
+        } else {
+            JobEventKind::Progress
+        },
+        project_id: None,
+        name: None,
+        status: None,
+        error: None,
+        progress: job.progress,
+    };
+
+    let events = stream::unfold(
+        (receiver, completed),
+        move |(mut receiver, done)| async move {
+            if done {
+                return None;
+            }
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.job_id == id => {
+                        let done = event.kind == JobEventKind::Completed;
+                        return Some((event, (receiver, done)));
+                    }
+                    // The counts of the next event make up for the missed ones.
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+    let events = stream::once(async move { progress })
+        .chain(events)
+        .map(|event| Event::default().event(event.kind.asStr()).json_data(&event));
+    return Ok(Sse::new(events).keep_alive(KeepAlive::default()));
+}
+
+/// Queues the analysis of past tags or commits of a project, which inserts stats
+/// dated by each commit.
+pub async fn backfillProjectStats(
+    State(appState): State<AppState>,
+    Path(id): Path<i32>,
+    Query(options): Query<BackfillOptions>,
+) -> Result<(StatusCode, Json<JobDTO>), AppError> {
+    if appState.repository.getProjectById(id).await?.is_empty() {
+        return Err(AppError::NotFound(format!("Project {id} does not exist")));
+    }
+    let jobId = appState.jobService.enqueueBackfill(id, &options).await?;
+    let job = appState
+        .jobStore
+        .getJobById(jobId)
+        .await?
+        .ok_or_else(|| AppError::Internal(format!("Job {jobId} vanished")))?;
+    return Ok((StatusCode::ACCEPTED, Json(job)));
+}
+
+pub async fn getProjectStatsById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<ProjectStats>>, AppError> {
+    let result = repository.getProjectsStatsById(id).await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProjectCategoryStatsById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<ProjectCategoryStats>>, AppError> {
+    let result = repository.getProjectCategoryStatsById(id).await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProjectUnsafeSitesById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<UnsafeSiteDTO>>, AppError> {
+    let result = repository.getUnsafeSitesByProjectId(id).await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProjectSafetyCoverageById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<FileSafetyCoverage>>, AppError> {
+    let result = repository.getSafetyCoverageByProjectId(id).await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProjectsStats(
+    State(repository): State<Arc<dyn Repository>>,
+    State(cache): State<Arc<dyn Cache>>,
+    pagination: Query<Pagination>,
+) -> Result<String, AppError> {
+    let query = ProjectStatsQuery::try_from(&pagination.0).map_err(AppError::BadRequest)?;
+    let cacheKey = query.cacheKey("v1");
+
+    // Return the cached value if we have one. Without the cache, the stats are queried.
+    match cache.getKey(&cacheKey).await {
+        Ok(Some(json)) => return Ok(json),
+        Ok(None) | Err(CacheError::Unavailable) => {}
+        Err(e) => {
+            let error = format!("getProjectsStats(): Cache::getKey() failed with error: {e}");
+            let _ = repository.logError(&error).await;
+        }
+    }
+
+    let result: ProjectStatsWithMeta = repository.getProjectsStats(&query).await?;
+    let json = serde_json::to_string(&result).unwrap();
+    if let Err(CacheError::Failed(e)) = cache
+        .setKey(&cacheKey, &json, &[query.cacheTag("v1")])
+        .await
+    {
+        let error = format!("getProjectsStats(): Cache::setKey() failed with error: {e}");
+        let _ = repository.logError(&error).await;
+    }
+    return Ok(json);
+}
+
+pub async fn getProviders(
+    State(repository): State<Arc<dyn Repository>>,
+) -> Result<Json<Vec<Provider>>, AppError> {
+    let result = repository.getProviders().await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProviderById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<Provider>>, AppError> {
+    let result = repository.getProviderById(id).await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProjects(
+    State(repository): State<Arc<dyn Repository>>,
+) -> Result<Json<Vec<Project>>, AppError> {
+    let result = repository.getProjects().await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProjectById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<Project>>, AppError> {
+    let result = repository.getProjectById(id).await?;
+    return Ok(Json(result));
+}
+
+/// A failed foreign key on the insert or update of a project means a missing provider.
+fn missingProvider(error: DatabaseError) -> AppError {
+    return match error {
+        DatabaseError::Reference(_) => {
+            AppError::Validation("The provider does not exist".to_owned())
+        }
+        e => AppError::Database(e),
+    };
+}
+
+pub async fn createProvider(
+    State(repository): State<Arc<dyn Repository>>,
+    input: Result<Json<ProviderInput>, JsonRejection>,
+) -> Result<(StatusCode, Json<Provider>), AppError> {
+    let Json(input) = input?;
+    input.validate().map_err(AppError::Validation)?;
+    let provider = repository.insertProvider(&input).await?;
+    return Ok((StatusCode::CREATED, Json(provider)));
+}
+
+/// The stats pages show the url of the provider, so they are invalidated.
+pub async fn replaceProvider(
+    State(repository): State<Arc<dyn Repository>>,
+    State(cache): State<Arc<dyn Cache>>,
+    Path(id): Path<i32>,
+    input: Result<Json<ProviderInput>, JsonRejection>,
+) -> Result<Json<Provider>, AppError> {
+    let Json(input) = input?;
+    input.validate().map_err(AppError::Validation)?;
+    let provider = repository
+        .updateProvider(id, &input)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Provider {id} does not exist")))?;
+    invalidateProjectStats(&*cache, &*repository, &[id]).await;
+    return Ok(Json(provider));
+}
+
+pub async fn patchProvider(
+    State(repository): State<Arc<dyn Repository>>,
+    State(cache): State<Arc<dyn Cache>>,
+    Path(id): Path<i32>,
+    patch: Result<Json<ProviderPatch>, JsonRejection>,
+) -> Result<Json<Provider>, AppError> {
+    let Json(patch) = patch?;
+    let provider = repository
+        .getProviderById(id)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound(format!("Provider {id} does not exist")))?;
+    let input = patch.apply(provider);
+    return replaceProvider(State(repository), State(cache), Path(id), Ok(Json(input))).await;
+}
+
+/// Only the providers without projects can be deleted.
+pub async fn deleteProvider(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let result = repository.deleteProvider(id).await;
+    return match result {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(AppError::NotFound(format!("Provider {id} does not exist"))),
+        Err(DatabaseError::Reference(_)) => Err(AppError::Conflict(
+            "The provider still has projects".to_owned(),
+        )),
+        Err(e) => Err(e.into()),
+    };
+}
+
+pub async fn createProject(
+    State(repository): State<Arc<dyn Repository>>,
+    input: Result<Json<ProjectInput>, JsonRejection>,
+) -> Result<(StatusCode, Json<Project>), AppError> {
+    let Json(input) = input?;
+    input.validate().map_err(AppError::Validation)?;
+    let project = repository
+        .insertProject(&input)
+        .await
+        .map_err(missingProvider)?;
+    return Ok((StatusCode::CREATED, Json(project)));
+}
+
+/// Invalidates the stats pages of the previous and of the new provider.
+pub async fn replaceProject(
+    State(repository): State<Arc<dyn Repository>>,
+    State(cache): State<Arc<dyn Cache>>,
+    Path(id): Path<i32>,
+    input: Result<Json<ProjectInput>, JsonRejection>,
+) -> Result<Json<Project>, AppError> {
+    let Json(input) = input?;
+    input.validate().map_err(AppError::Validation)?;
+    let previous = repository.getProjectById(id).await?;
+    let project = repository
+        .updateProject(id, &input)
+        .await
+        .map_err(missingProvider)?
+        .ok_or_else(|| AppError::NotFound(format!("Project {id} does not exist")))?;
+    let providerIds: Vec<i32> = previous
+        .iter()
+        .map(|v| v.provider_id)
+        .chain([project.provider_id])
+        .collect();
+    invalidateProjectStats(&*cache, &*repository, &providerIds).await;
+    return Ok(Json(project));
+}
+
+pub async fn patchProject(
+    State(repository): State<Arc<dyn Repository>>,
+    State(cache): State<Arc<dyn Cache>>,
+    Path(id): Path<i32>,
+    patch: Result<Json<ProjectPatch>, JsonRejection>,
+) -> Result<Json<Project>, AppError> {
+    let Json(patch) = patch?;
+    let project = repository
+        .getProjectById(id)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound(format!("Project {id} does not exist")))?;
+    let input = patch.apply(project);
+    return replaceProject(State(repository), State(cache), Path(id), Ok(Json(input))).await;
+}
+
+/// Deletes the stats of the project too.
+pub async fn deleteProject(
+    State(repository): State<Arc<dyn Repository>>,
+    State(cache): State<Arc<dyn Cache>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let Some(project) = repository.getProjectById(id).await?.into_iter().next() else {
+        return Err(AppError::NotFound(format!("Project {id} does not exist")));
+    };
+    if !repository.deleteProject(id).await? {
+        return Err(AppError::NotFound(format!("Project {id} does not exist")));
+    }
+    invalidateProjectStats(&*cache, &*repository, &[project.provider_id]).await;
+    return Ok(StatusCode::NO_CONTENT);
+}
+
+pub async fn getProjectCratesById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<ProjectCrate>>, AppError> {
+    let result = repository.getProjectCratesById(id).await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProjectsSchedule(
+    State(appState): State<AppState>,
+) -> Result<Json<Vec<ProjectSchedule>>, AppError> {
+    let result = appState.jobStore.getProjectsSchedule(None).await?;
+    return Ok(Json(result));
+}
+
+pub async fn getProjectScheduleById(
+    State(appState): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<ProjectSchedule>, AppError> {
+    let schedule = appState
+        .jobStore
+        .getProjectsSchedule(Some(id))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound(format!("Project {id} does not exist")))?;
+    return Ok(Json(schedule));
+}
+
+/// Imports `data/projects.txt`, with a project url per line, optionally
+/// followed by the hours between its scheduled updates.
+pub async fn projectsImport(State(appState): State<AppState>) -> Result<(), AppError> {
+    let file = std::fs::File::open("./data/projects.txt")
+        .map_err(|e| AppError::Internal(format!("Failed to open data/projects.txt: {e}")))?;
+    for line in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let url = fields.next().unwrap_or_default();
+        let updateIntervalHours = fields.next().map(|v| v.parse::<i32>());
+        let parts: Vec<&str> = url.split('/').collect();
+        if parts.len() != 5 || matches!(updateIntervalHours, Some(Err(_))) {
+            eprint!("Problem with line: {line}");
+            continue;
+        }
+
+        let provider_url = format!("{}//{}", parts[0], parts[2]);
+        let namespace = parts[3];
+        let name = parts[4];
+        appState
+            .repository
+            .createProject(&provider_url, namespace, name)
+            .await?;
+        if let Some(Ok(hours)) = updateIntervalHours {
+            appState
+                .jobStore
+                .setProjectUpdateInterval(namespace, name, hours)
+                .await?;
+        }
+    }
+
+    return Ok(());
+}
+
+pub async fn redisFlush(State(cache): State<Arc<dyn Cache>>) -> Result<(), AppError> {
+    cache.flush().await?;
+    return Ok(());
+}
@@ -0,0 +1,132 @@
+//! The v2 API: the resources by id are single objects, and a missing id is a 404.
+
+use crate::{
+    error::AppError,
+    models::{
+        project::{Project, ProjectStatsHistory},
+        provider::Provider,
+    },
+    services::repository::Repository,
+};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+
+pub async fn getProviderById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Provider>, AppError> {
+    let provider = repository
+        .getProviderById(id)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound(format!("Provider {id} does not exist")))?;
+    return Ok(Json(provider));
+}
+
+pub async fn getProjectById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Project>, AppError> {
+    let project = repository
+        .getProjectById(id)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound(format!("Project {id} does not exist")))?;
+    return Ok(Json(project));
+}
+
+/// An existing project without stats has an empty history.
+pub async fn getProjectStatsById(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<i32>,
+) -> Result<Json<ProjectStatsHistory>, AppError> {
+    let Json(project) = getProjectById(State(repository.clone()), Path(id)).await?;
+    let stats = repository.getProjectsStatsById(id).await?;
+    return Ok(Json(ProjectStatsHistory { project, stats }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handlers,
+        models::{project::ProjectInput, provider::ProviderInput},
+        services::{
+            cache::{Cache, InMemoryCache},
+            memory::InMemoryRepository,
+        },
+    };
+    use axum::http::StatusCode;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn testHandlersWithInMemoryRepository() {
+        let repository: Arc<dyn Repository> = Arc::new(InMemoryRepository::new());
+        let cache: Arc<dyn Cache> = Arc::new(InMemoryCache::new(10, Duration::from_secs(60)));
+        let provider = ProviderInput {
+            url: "https://github.com".to_owned(),
+        };
+        let (status, Json(provider)) =
+            handlers::createProvider(State(repository.clone()), Ok(Json(provider)))
+                .await
+                .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let project = ProjectInput {
+            provider_id: provider.id,
+            namespace: "clap-rs".to_owned(),
+            name: "clap".to_owned(),
+        };
+        let (_, Json(project)) =
+            handlers::createProject(State(repository.clone()), Ok(Json(project)))
+                .await
+                .unwrap();
+
+        let Json(history) = getProjectStatsById(State(repository.clone()), Path(project.id))
+            .await
+            .unwrap();
+        assert_eq!(history.project.name, "clap");
+        assert!(history.stats.is_empty());
+
+        let error = handlers::deleteProvider(State(repository.clone()), Path(provider.id))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::CONFLICT);
+        let error = getProjectById(State(repository.clone()), Path(project.id + 1))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+        let error = handlers::createProject(
+            State(repository.clone()),
+            Ok(Json(ProjectInput {
+                provider_id: provider.id + 1,
+                namespace: "serde-rs".to_owned(),
+                name: "serde".to_owned(),
+            })),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        assert_eq!(
+            handlers::deleteProject(State(repository.clone()), State(cache), Path(project.id))
+                .await
+                .unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            handlers::deleteProvider(State(repository.clone()), Path(provider.id))
+                .await
+                .unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        let error = getProviderById(State(repository), Path(provider.id))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+    }
+}
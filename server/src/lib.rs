@@ -1,85 +1,277 @@
 #![allow(clippy::needless_return, non_snake_case)]
 
+pub mod analysis;
+pub mod auth;
+pub mod error;
 pub mod handlers;
 pub mod models;
 pub mod services;
 mod utils;
 
 use crate::{
+    auth::requireScope,
+    error::logErrors,
     handlers::*,
-    services::{postgres::PostgresService, redis::RedisService},
+    models::token::ApiTokenScope,
+    services::{
+        cache::Cache,
+        git::GitService,
+        jobs::{JobOptions, JobService},
+        repository::{JobStore, Repository, TokenStore},
+    },
 };
 use axum::{
-    http::{HeaderValue, Method},
-    routing::{get, IntoMakeService},
+    extract::FromRef,
+    http::{header, HeaderValue, Method},
+    middleware,
+    routing::{get, post, put, IntoMakeService},
     Router,
 };
 use hyper::{header::HeaderName, server::conn::AddrIncoming};
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 use tower_http::cors::CorsLayer;
 
 #[derive(Clone)]
 pub struct AppState {
-    // Todo: Move this to models
-    redisService: RedisService,
-    databaseService: PostgresService,
+    /// Redis, or a cache in memory when Redis is unavailable at startup.
+    cache: Arc<dyn Cache>,
+    /// The providers, projects, stats and errors.
+    repository: Arc<dyn Repository>,
+    /// The jobs and the schedule.
+    jobStore: Arc<dyn JobStore>,
+    /// The API tokens of the admin endpoints.
+    tokenStore: Arc<dyn TokenStore>,
+    jobService: JobService,
 }
 
+/// Lets the handlers that only need the storage take `State<Arc<dyn Repository>>`.
+impl FromRef<AppState> for Arc<dyn Repository> {
+    fn from_ref(appState: &AppState) -> Self {
+        return appState.repository.clone();
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Cache> {
+    fn from_ref(appState: &AppState) -> Self {
+        return appState.cache.clone();
+    }
+}
+
+/// The state of the read-only API, which runs on any repository.
+#[derive(Clone)]
+pub struct ReadOnlyState {
+    cache: Arc<dyn Cache>,
+    repository: Arc<dyn Repository>,
+}
+
+impl FromRef<ReadOnlyState> for Arc<dyn Repository> {
+    fn from_ref(state: &ReadOnlyState) -> Self {
+        return state.repository.clone();
+    }
+}
+
+impl FromRef<ReadOnlyState> for Arc<dyn Cache> {
+    fn from_ref(state: &ReadOnlyState) -> Self {
+        return state.cache.clone();
+    }
+}
+
+/// Serves the API and runs the jobs. Every store is usually the same database.
 pub fn run(
     listener: std::net::TcpListener,
-    redisService: RedisService,
-    databaseService: PostgresService,
+    cache: Arc<dyn Cache>,
+    repository: Arc<dyn Repository>,
+    jobStore: Arc<dyn JobStore>,
+    tokenStore: Arc<dyn TokenStore>,
+    gitService: GitService,
+    jobOptions: JobOptions,
 ) -> Result<hyper::server::Server<AddrIncoming, IntoMakeService<Router>>, std::io::Error> {
+    let jobService = JobService::new(
+        repository.clone(),
+        jobStore.clone(),
+        gitService,
+        cache.clone(),
+        jobOptions,
+    );
     let appState = AppState {
-        redisService,
-        databaseService,
+        cache,
+        repository,
+        jobStore,
+        tokenStore,
+        jobService,
     };
-
-    let corsOrigins = [
-        "http://localhost:3000".parse().unwrap(),
-        "http://127.0.0.1:3000".parse().unwrap(),
-        "https://unsaferust.org".parse().unwrap(),
-        "http://unsaferust.org".parse().unwrap(),
-    ];
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET])
-        .allow_origin(corsOrigins)
-        .max_age(std::time::Duration::from_secs(60) * 60);
+    appState.jobService.spawnWorkers();
+    appState.jobService.spawnScheduler();
+    appState.jobService.spawnEventListener();
 
     // Routes
     let providerRoutes = Router::new()
         .route("/:id", get(getProviderById))
         .route("/", get(getProviders))
         .with_state(appState.clone());
-    let providerRoutesNamespace = Router::new().nest("/providers", providerRoutes);
+    let providerAdminRoutes = Router::new()
+        .route(
+            "/:id",
+            put(replaceProvider)
+                .patch(patchProvider)
+                .delete(deleteProvider),
+        )
+        .route("/", post(createProvider))
+        .route_layer(middleware::from_fn_with_state(
+            (appState.clone(), ApiTokenScope::Admin),
+            requireScope,
+        ))
+        .with_state(appState.clone());
+    let providerRoutesNamespace =
+        Router::new().nest("/providers", providerRoutes.merge(providerAdminRoutes));
 
     let projectRoutes = Router::new()
-        .route("/import", get(projectsImport))
+        .route("/schedule", get(getProjectsSchedule))
+        .route("/:id/schedule", get(getProjectScheduleById))
+        .route("/:id/crates", get(getProjectCratesById))
+        .route("/:id/unsafe-sites", get(getProjectUnsafeSitesById))
+        .route("/:id/safety-coverage", get(getProjectSafetyCoverageById))
         .route("/:id", get(getProjectById))
         .route("/", get(getProjects))
         .with_state(appState.clone());
-    let projectRoutesNamespace = Router::new().nest("/projects", projectRoutes);
+    let projectAdminRoutes = Router::new()
+        .route(
+            "/:id",
+            put(replaceProject)
+                .patch(patchProject)
+                .delete(deleteProject),
+        )
+        .route("/", post(createProject))
+        .route_layer(middleware::from_fn_with_state(
+            (appState.clone(), ApiTokenScope::Admin),
+            requireScope,
+        ))
+        .with_state(appState.clone());
+    let projectRoutesNamespace =
+        Router::new().nest("/projects", projectRoutes.merge(projectAdminRoutes));
 
     let projectStatsRoutes: Router<()> = Router::new()
-        .route("/update", get(updateProjectsStats))
+        .route("/:id/categories", get(getProjectCategoryStatsById))
         .route("/:id", get(getProjectStatsById))
         .route("/", get(getProjectsStats))
         .with_state(appState.clone());
     let projectStatsNamespace = Router::new().nest("/project-stats", projectStatsRoutes);
+
+    let jobRoutes = Router::new()
+        .route("/:id/events", get(getJobEventsById))
+        .route("/:id", get(getJobById))
+        .with_state(appState.clone());
+    let jobRoutesNamespace = Router::new().nest("/jobs", jobRoutes);
+
+    // The mutating endpoints need a bearer token with at least the scope of their group.
+    let adminUpdateRoutes = Router::new()
+        .route("/project-stats/update", post(updateProjectsStats))
+        .route("/project-stats/:id/backfill", post(backfillProjectStats))
+        .route_layer(middleware::from_fn_with_state(
+            (appState.clone(), ApiTokenScope::Update),
+            requireScope,
+        ))
+        .with_state(appState.clone());
+    let adminRoutes = Router::new()
+        .route("/projects/import", post(projectsImport))
+        .route("/redis/flush", post(redisFlush))
+        .route_layer(middleware::from_fn_with_state(
+            (appState.clone(), ApiTokenScope::Admin),
+            requireScope,
+        ))
+        .with_state(appState.clone());
+    let adminRoutesNamespace = Router::new().nest("/admin", adminRoutes.merge(adminUpdateRoutes));
     let apiV1Namespace = Router::new().nest(
         "/v1",
         providerRoutesNamespace
             .merge(projectRoutesNamespace)
-            .merge(projectStatsNamespace),
+            .merge(projectStatsNamespace)
+            .merge(jobRoutesNamespace)
+            .merge(adminRoutesNamespace),
     );
 
+    // v2 returns single objects by id, and a 404 for the missing ids.
+    let apiV2Routes = Router::new()
+        .route("/providers/:id", get(v2::getProviderById))
+        .route("/projects/:id", get(v2::getProjectById))
+        .route("/project-stats/:id", get(v2::getProjectStatsById))
+        .with_state(appState.clone());
+    let apiV2Namespace = Router::new().nest("/v2", apiV2Routes);
+
     let apiNamespaceRoutes = axum::routing::Router::new()
         .route("/health_check", get(healthCheck))
-        .route("/redis/flush", get(redisFlush))
-        .with_state(appState);
-    let apiNamespace = Router::new().nest("/api", apiNamespaceRoutes.merge(apiV1Namespace));
+        .with_state(appState.clone());
+    let apiNamespace = Router::new().nest(
+        "/api",
+        apiNamespaceRoutes
+            .merge(apiV1Namespace)
+            .merge(apiV2Namespace),
+    );
     let app = Router::new()
         .merge(apiNamespace)
+        .layer(middleware::from_fn_with_state(
+            appState.repository.clone(),
+            logErrors,
+        ));
+    return Ok(serve(listener, app));
+}
+
+/// Serves the providers, projects and stats of a repository without a `JobStore` and
+/// a `TokenStore`, e.g. the one in memory, so without the jobs, the schedule and the admin endpoints.
+pub fn runReadOnly(
+    listener: std::net::TcpListener,
+    cache: Arc<dyn Cache>,
+    repository: Arc<dyn Repository>,
+) -> Result<hyper::server::Server<AddrIncoming, IntoMakeService<Router>>, std::io::Error> {
+    let state = ReadOnlyState {
+        cache,
+        repository: repository.clone(),
+    };
+    let apiV1Routes = Router::new()
+        .route("/providers/:id", get(getProviderById))
+        .route("/providers", get(getProviders))
+        .route("/projects/:id", get(getProjectById))
+        .route("/projects", get(getProjects))
+        .route("/project-stats/:id", get(getProjectStatsById))
+        .route("/project-stats", get(getProjectsStats));
+    let apiV2Routes = Router::new()
+        .route("/providers/:id", get(v2::getProviderById))
+        .route("/projects/:id", get(v2::getProjectById))
+        .route("/project-stats/:id", get(v2::getProjectStatsById));
+    let apiRoutes = Router::new()
+        .route("/health_check", get(healthCheck))
+        .nest("/v1", apiV1Routes)
+        .nest("/v2", apiV2Routes)
+        .with_state(state);
+    let app = Router::new()
+        .nest("/api", apiRoutes)
+        .layer(middleware::from_fn_with_state(repository, logErrors));
+    return Ok(serve(listener, app));
+}
+
+/// Serves the API with the CORS and security headers.
+fn serve(
+    listener: std::net::TcpListener,
+    app: Router,
+) -> hyper::server::Server<AddrIncoming, IntoMakeService<Router>> {
+    let corsOrigins = [
+        "http://localhost:3000".parse().unwrap(),
+        "http://127.0.0.1:3000".parse().unwrap(),
+        "https://unsaferust.org".parse().unwrap(),
+        "http://unsaferust.org".parse().unwrap(),
+    ];
+    let cors = CorsLayer::new()
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+        .allow_origin(corsOrigins)
+        .max_age(std::time::Duration::from_secs(60) * 60);
+    let app = app
         .layer(cors)
         .layer(tower_http::set_header::SetResponseHeaderLayer::overriding(
             HeaderName::from_str("X-Content-Type-Options").unwrap(),
@@ -128,9 +320,7 @@ pub fn run(
             ),
         );
 
-    let server = axum::Server::from_tcp(listener)
+    return axum::Server::from_tcp(listener)
         .expect("axum::Server::from_tcp failed")
         .serve(app.into_make_service());
-
-    return Ok(server);
 }
@@ -1,6 +1,5 @@
-#![allow(non_snake_case)]
+#![allow(clippy::needless_return, non_snake_case)]
 
-// Todo: Add cascade on delete to projects.
 // Todo: Check why update is blocking. Comment the services stuff and check performance.
 // Todo: Use client caching too
 // Todo: Add most popular packages
@@ -8,80 +7,161 @@
 
 use std::io::BufRead;
 use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+use unsaferust::models::configuration::DatabaseBackend;
+use unsaferust::services::cache::{Cache, InMemoryCache};
+use unsaferust::services::git::GitService;
+use unsaferust::services::jobs::JobOptions;
 use unsaferust::services::postgres::PostgresService;
 use unsaferust::services::redis::RedisService;
+use unsaferust::services::repository::{JobStore, Repository, TokenStore};
+use unsaferust::services::sqlite::SqliteService;
 
 #[tokio::main]
 async fn main() {
     // Prepare the variables that the run method needs.
     let serverPort = std::env::var("SERVER_PORT").expect("env::var SERVER_PORT failed");
-    let databaseService = PostgresService::new(None).await;
-    let redisService = RedisService::new().await;
+    let address = format!("0.0.0.0:{}", serverPort);
+    let backend: DatabaseBackend = std::env::var("DATABASE_BACKEND")
+        .map(|v| v.parse().expect("Failed to parse DATABASE_BACKEND"))
+        .unwrap_or_default();
+
+    let (repository, jobStore, tokenStore) = database(backend).await;
+
+    // Manage the API tokens, e.g. `unsaferust token create cron update`, instead of serving.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|v| v.as_str()) == Some("token") {
+        match unsaferust::auth::tokenCommand(&*tokenStore, &args[1..]).await {
+            Ok(output) => println!("{output}"),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let cache = cache().await;
+    let gitService = GitService::new(None);
 
-    // Execute the migrations.
-    sqlx::migrate!("./migrations")
-        .run(&databaseService.connection)
-        .await
-        .expect("migrations failed");
+    for (namespace, name, hours) in seed(&*repository).await {
+        jobStore
+            .setProjectUpdateInterval(&namespace, &name, hours)
+            .await
+            .expect("Failed to set the update interval");
+    }
 
-    // Todo: Delete when done =========================================================================
+    let listener = TcpListener::bind(&address).expect("TcpListener failed");
+    println!("Listening at: {}", &address);
+    unsaferust::run(
+        listener,
+        cache,
+        repository,
+        jobStore,
+        tokenStore,
+        gitService,
+        JobOptions::new(),
+    )
+    .expect("unsaferust::run failed")
+    .await
+    .expect("axum::Server failed");
+}
+
+/// Connects to the database of the backend and runs its migrations. Both backends
+/// implement every store.
+async fn database(
+    backend: DatabaseBackend,
+) -> (Arc<dyn Repository>, Arc<dyn JobStore>, Arc<dyn TokenStore>) {
+    return match backend {
+        DatabaseBackend::Postgres => {
+            let databaseService = Arc::new(PostgresService::new(None).await);
+            sqlx::migrate!("./migrations")
+                .run(&databaseService.connection)
+                .await
+                .expect("migrations failed");
+            (
+                databaseService.clone(),
+                databaseService.clone(),
+                databaseService,
+            )
+        }
+        DatabaseBackend::Sqlite => {
+            let databaseService = Arc::new(SqliteService::new(None).await);
+            sqlx::migrate!("./migrations-sqlite")
+                .run(&databaseService.connection)
+                .await
+                .expect("migrations failed");
+            (
+                databaseService.clone(),
+                databaseService.clone(),
+                databaseService,
+            )
+        }
+    };
+}
+
+/// Connects to Redis, or caches in memory if Redis is down. The values live for
+/// CACHE_TTL_SECONDS, and the size of the cache in memory is read from CACHE_CAPACITY.
+async fn cache() -> Arc<dyn Cache> {
+    let ttl = std::env::var("CACHE_TTL_SECONDS")
+        .map(|v| v.parse().expect("Failed to parse CACHE_TTL_SECONDS"))
+        .unwrap_or(3600);
+    let ttl = Duration::from_secs(ttl);
+    return match RedisService::new(ttl).await {
+        Ok(redisService) => Arc::new(redisService),
+        Err(e) => {
+            eprintln!("Redis is unavailable, caching in memory instead: {e}");
+            let capacity = std::env::var("CACHE_CAPACITY")
+                .map(|v| v.parse().expect("Failed to parse CACHE_CAPACITY"))
+                .unwrap_or(1000);
+            Arc::new(InMemoryCache::new(capacity, ttl))
+        }
+    };
+}
+
+// Todo: Delete when done =========================================================================
+/// Inserts the providers and the projects of the data directory, and returns the
+/// namespace, name and hours between the scheduled updates of the projects that set them.
+async fn seed(repository: &dyn Repository) -> Vec<(String, String, i32)> {
     let file =
         std::fs::File::open("./data/providers.txt").expect("Failed to read providers.txt file");
-    for url in std::io::BufReader::new(file).lines().flatten() {
+    for url in std::io::BufReader::new(file).lines().map_while(Result::ok) {
         if url.is_empty() {
             continue;
         }
-        sqlx::query(&format!(
-            "
-                do
-                $do$
-                begin
-                if not exists (select id from providers where url = '{url}') then
-                    insert into providers (url) VALUES ('{url}');
-                end if;
-                end
-                $do$
-    "
-        ))
-        .execute(&databaseService.connection)
-        .await
-        .expect("Failed to insert to providers");
+        repository
+            .createProvider(&url)
+            .await
+            .expect("Failed to insert to providers");
     }
 
+    let mut updateIntervals = Vec::new();
     let file =
         std::fs::File::open("./data/projects.txt").expect("Failed to read projects.txt file");
-    for project in std::io::BufReader::new(file).lines().flatten() {
+    for project in std::io::BufReader::new(file).lines().map_while(Result::ok) {
         if project.is_empty() {
             continue;
         }
-        let project = project.replace("https://", "");
-        let parts: Vec<&str> = project.split('/').collect();
-        let namespace = parts[1];
-        let name = parts[2];
-        println!("parts: {:?}", parts);
-        sqlx::query(&format!(
-            "
-                    do
-                    $do$
-                    begin
-                    if not exists (select id from projects where namespace = '{namespace}' and name = '{name}') then
-                        insert into projects (provider_id, namespace, name) VALUES ('1', '{namespace}', '{name}');
-                    end if;
-                    end
-                    $do$
-        "
-        ))
-            .execute(&databaseService.connection)
+        // A url, optionally followed by the hours between the scheduled updates.
+        let mut fields = project.split_whitespace();
+        let url = fields.next().unwrap_or_default();
+        let updateIntervalHours = fields.next().map(|v| v.parse::<i32>());
+        let parts: Vec<&str> = url.split('/').collect();
+        if parts.len() != 5 || matches!(updateIntervalHours, Some(Err(_))) {
+            eprintln!("Problem with line: {project}");
+            continue;
+        }
+        let namespace = parts[3];
+        let name = parts[4];
+        repository
+            .createProject(&format!("{}//{}", parts[0], parts[2]), namespace, name)
             .await
-            .expect("Failed to insert to providers");
+            .expect("Failed to insert to projects");
+        if let Some(Ok(hours)) = updateIntervalHours {
+            updateIntervals.push((namespace.to_owned(), name.to_owned(), hours));
+        }
     }
-    // Todo: Delete when done =========================================================================
-
-    let address = format!("0.0.0.0:{}", serverPort);
-    let listener = TcpListener::bind(&address).expect("TcpListener failed");
-    println!("Listening at: {}", &address);
-    unsaferust::run(listener, redisService, databaseService)
-        .expect("unsaferust::run failed")
-        .await
-        .expect("axum::Server failed");
+    return updateIntervals;
 }
+// Todo: Delete when done =========================================================================
@@ -0,0 +1,7 @@
+/// The revisions that a backfill analyzes: the latest `limit` tags by default,
+/// or every `every`th commit of the history of HEAD.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BackfillOptions {
+    pub(crate) every: Option<usize>,
+    pub(crate) limit: Option<usize>,
+}
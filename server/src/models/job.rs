@@ -0,0 +1,144 @@
+use crate::models::project::ProjectWithUrl;
+
+/// The status of the update of one project in a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobProjectStatus {
+    Queued,
+    Running,
+    Succeeded,
+    /// HEAD has not moved since the last analysis.
+    Skipped,
+    /// All the attempts failed.
+    Failed,
+    /// The clone and analysis took longer than the timeout.
+    TimedOut,
+    /// The repository is larger than the size limit.
+    TooLarge,
+    /// The project has more Rust files than the limit.
+    TooManyFiles,
+}
+
+impl JobProjectStatus {
+    pub fn asStr(&self) -> &'static str {
+        return match self {
+            JobProjectStatus::Queued => "queued",
+            JobProjectStatus::Running => "running",
+            JobProjectStatus::Succeeded => "succeeded",
+            JobProjectStatus::Skipped => "skipped",
+            JobProjectStatus::Failed => "failed",
+            JobProjectStatus::TimedOut => "timed_out",
+            JobProjectStatus::TooLarge => "too_large",
+            JobProjectStatus::TooManyFiles => "too_many_files",
+        };
+    }
+}
+
+/// The number of projects of a job in each status.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct JobProgress {
+    pub total: i32,
+    pub queued: i32,
+    pub running: i32,
+    pub succeeded: i32,
+    pub skipped: i32,
+    /// Including the projects that exceeded a limit.
+    pub failed: i32,
+}
+
+/// The status of a job is `queued`, `running` or `completed`.
+#[derive(Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct Job {
+    pub id: i32,
+    pub kind: String,
+    pub status: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub progress: JobProgress,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct JobProject {
+    pub project_id: i32,
+    pub namespace: String,
+    pub name: String,
+    pub status: String,
+    pub attempts: i32,
+    /// The error of the latest failed attempt.
+    pub error: Option<String>,
+    /// When a queued project is retried.
+    pub run_after: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct JobDTO {
+    #[serde(flatten)]
+    pub job: Job,
+    pub projects: Vec<JobProject>,
+}
+
+/// A project that a worker claimed.
+#[derive(Debug, sqlx::FromRow)]
+pub struct ClaimedJobProject {
+    pub job_project_id: i32,
+    pub job_id: i32,
+    /// The kind of the job, e.g. `update_projects_stats`.
+    pub kind: String,
+    /// The revisions of a `backfill_project_stats` job, see `BackfillOptions`.
+    pub backfill_every: Option<i32>,
+    pub backfill_limit: Option<i32>,
+    /// Including the current one.
+    pub attempts: i32,
+    #[sqlx(flatten)]
+    pub project: ProjectWithUrl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobEventKind {
+    /// Sent first to every subscriber, with the progress so far.
+    Progress,
+    Cloning,
+    Analyzing,
+    Stored,
+    /// HEAD has not moved since the last analysis.
+    Skipped,
+    /// The attempt failed and the project is queued again.
+    Retrying,
+    Failed,
+    Completed,
+}
+
+impl JobEventKind {
+    pub fn asStr(&self) -> &'static str {
+        return match self {
+            JobEventKind::Progress => "progress",
+            JobEventKind::Cloning => "cloning",
+            JobEventKind::Analyzing => "analyzing",
+            JobEventKind::Stored => "stored",
+            JobEventKind::Skipped => "skipped",
+            JobEventKind::Retrying => "retrying",
+            JobEventKind::Failed => "failed",
+            JobEventKind::Completed => "completed",
+        };
+    }
+}
+
+/// What happened to a job, with its progress right after.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JobEvent {
+    pub job_id: i32,
+    pub kind: JobEventKind,
+    /// `None` for the events of the whole job.
+    pub project_id: Option<i32>,
+    pub name: Option<String>,
+    /// The status of the project, e.g. `too_large`, for the failed events.
+    pub status: Option<String>,
+    pub error: Option<String>,
+    #[serde(flatten)]
+    pub progress: JobProgress,
+}
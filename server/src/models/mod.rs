@@ -1,4 +1,7 @@
+pub mod backfill;
 pub mod configuration;
+pub mod job;
 pub mod pagination;
 pub mod project;
 pub mod provider;
+pub mod token;
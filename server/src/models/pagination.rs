@@ -1,7 +1,253 @@
+use crate::analysis::lints::UnsafeCodeLint;
+
 // Todo: Move this to another file.
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Pagination {
     pub(crate) limit: Option<u32>,
     pub(crate) page: Option<u32>,
     pub(crate) name: Option<String>,
+    /// Only the projects with this level of the `unsafe_code` lint, e.g. `forbid`.
+    pub(crate) unsafe_code_lint: Option<String>,
+    /// `name`, `code_lines`, `unsafe_lines`, `unsafe_ratio` or `updated_at`.
+    pub(crate) sort: Option<String>,
+    /// `asc` or `desc`.
+    pub(crate) order: Option<String>,
+    /// The unsafe lines in percent of the code lines, e.g. `1` for 1%.
+    pub(crate) min_unsafe_ratio: Option<f64>,
+    pub(crate) max_unsafe_ratio: Option<f64>,
+    pub(crate) min_code_lines: Option<i32>,
+    pub(crate) provider_id: Option<i32>,
+    /// Only the stats updated on or after this date, e.g. `2024-01-31`.
+    pub(crate) updated_since: Option<String>,
+}
+
+/// The columns that `/project-stats` can be sorted by.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectStatsSort {
+    #[default]
+    Name,
+    CodeLines,
+    UnsafeLines,
+    UnsafeRatio,
+    UpdatedAt,
+}
+
+impl ProjectStatsSort {
+    pub const ALL: [ProjectStatsSort; 5] = [
+        ProjectStatsSort::Name,
+        ProjectStatsSort::CodeLines,
+        ProjectStatsSort::UnsafeLines,
+        ProjectStatsSort::UnsafeRatio,
+        ProjectStatsSort::UpdatedAt,
+    ];
+
+    pub fn asStr(&self) -> &'static str {
+        return match self {
+            ProjectStatsSort::Name => "name",
+            ProjectStatsSort::CodeLines => "code_lines",
+            ProjectStatsSort::UnsafeLines => "unsafe_lines",
+            ProjectStatsSort::UnsafeRatio => "unsafe_ratio",
+            ProjectStatsSort::UpdatedAt => "updated_at",
+        };
+    }
+}
+
+impl std::str::FromStr for ProjectStatsSort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return ProjectStatsSort::ALL
+            .into_iter()
+            .find(|v| v.asStr() == value)
+            .ok_or_else(|| format!("Unknown sort column: {value}"));
+    }
+}
+
+/// The validated filters, order and page of `/project-stats`.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize)]
+pub struct ProjectStatsQuery {
+    pub name: Option<String>,
+    pub unsafe_code_lint: Option<&'static str>,
+    pub min_unsafe_ratio: Option<f64>,
+    pub max_unsafe_ratio: Option<f64>,
+    pub min_code_lines: Option<i32>,
+    pub provider_id: Option<i32>,
+    /// A `YYYY-MM-DD` date.
+    pub updated_since: Option<String>,
+    pub sort: ProjectStatsSort,
+    pub descending: bool,
+    pub limit: u32,
+    /// Starting from 0.
+    pub page: u32,
+}
+
+/// The API versions whose `/project-stats` pages are cached.
+pub const PROJECT_STATS_CACHE_VERSIONS: [&str; 1] = ["v1"];
+
+impl ProjectStatsQuery {
+    /// The cache key of the page, which covers every parameter, in the namespace of the
+    /// API version.
+    pub fn cacheKey(&self, version: &str) -> String {
+        return format!(
+            "{version}:project_stats:{}",
+            serde_json::to_string(self).unwrap_or_default()
+        );
+    }
+
+    /// The tag of the page, which a change to the projects of its provider invalidates.
+    pub fn cacheTag(&self, version: &str) -> String {
+        return projectStatsCacheTag(version, self.provider_id);
+    }
+}
+
+/// The tag of the `/project-stats` pages of a provider, or with `None` of the pages
+/// that are not filtered by provider.
+pub fn projectStatsCacheTag(version: &str, providerId: Option<i32>) -> String {
+    return match providerId {
+        Some(id) => format!("{version}:project_stats:provider:{id}"),
+        None => format!("{version}:project_stats:provider:all"),
+    };
+}
+
+impl TryFrom<&Pagination> for ProjectStatsQuery {
+    type Error = String;
+
+    fn try_from(pagination: &Pagination) -> Result<Self, Self::Error> {
+        let unsafe_code_lint = match &pagination.unsafe_code_lint {
+            Some(v) => Some(v.parse::<UnsafeCodeLint>()?.asStr()),
+            None => None,
+        };
+        for ratio in [pagination.min_unsafe_ratio, pagination.max_unsafe_ratio]
+            .into_iter()
+            .flatten()
+        {
+            if !(0.0..=100.0).contains(&ratio) {
+                return Err(format!("The unsafe ratio must be a percentage: {ratio}"));
+            }
+        }
+        let updated_since = match &pagination.updated_since {
+            Some(v) => Some(
+                chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                    .map_err(|_e| format!("Invalid date, expected YYYY-MM-DD: {v}"))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        let sort = match &pagination.sort {
+            Some(v) => v.parse()?,
+            None => ProjectStatsSort::default(),
+        };
+        let descending = match pagination.order.as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(v) => return Err(format!("Unknown order, expected asc or desc: {v}")),
+        };
+
+        return Ok(Self {
+            name: pagination.name.clone().filter(|v| !v.is_empty()),
+            unsafe_code_lint,
+            min_unsafe_ratio: pagination.min_unsafe_ratio,
+            max_unsafe_ratio: pagination.max_unsafe_ratio,
+            min_code_lines: pagination.min_code_lines,
+            provider_id: pagination.provider_id,
+            updated_since,
+            sort,
+            descending,
+            limit: pagination.limit.unwrap_or(50),
+            page: pagination.page.unwrap_or(1).saturating_sub(1),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pagination() -> Pagination {
+        return Pagination {
+            limit: None,
+            page: None,
+            name: None,
+            unsafe_code_lint: None,
+            sort: None,
+            order: None,
+            min_unsafe_ratio: None,
+            max_unsafe_ratio: None,
+            min_code_lines: None,
+            provider_id: None,
+            updated_since: None,
+        };
+    }
+
+    #[test]
+    fn testProjectStatsQuery() {
+        let query = ProjectStatsQuery::try_from(&pagination()).unwrap();
+        assert_eq!(
+            (query.sort, query.descending, query.limit, query.page),
+            (ProjectStatsSort::Name, false, 50, 0)
+        );
+
+        let query = ProjectStatsQuery::try_from(&Pagination {
+            sort: Some("unsafe_ratio".to_owned()),
+            order: Some("desc".to_owned()),
+            min_unsafe_ratio: Some(1.0),
+            updated_since: Some("2024-01-31".to_owned()),
+            page: Some(0),
+            ..pagination()
+        })
+        .unwrap();
+        assert_eq!(query.sort, ProjectStatsSort::UnsafeRatio);
+        assert!(query.descending);
+        assert_eq!(query.page, 0);
+        assert_eq!(query.updated_since.as_deref(), Some("2024-01-31"));
+
+        let invalid = [
+            Pagination {
+                sort: Some("unsafe_lines; drop table projects".to_owned()),
+                ..pagination()
+            },
+            Pagination {
+                order: Some("up".to_owned()),
+                ..pagination()
+            },
+            Pagination {
+                max_unsafe_ratio: Some(101.0),
+                ..pagination()
+            },
+            Pagination {
+                updated_since: Some("yesterday".to_owned()),
+                ..pagination()
+            },
+        ];
+        for pagination in invalid {
+            assert!(ProjectStatsQuery::try_from(&pagination).is_err());
+        }
+    }
+
+    #[test]
+    fn testProjectStatsQueryCacheKey() {
+        let query = ProjectStatsQuery::try_from(&pagination()).unwrap();
+        let descending = ProjectStatsQuery {
+            descending: true,
+            ..query.clone()
+        };
+        let filtered = ProjectStatsQuery {
+            min_code_lines: Some(1000),
+            ..query.clone()
+        };
+        assert_ne!(query.cacheKey("v1"), descending.cacheKey("v1"));
+        assert_ne!(query.cacheKey("v1"), filtered.cacheKey("v1"));
+        assert_ne!(descending.cacheKey("v1"), filtered.cacheKey("v1"));
+        assert_eq!(query.cacheKey("v1"), query.clone().cacheKey("v1"));
+        assert_ne!(query.cacheKey("v1"), query.cacheKey("v2"));
+
+        let provider = ProjectStatsQuery {
+            provider_id: Some(2),
+            ..query.clone()
+        };
+        assert_eq!(query.cacheTag("v1"), "v1:project_stats:provider:all");
+        assert_eq!(provider.cacheTag("v1"), "v1:project_stats:provider:2");
+        assert_eq!(provider.cacheTag("v1"), projectStatsCacheTag("v1", Some(2)));
+    }
 }
@@ -1,6 +1,8 @@
+use crate::analysis::{lines::LineCounts, UnsafeUsage};
+
 /// This is used to create new projects
 /// and also provide the project info to the clients.
-#[derive(Debug, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
 pub struct Project {
     pub(crate) id: i32,
     pub provider_id: i32,
@@ -8,9 +10,71 @@ pub struct Project {
     pub name: String,
 }
 
+/// Whether a namespace or a name is safe to use in a url and as a directory name.
+pub fn isValidRepositoryName(value: &str) -> bool {
+    return !value.is_empty()
+        && value.len() <= 255
+        && !value.starts_with('.')
+        && value
+            .chars()
+            .all(|v| v.is_ascii_alphanumeric() || matches!(v, '-' | '_' | '.'));
+}
+
+/// The body of `POST /projects` and `PUT /projects/:id`.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectInput {
+    pub provider_id: i32,
+    pub namespace: String,
+    pub name: String,
+}
+
+impl ProjectInput {
+    pub fn validate(&self) -> Result<(), String> {
+        for value in [&self.namespace, &self.name] {
+            if !isValidRepositoryName(value) {
+                return Err(format!("Invalid repository name: {value:?}"));
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// The body of `PATCH /projects/:id`. The missing fields are kept.
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectPatch {
+    pub provider_id: Option<i32>,
+    pub namespace: Option<String>,
+    pub name: Option<String>,
+}
+
+impl ProjectPatch {
+    pub fn apply(self, project: Project) -> ProjectInput {
+        return ProjectInput {
+            provider_id: self.provider_id.unwrap_or(project.provider_id),
+            namespace: self.namespace.unwrap_or(project.namespace),
+            name: self.name.unwrap_or(project.name),
+        };
+    }
+}
+
+/// When the scheduler last queued an update of a project, and when it queues the next one.
+#[derive(Debug, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
+pub struct ProjectSchedule {
+    pub project_id: i32,
+    pub namespace: String,
+    pub name: String,
+    pub update_interval_hours: i32,
+    pub last_run_at: Option<String>,
+    pub next_run_at: String,
+    /// The status of the project in its latest job.
+    pub last_status: Option<String>,
+}
+
 /// This is used internally in stats/update.
 /// The provider url is included, instead of the provider_id.
-#[derive(Debug, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
 pub struct ProjectWithUrl {
     pub(crate) id: i32,
     pub(crate) namespace: String,
@@ -24,19 +88,46 @@ pub struct ProjectStatsDTO {
     pub(crate) project_id: i32,
     pub name: String,
     pub(crate) url: String,
-    pub(crate) code_lines: i32,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub line_counts: LineCounts,
+    /// The lines spanned by unsafe blocks. The unsafe constructs are counted by kind
+    /// in `unsafe_usage`.
     pub unsafe_lines: i32,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub unsafe_usage: UnsafeUsage,
+    /// The percentage of documented unsafe blocks and functions, if there are any.
+    pub safety_coverage: Option<f64>,
+    /// The figures of the code that ships, i.e. without tests, examples etc.
+    pub production_code_lines: i32,
+    pub production_unsafe_lines: i32,
+    /// The level of the `unsafe_code` lint: allow, warn, deny or forbid.
+    pub unsafe_code_lint: String,
+    /// The analyzed commit. `None` for the stats that predate commit tracking.
+    pub commit_sha: Option<String>,
+    pub commit_branch: Option<String>,
+    pub commit_date: Option<String>,
     pub created_at: String,
     pub(crate) updated_at: String,
 }
 
 impl ProjectStatsDTO {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         project_id: i32,
         name: String,
         url: String,
-        code_lines: i32,
+        line_counts: LineCounts,
         unsafe_lines: i32,
+        unsafe_usage: UnsafeUsage,
+        safety_coverage: Option<f64>,
+        production_code_lines: i32,
+        production_unsafe_lines: i32,
+        unsafe_code_lint: String,
+        commit_sha: Option<String>,
+        commit_branch: Option<String>,
+        commit_date: Option<String>,
         created_at: String,
         updated_at: String,
     ) -> Self {
@@ -44,44 +135,250 @@ impl ProjectStatsDTO {
             project_id,
             name,
             url,
-            code_lines,
+            line_counts,
             unsafe_lines,
+            unsafe_usage,
+            safety_coverage,
+            production_code_lines,
+            production_unsafe_lines,
+            unsafe_code_lint,
+            commit_sha,
+            commit_branch,
+            commit_date,
             created_at,
             updated_at,
         };
     }
 }
 
+/// The stats of a project over time, newest first.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub struct ProjectStatsHistory {
+    pub project: Project,
+    pub stats: Vec<ProjectStats>,
+}
+
 /// This is used internally. Todo; Add more info (where exactly).
-#[derive(Debug, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
 pub struct ProjectStats {
     pub(crate) project_id: i32,
-    pub(crate) code_lines: i32,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub line_counts: LineCounts,
+    /// The lines spanned by unsafe blocks. The unsafe constructs are counted by kind
+    /// in `unsafe_usage`.
     pub unsafe_lines: i32,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub unsafe_usage: UnsafeUsage,
+    /// The percentage of documented unsafe blocks and functions, if there are any.
+    pub safety_coverage: Option<f64>,
+    pub production_code_lines: i32,
+    pub production_unsafe_lines: i32,
+    /// The level of the `unsafe_code` lint: allow, warn, deny or forbid.
+    pub unsafe_code_lint: String,
+    /// The analyzed commit. `None` for the stats that predate commit tracking.
+    pub commit_sha: Option<String>,
+    pub commit_branch: Option<String>,
+    pub commit_date: Option<String>,
     pub created_at: String,
     pub(crate) updated_at: String,
 }
 
 impl ProjectStats {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         project_id: i32,
-        code_lines: i32,
+        line_counts: LineCounts,
         unsafe_lines: i32,
+        unsafe_usage: UnsafeUsage,
+        safety_coverage: Option<f64>,
+        production_code_lines: i32,
+        production_unsafe_lines: i32,
+        unsafe_code_lint: String,
+        commit_sha: Option<String>,
+        commit_branch: Option<String>,
+        commit_date: Option<String>,
         created_at: String,
         updated_at: String,
     ) -> Self {
         return Self {
             project_id,
-            code_lines,
+            line_counts,
             unsafe_lines,
+            unsafe_usage,
+            safety_coverage,
+            production_code_lines,
+            production_unsafe_lines,
+            unsafe_code_lint,
+            commit_sha,
+            commit_branch,
+            commit_date,
             created_at,
             updated_at,
         };
     }
 }
 
+/// The stats of one category of code (source, tests, benches etc.) of a project.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
+pub struct ProjectCategoryStats {
+    pub category: String,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub line_counts: LineCounts,
+    /// The lines spanned by unsafe blocks.
+    pub unsafe_lines: i32,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub unsafe_usage: UnsafeUsage,
+}
+
+/// The latest stats of one crate of a project (every member of a workspace).
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
+pub struct ProjectCrate {
+    pub project_id: i32,
+    pub name: String,
+    /// The crate directory, relative to the repository root.
+    pub path: String,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub line_counts: LineCounts,
+    /// The lines spanned by unsafe blocks. The unsafe constructs are counted by kind
+    /// in `unsafe_usage`.
+    pub unsafe_lines: i32,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub unsafe_usage: UnsafeUsage,
+    /// The percentage of documented unsafe blocks and functions, if there are any.
+    pub safety_coverage: Option<f64>,
+    pub production_code_lines: i32,
+    pub production_unsafe_lines: i32,
+    /// The level of the `unsafe_code` lint: allow, warn, deny or forbid.
+    pub unsafe_code_lint: String,
+    pub created_at: String,
+    pub(crate) updated_at: String,
+}
+
+/// An unsafe site of the latest analysis of a project,
+/// with a link to its source on the provider.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub struct UnsafeSiteDTO {
+    pub file_path: String,
+    pub start_line: i32,
+    pub end_line: i32,
+    pub kind: String,
+    pub item_path: String,
+    pub category: String,
+    pub documented: Option<bool>,
+    pub commit_sha: Option<String>,
+    pub permalink: String,
+}
+
+impl UnsafeSiteDTO {
+    /// Builds the link to a line range of a file, in the format of the provider.
+    /// Links to the default branch when the analyzed commit is unknown.
+    pub fn permalink(
+        project: &ProjectWithUrl,
+        commit_sha: Option<&str>,
+        file_path: &str,
+        start_line: i32,
+        end_line: i32,
+    ) -> String {
+        let base = format!(
+            "{}/{}/{}",
+            project.url.trim_end_matches('/'),
+            project.namespace,
+            project.name
+        );
+        let revision = commit_sha.unwrap_or("HEAD");
+        if project.url.contains("gitlab") {
+            return format!("{base}/-/blob/{revision}/{file_path}#L{start_line}-{end_line}");
+        }
+        if project.url.contains("bitbucket") {
+            return format!("{base}/src/{revision}/{file_path}#lines-{start_line}:{end_line}");
+        }
+        return format!("{base}/blob/{revision}/{file_path}#L{start_line}-L{end_line}");
+    }
+}
+
+/// The safety justification coverage of one file of a project.
+#[derive(Debug, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
+pub struct FileSafetyCoverage {
+    pub file_path: String,
+    pub documented_unsafe: i32,
+    pub undocumented_unsafe: i32,
+    pub safety_coverage: Option<f64>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct ProjectStatsWithMeta {
     pub projectStats: Vec<ProjectStatsDTO>,
     pub meta: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testUnsafeSitePermalink() {
+        let project = |url: &str| ProjectWithUrl {
+            id: 1,
+            namespace: "tokio-rs".to_owned(),
+            name: "tokio".to_owned(),
+            url: url.to_owned(),
+        };
+        let permalink =
+            |url: &str, sha| UnsafeSiteDTO::permalink(&project(url), sha, "src/lib.rs", 3, 7);
+        assert_eq!(
+            permalink("https://github.com", Some("abc123")),
+            "https://github.com/tokio-rs/tokio/blob/abc123/src/lib.rs#L3-L7"
+        );
+        assert_eq!(
+            permalink("https://github.com/", None),
+            "https://github.com/tokio-rs/tokio/blob/HEAD/src/lib.rs#L3-L7"
+        );
+        assert_eq!(
+            permalink("https://gitlab.com", Some("abc123")),
+            "https://gitlab.com/tokio-rs/tokio/-/blob/abc123/src/lib.rs#L3-7"
+        );
+        assert_eq!(
+            permalink("https://bitbucket.org", Some("abc123")),
+            "https://bitbucket.org/tokio-rs/tokio/src/abc123/src/lib.rs#lines-3:7"
+        );
+    }
+
+    #[test]
+    fn testProjectPatch() {
+        let project = Project {
+            id: 1,
+            provider_id: 1,
+            namespace: "tokio-rs".to_owned(),
+            name: "tokio".to_owned(),
+        };
+        let patch = ProjectPatch {
+            name: Some("mio".to_owned()),
+            ..Default::default()
+        };
+        let input = patch.apply(project);
+        assert_eq!(
+            (
+                input.provider_id,
+                input.namespace.as_str(),
+                input.name.as_str()
+            ),
+            (1, "tokio-rs", "mio")
+        );
+        assert_eq!(input.validate(), Ok(()));
+
+        for name in ["", ".git", "a/b", "a; rm -rf /", &"a".repeat(256)] {
+            let input = ProjectInput {
+                provider_id: 1,
+                namespace: "tokio-rs".to_owned(),
+                name: name.to_owned(),
+            };
+            assert!(input.validate().is_err(), "{name}");
+        }
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
 pub struct Provider {
     pub(crate) id: i32,
     pub url: String,
@@ -12,3 +12,63 @@ impl Provider {
         };
     }
 }
+
+/// The body of `POST /providers` and `PUT /providers/:id`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderInput {
+    /// The base url of the repositories, e.g. `https://github.com`.
+    pub url: String,
+}
+
+impl ProviderInput {
+    pub fn validate(&self) -> Result<(), String> {
+        let host = self.url.strip_prefix("https://").unwrap_or_default();
+        if host.is_empty() || self.url.len() > 255 || self.url.contains(char::is_whitespace) {
+            return Err(format!("Invalid provider url: {:?}", self.url));
+        }
+        return Ok(());
+    }
+}
+
+/// The body of `PATCH /providers/:id`. The missing fields are kept.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderPatch {
+    pub url: Option<String>,
+}
+
+impl ProviderPatch {
+    pub fn apply(self, provider: Provider) -> ProviderInput {
+        return ProviderInput {
+            url: self.url.unwrap_or(provider.url),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testProviderInputValidate() {
+        for url in ["https://github.com", "https://git.example.org/mirrors"] {
+            let input = ProviderInput {
+                url: url.to_owned(),
+            };
+            assert_eq!(input.validate(), Ok(()));
+        }
+        for url in [
+            "",
+            "github.com",
+            "https://",
+            "http://github.com",
+            "https://a b",
+        ] {
+            let input = ProviderInput {
+                url: url.to_owned(),
+            };
+            assert!(input.validate().is_err(), "{url}");
+        }
+    }
+}
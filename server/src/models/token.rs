@@ -0,0 +1,44 @@
+/// What an API token may do. Every scope includes the ones before it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenScope {
+    /// Trigger stats updates and backfills, e.g. from a cron job.
+    Update,
+    /// Everything, including flushing the cache and importing projects.
+    Admin,
+}
+
+impl ApiTokenScope {
+    pub const ALL: [ApiTokenScope; 2] = [ApiTokenScope::Update, ApiTokenScope::Admin];
+
+    pub fn asStr(&self) -> &'static str {
+        return match self {
+            ApiTokenScope::Update => "update",
+            ApiTokenScope::Admin => "admin",
+        };
+    }
+}
+
+impl std::str::FromStr for ApiTokenScope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return ApiTokenScope::ALL
+            .into_iter()
+            .find(|v| v.asStr() == value)
+            .ok_or_else(|| format!("Unknown token scope: {value}"));
+    }
+}
+
+/// An API token, without its secret, which is only stored hashed.
+#[derive(Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub scope: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
@@ -0,0 +1,241 @@
+//! The cache of the API responses. `RedisService` implements it for the server, and
+//! `InMemoryCache` in the process when Redis is unavailable at startup. The values expire
+//! after the time to live of the cache, and are invalidated by their tags before that.
+
+use crate::models::pagination::{projectStatsCacheTag, PROJECT_STATS_CACHE_VERSIONS};
+use crate::services::repository::Repository;
+use async_trait::async_trait;
+use lru::LruCache;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub enum CacheError {
+    /// The cache is down and is not retried yet. The callers skip it without logging.
+    Unavailable,
+    /// A command failed, e.g. because the connection was lost.
+    Failed(String),
+}
+
+impl std::fmt::Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            CacheError::Unavailable => write!(f, "The cache is unavailable"),
+            CacheError::Failed(v) => write!(f, "{v}"),
+        };
+    }
+}
+
+#[async_trait]
+pub trait Cache: Send + Sync {
+    /// Returns `None` if the key is missing or expired, which is not an error.
+    async fn getKey(&self, key: &str) -> Result<Option<String>, CacheError>;
+
+    /// Stores the value for the time to live of the cache, or until one of the tags
+    /// is invalidated.
+    async fn setKey(&self, key: &str, value: &str, tags: &[String]) -> Result<(), CacheError>;
+
+    /// Removes the values stored with any of the tags.
+    async fn invalidateTags(&self, tags: &[String]) -> Result<(), CacheError>;
+
+    /// Removes every key.
+    async fn flush(&self) -> Result<(), CacheError>;
+}
+
+/// Removes the cached `/project-stats` pages that a change to the projects of the providers
+/// may affect, i.e. the pages of the providers and the pages of every provider. A failure is
+/// logged, and the pages then expire with their time to live.
+pub async fn invalidateProjectStats(
+    cache: &dyn Cache,
+    repository: &dyn Repository,
+    providerIds: &[i32],
+) {
+    let tags: Vec<String> = PROJECT_STATS_CACHE_VERSIONS
+        .into_iter()
+        .flat_map(|version| {
+            return providerIds
+                .iter()
+                .map(|id| projectStatsCacheTag(version, Some(*id)))
+                .chain([projectStatsCacheTag(version, None)]);
+        })
+        .collect();
+    if let Err(CacheError::Failed(e)) = cache.invalidateTags(&tags).await {
+        let error = format!("invalidateProjectStats(): Cache::invalidateTags() failed: {e}");
+        let _ = repository.logError(&error).await;
+    }
+}
+
+struct Entry {
+    value: String,
+    expiresAt: Instant,
+    tags: Vec<String>,
+}
+
+#[derive(Default)]
+struct Tags {
+    /// The keys of every tag.
+    keys: HashMap<String, HashSet<String>>,
+}
+
+impl Tags {
+    fn add(&mut self, key: &str, tags: &[String]) {
+        for tag in tags {
+            self.keys
+                .entry(tag.clone())
+                .or_default()
+                .insert(key.to_owned());
+        }
+    }
+
+    fn remove(&mut self, key: &str, tags: &[String]) {
+        for tag in tags {
+            if let Some(keys) = self.keys.get_mut(tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.keys.remove(tag);
+                }
+            }
+        }
+    }
+}
+
+/// A cache in the process, which evicts the least recently used key once it is full.
+pub struct InMemoryCache {
+    ttl: Duration,
+    entries: Mutex<(LruCache<String, Entry>, Tags)>,
+}
+
+impl InMemoryCache {
+    pub fn new(capacity: usize, ttl: Duration) -> InMemoryCache {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        return InMemoryCache {
+            ttl,
+            entries: Mutex::new((LruCache::new(capacity), Tags::default())),
+        };
+    }
+}
+
+#[async_trait]
+impl Cache for InMemoryCache {
+    async fn getKey(&self, key: &str) -> Result<Option<String>, CacheError> {
+        let mut guard = self.entries.lock().unwrap();
+        let (entries, tags) = &mut *guard;
+        let expired = match entries.get(key) {
+            None => return Ok(None),
+            Some(entry) if entry.expiresAt > Instant::now() => {
+                return Ok(Some(entry.value.clone()));
+            }
+            Some(_) => entries.pop(key),
+        };
+        if let Some(entry) = expired {
+            tags.remove(key, &entry.tags);
+        }
+        return Ok(None);
+    }
+
+    async fn setKey(&self, key: &str, value: &str, tags: &[String]) -> Result<(), CacheError> {
+        let mut guard = self.entries.lock().unwrap();
+        let (entries, keysByTag) = &mut *guard;
+        let entry = Entry {
+            value: value.to_owned(),
+            expiresAt: Instant::now() + self.ttl,
+            tags: tags.to_vec(),
+        };
+        // The replaced or evicted entry.
+        if let Some((removedKey, removed)) = entries.push(key.to_owned(), entry) {
+            keysByTag.remove(&removedKey, &removed.tags);
+        }
+        keysByTag.add(key, tags);
+        return Ok(());
+    }
+
+    async fn invalidateTags(&self, tags: &[String]) -> Result<(), CacheError> {
+        let mut guard = self.entries.lock().unwrap();
+        let (entries, keysByTag) = &mut *guard;
+        for tag in tags {
+            for key in keysByTag.keys.remove(tag).unwrap_or_default() {
+                if let Some(entry) = entries.pop(&key) {
+                    keysByTag.remove(&key, &entry.tags);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    async fn flush(&self) -> Result<(), CacheError> {
+        let mut guard = self.entries.lock().unwrap();
+        guard.0.clear();
+        guard.1 = Tags::default();
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::memory::InMemoryRepository;
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        return values.iter().map(|v| v.to_string()).collect();
+    }
+
+    #[tokio::test]
+    async fn testInMemoryCacheEviction() {
+        let cache = InMemoryCache::new(2, Duration::from_secs(60));
+
+        // Miss
+        assert!(matches!(cache.getKey("a").await, Ok(None)));
+
+        // The least recently used key is evicted.
+        cache.setKey("a", "1", &[]).await.unwrap();
+        cache.setKey("b", "2", &tags(&["t"])).await.unwrap();
+        assert_eq!(cache.getKey("a").await.unwrap().as_deref(), Some("1"));
+        cache.setKey("c", "3", &[]).await.unwrap();
+        assert!(cache.getKey("b").await.unwrap().is_none());
+        assert_eq!(cache.getKey("a").await.unwrap().as_deref(), Some("1"));
+        assert_eq!(cache.getKey("c").await.unwrap().as_deref(), Some("3"));
+        // The tags of the evicted key are dropped with it.
+        assert!(cache.entries.lock().unwrap().1.keys.is_empty());
+
+        // Flush
+        cache.flush().await.unwrap();
+        assert!(cache.getKey("a").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn testInMemoryCacheExpiry() {
+        let cache = InMemoryCache::new(10, Duration::from_millis(50));
+        cache.setKey("a", "1", &tags(&["t"])).await.unwrap();
+        assert_eq!(cache.getKey("a").await.unwrap().as_deref(), Some("1"));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(cache.getKey("a").await.unwrap().is_none());
+        assert!(cache.entries.lock().unwrap().1.keys.is_empty());
+    }
+
+    #[tokio::test]
+    async fn testInvalidateProjectStats() {
+        let cache = InMemoryCache::new(10, Duration::from_secs(60));
+        let repository = InMemoryRepository::new();
+        let pages = [
+            ("all", projectStatsCacheTag("v1", None)),
+            ("provider1", projectStatsCacheTag("v1", Some(1))),
+            ("provider2", projectStatsCacheTag("v1", Some(2))),
+        ];
+        for (key, tag) in &pages {
+            cache
+                .setKey(key, "page", std::slice::from_ref(tag))
+                .await
+                .unwrap();
+        }
+
+        // Only the pages of the provider, and of every provider, are removed.
+        invalidateProjectStats(&cache, &repository, &[1]).await;
+        assert!(cache.getKey("all").await.unwrap().is_none());
+        assert!(cache.getKey("provider1").await.unwrap().is_none());
+        assert!(cache.getKey("provider2").await.unwrap().is_some());
+        assert!(repository.errors().is_empty());
+    }
+}
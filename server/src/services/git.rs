@@ -0,0 +1,593 @@
+use crate::{
+    analysis::limits::AnalysisLimits,
+    models::project::{isValidRepositoryName, ProjectWithUrl},
+};
+use chrono::TimeZone;
+use git2::{build::CheckoutBuilder, build::RepoBuilder, FetchOptions, RemoteCallbacks, Repository};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+/// libgit2 turns a shallow repository into a complete one when fetching with this depth.
+const UNSHALLOW_DEPTH: i32 = i32::MAX;
+
+#[derive(Debug)]
+pub enum GitError {
+    /// A namespace or name that is not safe to use as a directory name.
+    InvalidName(String),
+    Io(std::io::Error),
+    Clone {
+        url: String,
+        source: git2::Error,
+    },
+    Fetch {
+        url: String,
+        source: git2::Error,
+    },
+    Open {
+        path: PathBuf,
+        source: git2::Error,
+    },
+    UnknownRevision {
+        revision: String,
+        source: git2::Error,
+    },
+    Checkout(git2::Error),
+    /// The tags of a repository could not be listed.
+    Tags(git2::Error),
+    /// The deadline of the limits passed during a clone or a fetch.
+    TimedOut,
+    /// A clone or a fetch downloaded more bytes than the limits allow.
+    TooLarge {
+        limit: u64,
+    },
+}
+
+impl std::fmt::Display for GitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            GitError::InvalidName(v) => write!(f, "Invalid repository name: {v:?}"),
+            GitError::Io(e) => write!(f, "Git workspace error: {e}"),
+            GitError::Clone { url, source } => write!(f, "Failed to clone {url}: {source}"),
+            GitError::Fetch { url, source } => write!(f, "Failed to fetch {url}: {source}"),
+            GitError::Open { path, source } => {
+                write!(f, "Failed to open {}: {source}", path.display())
+            }
+            GitError::UnknownRevision { revision, source } => {
+                write!(f, "Unknown revision {revision}: {source}")
+            }
+            GitError::Checkout(e) => write!(f, "Failed to checkout: {e}"),
+            GitError::Tags(e) => write!(f, "Failed to list the tags: {e}"),
+            GitError::TimedOut => write!(f, "The clone timed out"),
+            GitError::TooLarge { limit } => {
+                write!(f, "The repository is larger than {limit} bytes")
+            }
+        };
+    }
+}
+
+impl std::error::Error for GitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            GitError::InvalidName(_) | GitError::TimedOut | GitError::TooLarge { .. } => None,
+            GitError::Io(e) => Some(e),
+            GitError::Clone { source, .. }
+            | GitError::Fetch { source, .. }
+            | GitError::Open { source, .. }
+            | GitError::UnknownRevision { source, .. }
+            | GitError::Checkout(source)
+            | GitError::Tags(source) => Some(source),
+        };
+    }
+}
+
+impl From<std::io::Error> for GitError {
+    fn from(e: std::io::Error) -> Self {
+        return GitError::Io(e);
+    }
+}
+
+/// A commit of a repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    pub sha: String,
+    /// The checked out branch. `None` on a detached HEAD or for other revisions.
+    pub branch: Option<String>,
+    /// The committer date, in RFC 3339.
+    pub date: String,
+}
+
+/// Keeps the repositories of the projects under a workspace directory.
+#[derive(Clone)]
+pub struct GitService {
+    pub workspaceDir: PathBuf,
+    /// The lock of every project directory that was used.
+    locks: Arc<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+/// Held while a project directory is used. Shared with the blocking tasks that use it,
+/// so that the directory stays locked until the last of them is done.
+pub type ProjectLock = Arc<OwnedMutexGuard<()>>;
+
+impl GitService {
+    /// Uses `workspaceDir`, else the `GIT_WORKSPACE_DIR` env var, else `/tmp/rust_projects`.
+    pub fn new(workspaceDir: Option<PathBuf>) -> Self {
+        let workspaceDir = workspaceDir.unwrap_or_else(|| {
+            let workspaceDir = std::env::var("GIT_WORKSPACE_DIR")
+                .unwrap_or_else(|_| "/tmp/rust_projects".to_owned());
+            return PathBuf::from(workspaceDir);
+        });
+        return Self {
+            workspaceDir,
+            locks: Default::default(),
+        };
+    }
+
+    /// Waits until no update or backfill uses the directory of the project, and locks it,
+    /// so that two syncs never change the same checkout at once.
+    pub async fn lockProject(&self, project: &ProjectWithUrl) -> Result<ProjectLock, GitError> {
+        let dir = self.projectDir(project)?;
+        let lock = self.locks.lock().unwrap().entry(dir).or_default().clone();
+        return Ok(Arc::new(lock.lock_owned().await));
+    }
+
+    /// The directory of the repository of a project: `<workspace>/<namespace>/<name>`.
+    pub fn projectDir(&self, project: &ProjectWithUrl) -> Result<PathBuf, GitError> {
+        for part in [&project.namespace, &project.name] {
+            if !isValidRepositoryName(part) {
+                return Err(GitError::InvalidName(part.to_owned()));
+            }
+        }
+        return Ok(self
+            .workspaceDir
+            .join(&project.namespace)
+            .join(&project.name));
+    }
+
+    /// Brings the default branch of a project up to date, with a shallow clone
+    /// for new repositories. Returns the directory of the repository.
+    pub fn sync(
+        &self,
+        project: &ProjectWithUrl,
+        limits: &AnalysisLimits,
+    ) -> Result<PathBuf, GitError> {
+        return self.syncRepository(project, limits, false);
+    }
+
+    /// Like `sync`, but with the complete history and all the tags.
+    pub fn syncHistory(
+        &self,
+        project: &ProjectWithUrl,
+        limits: &AnalysisLimits,
+    ) -> Result<PathBuf, GitError> {
+        return self.syncRepository(project, limits, true);
+    }
+
+    fn syncRepository(
+        &self,
+        project: &ProjectWithUrl,
+        limits: &AnalysisLimits,
+        history: bool,
+    ) -> Result<PathBuf, GitError> {
+        let dir = self.projectDir(project)?;
+        let url = format!(
+            "{}/{}/{}.git",
+            project.url.trim_end_matches('/'),
+            project.namespace,
+            project.name
+        );
+        if limits.isExpired() {
+            return Err(GitError::TimedOut);
+        }
+
+        // The transfer is aborted as soon as it exceeds a limit.
+        let timedOut = Cell::new(false);
+        let tooLarge = Cell::new(false);
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.transfer_progress(|progress| {
+            if limits.isExpired() {
+                timedOut.set(true);
+                return false;
+            }
+            let receivedBytes = progress.received_bytes() as u64;
+            if limits.maxRepositoryBytes.is_some_and(|v| receivedBytes > v) {
+                tooLarge.set(true);
+                return false;
+            }
+            return true;
+        });
+        let limitError = |error: GitError| {
+            if timedOut.get() {
+                return GitError::TimedOut;
+            }
+            if let (true, Some(limit)) = (tooLarge.get(), limits.maxRepositoryBytes) {
+                return GitError::TooLarge { limit };
+            }
+            return error;
+        };
+        let mut fetchOptions = FetchOptions::new();
+        fetchOptions.remote_callbacks(callbacks);
+        if history {
+            fetchOptions.download_tags(git2::AutotagOption::All);
+        }
+
+        if !dir.join(".git").is_dir() {
+            // A leftover of a failed clone.
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir)?;
+            }
+            std::fs::create_dir_all(&dir)?;
+            if !history {
+                fetchOptions.depth(1);
+            }
+            RepoBuilder::new()
+                .fetch_options(fetchOptions)
+                .clone(&url, &dir)
+                .map_err(|source| {
+                    let _ = std::fs::remove_dir_all(&dir);
+                    return limitError(GitError::Clone {
+                        url: url.clone(),
+                        source,
+                    });
+                })?;
+            return Ok(dir);
+        }
+
+        let repository = open(&dir)?;
+        if repository.is_shallow() {
+            fetchOptions.depth(if history { UNSHALLOW_DEPTH } else { 1 });
+        }
+        let branch = repository
+            .head()
+            .ok()
+            .filter(|v| v.is_branch())
+            .and_then(|v| v.shorthand().map(|v| v.to_owned()))
+            .unwrap_or_else(|| "HEAD".to_owned());
+        let fetchError = |source| GitError::Fetch {
+            url: url.clone(),
+            source,
+        };
+        let mut remote = repository.find_remote("origin").map_err(fetchError)?;
+        remote
+            .fetch(
+                &[format!("+refs/heads/{branch}:refs/remotes/origin/{branch}")],
+                Some(&mut fetchOptions),
+                None,
+            )
+            .map_err(|source| limitError(fetchError(source)))?;
+        let target = repository
+            .find_reference(&format!("refs/remotes/origin/{branch}"))
+            .and_then(|v| v.peel_to_commit())
+            .map_err(fetchError)?;
+        repository
+            .reset(
+                target.as_object(),
+                git2::ResetType::Hard,
+                Some(CheckoutBuilder::new().force()),
+            )
+            .map_err(GitError::Checkout)?;
+        return Ok(dir);
+    }
+}
+
+fn open(dir: &Path) -> Result<Repository, GitError> {
+    return Repository::open(dir).map_err(|source| GitError::Open {
+        path: dir.to_path_buf(),
+        source,
+    });
+}
+
+fn findCommit<'a>(
+    repository: &'a Repository,
+    revision: &str,
+) -> Result<git2::Commit<'a>, GitError> {
+    return repository
+        .revparse_single(revision)
+        .and_then(|v| v.peel_to_commit())
+        .map_err(|source| GitError::UnknownRevision {
+            revision: revision.to_owned(),
+            source,
+        });
+}
+
+fn formatTime(time: git2::Time) -> String {
+    return chrono::FixedOffset::east_opt(time.offset_minutes() * 60)
+        .and_then(|v| v.timestamp_opt(time.seconds(), 0).single())
+        .map(|v| v.to_rfc3339())
+        .unwrap_or_default();
+}
+
+/// Returns the commit checked out in the repository at `dir`.
+pub fn head(dir: &Path) -> Result<CommitInfo, GitError> {
+    let repository = open(dir)?;
+    let mut commit = commitInfo(&repository, "HEAD")?;
+    commit.branch = repository
+        .head()
+        .ok()
+        .filter(|v| v.is_branch())
+        .and_then(|v| v.shorthand().map(|v| v.to_owned()));
+    return Ok(commit);
+}
+
+/// Returns the commit that `revision` (a sha, tag or branch) points to.
+pub fn commit(dir: &Path, revision: &str) -> Result<CommitInfo, GitError> {
+    return commitInfo(&open(dir)?, revision);
+}
+
+fn commitInfo(repository: &Repository, revision: &str) -> Result<CommitInfo, GitError> {
+    let commit = findCommit(repository, revision)?;
+    return Ok(CommitInfo {
+        sha: commit.id().to_string(),
+        branch: None,
+        date: formatTime(commit.committer().when()),
+    });
+}
+
+/// Returns the latest `limit` tags, from the oldest to the newest.
+/// Annotated tags are dated by their tagger, the others by their commit.
+pub fn tags(dir: &Path, limit: usize) -> Result<Vec<String>, GitError> {
+    let repository = open(dir)?;
+    let names = repository.tag_names(None).map_err(GitError::Tags)?;
+    let mut tags: Vec<(i64, String)> = names
+        .iter()
+        .flatten()
+        .filter_map(|name| {
+            let object = repository.revparse_single(name).ok()?;
+            let time = match object.as_tag().and_then(|v| v.tagger()) {
+                Some(tagger) => tagger.when(),
+                None => object.peel_to_commit().ok()?.committer().when(),
+            };
+            return Some((time.seconds(), name.to_owned()));
+        })
+        .collect();
+    tags.sort();
+    let tags: Vec<String> = tags.into_iter().map(|v| v.1).collect();
+    return Ok(tags[tags.len().saturating_sub(limit)..].to_vec());
+}
+
+/// Returns every `every`th commit of the first parent history of HEAD,
+/// starting from HEAD, up to `limit` commits, from the oldest to the newest.
+pub fn commits(dir: &Path, every: usize, limit: usize) -> Result<Vec<String>, GitError> {
+    let repository = open(dir)?;
+    let revisionError = |source| GitError::UnknownRevision {
+        revision: "HEAD".to_owned(),
+        source,
+    };
+    let mut revwalk = repository.revwalk().map_err(revisionError)?;
+    revwalk.push_head().map_err(revisionError)?;
+    revwalk.simplify_first_parent().map_err(revisionError)?;
+    let mut commits: Vec<String> = revwalk
+        .flatten()
+        .step_by(every.max(1))
+        .take(limit)
+        .map(|v| v.to_string())
+        .collect();
+    commits.reverse();
+    return Ok(commits);
+}
+
+/// Writes the files of `revision` to `destination`, without touching the working tree.
+pub fn export(dir: &Path, revision: &str, destination: &Path) -> Result<(), GitError> {
+    let repository = open(dir)?;
+    let commit = findCommit(&repository, revision)?;
+    std::fs::create_dir_all(destination)?;
+    repository
+        .checkout_tree(
+            commit.as_object(),
+            Some(
+                CheckoutBuilder::new()
+                    .target_dir(destination)
+                    .update_index(false)
+                    .force(),
+            ),
+        )
+        .map_err(GitError::Checkout)?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    }
+
+    /// Creates `<dir>/remote/test/history.git`, a bare repository with five
+    /// commits, where the odd ones are tagged.
+    fn createRemote(dir: &Path) -> ProjectWithUrl {
+        let source = dir.join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        git(&source, &["init", "-q", "-b", "main"]);
+        for i in 1..=5 {
+            std::fs::write(source.join("lib.rs"), format!("// Version {i}.\n")).unwrap();
+            git(&source, &["add", "lib.rs"]);
+            git(&source, &["commit", "-q", "-m", &format!("Version {i}")]);
+            if i % 2 == 1 {
+                git(&source, &["tag", "-a", &format!("v{i}"), "-m", "Release"]);
+            }
+        }
+        let remote = dir.join("remote/test/history.git");
+        git(
+            dir,
+            &[
+                "clone",
+                "-q",
+                "--bare",
+                &source.to_string_lossy(),
+                &remote.to_string_lossy(),
+            ],
+        );
+        return ProjectWithUrl {
+            id: 1,
+            namespace: "test".to_owned(),
+            name: "history".to_owned(),
+            url: format!("file://{}", dir.join("remote").display()),
+        };
+    }
+
+    fn createTestDir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("git_{name}_{}", utils::getTimestamp()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    #[test]
+    fn testSync() {
+        let dir = createTestDir("sync");
+        let project = createRemote(&dir);
+        let gitService = GitService::new(Some(dir.join("workspace")));
+
+        let projectDir = gitService
+            .sync(&project, &AnalysisLimits::default())
+            .unwrap();
+        assert_eq!(projectDir, dir.join("workspace/test/history"));
+        let first = head(&projectDir).unwrap();
+        assert_eq!(first.branch.as_deref(), Some("main"));
+        assert!(chrono::DateTime::parse_from_rfc3339(&first.date).is_ok());
+
+        // A new commit on the remote is pulled.
+        let source = dir.join("source");
+        std::fs::write(source.join("lib.rs"), "// Version 6.\n").unwrap();
+        git(&source, &["commit", "-q", "-am", "Version 6"]);
+        git(
+            &source,
+            &["push", "-q", "../remote/test/history.git", "main"],
+        );
+        gitService
+            .sync(&project, &AnalysisLimits::default())
+            .unwrap();
+        let second = head(&projectDir).unwrap();
+        assert_ne!(first.sha, second.sha);
+        let content = std::fs::read_to_string(projectDir.join("lib.rs")).unwrap();
+        assert_eq!(content, "// Version 6.\n");
+
+        gitService
+            .syncHistory(&project, &AnalysisLimits::default())
+            .unwrap();
+        assert_eq!(tags(&projectDir, 2).unwrap(), vec!["v3", "v5"]);
+        assert_eq!(commits(&projectDir, 1, 100).unwrap().len(), 6);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn testLockProject() {
+        let gitService = GitService::new(Some(std::env::temp_dir().join("git_locks")));
+        let project = |name: &str| ProjectWithUrl {
+            id: 1,
+            namespace: "test".to_owned(),
+            name: name.to_owned(),
+            url: "https://example.com".to_owned(),
+        };
+        let locked = project("locked");
+        let lock = gitService.lockProject(&locked).await.unwrap();
+
+        // Another project is not locked.
+        let other = gitService.lockProject(&project("other")).await;
+        assert!(other.is_ok());
+        // The same project waits until every holder of the lock is done.
+        let shared = lock.clone();
+        drop(lock);
+        let waiting = gitService.lockProject(&locked);
+        tokio::pin!(waiting);
+        let timeout = std::time::Duration::from_millis(50);
+        assert!(tokio::time::timeout(timeout, &mut waiting).await.is_err());
+        drop(shared);
+        assert!(tokio::time::timeout(timeout, waiting).await.is_ok());
+    }
+
+    #[test]
+    fn testSyncErrors() {
+        let dir = createTestDir("errors");
+        let gitService = GitService::new(Some(dir.clone()));
+        let project = |namespace: &str, name: &str| ProjectWithUrl {
+            id: 1,
+            namespace: namespace.to_owned(),
+            name: name.to_owned(),
+            url: format!("file://{}", dir.display()),
+        };
+
+        for (namespace, name) in [("test", "a; rm -rf /"), ("..", "foo"), ("test", "")] {
+            let result = gitService.sync(&project(namespace, name), &AnalysisLimits::default());
+            assert!(matches!(result, Err(GitError::InvalidName(_))));
+        }
+        let result = gitService.sync(&project("test", "missing"), &AnalysisLimits::default());
+        assert!(matches!(result, Err(GitError::Clone { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn testSyncLimits() {
+        let dir = createTestDir("limits");
+        let project = createRemote(&dir);
+        let gitService = GitService::new(Some(dir.join("workspace")));
+
+        let tooLarge = AnalysisLimits {
+            maxRepositoryBytes: Some(1),
+            ..Default::default()
+        };
+        let result = gitService.sync(&project, &tooLarge);
+        assert!(matches!(result, Err(GitError::TooLarge { limit: 1 })));
+        // The partial clone is removed.
+        assert!(!gitService.projectDir(&project).unwrap().exists());
+
+        let timedOut = AnalysisLimits {
+            deadline: Some(std::time::Instant::now()),
+            ..Default::default()
+        };
+        let result = gitService.sync(&project, &timedOut);
+        assert!(matches!(result, Err(GitError::TimedOut)));
+
+        let withinLimits = AnalysisLimits {
+            deadline: Some(std::time::Instant::now() + std::time::Duration::from_secs(60)),
+            maxRepositoryBytes: Some(1 << 20),
+            ..Default::default()
+        };
+        assert!(gitService.sync(&project, &withinLimits).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn testRevisions() {
+        let dir = createTestDir("revisions");
+        let project = createRemote(&dir);
+        let projectDir = GitService::new(Some(dir.join("workspace")))
+            .syncHistory(&project, &AnalysisLimits::default())
+            .unwrap();
+
+        assert_eq!(tags(&projectDir, 10).unwrap(), vec!["v1", "v3", "v5"]);
+        let commits = commits(&projectDir, 2, 10).unwrap();
+        assert_eq!(commits.len(), 3);
+        assert_eq!(commits[2], head(&projectDir).unwrap().sha);
+        assert_eq!(super::commits(&projectDir, 1, 2).unwrap().len(), 2);
+
+        let destination = dir.join("export");
+        export(&projectDir, "v3", &destination).unwrap();
+        let source = std::fs::read_to_string(destination.join("lib.rs")).unwrap();
+        assert_eq!(source, "// Version 3.\n");
+        // The working tree is left alone.
+        let source = std::fs::read_to_string(projectDir.join("lib.rs")).unwrap();
+        assert_eq!(source, "// Version 5.\n");
+        assert_eq!(commit(&projectDir, "v3").unwrap().branch, None);
+        assert!(matches!(
+            export(&projectDir, "v4", &dir.join("missing")),
+            Err(GitError::UnknownRevision { .. })
+        ));
+
+        git(&projectDir, &["checkout", "-q", "--detach"]);
+        assert_eq!(head(&projectDir).unwrap().branch, None);
+        assert!(matches!(head(&dir), Err(GitError::Open { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,658 @@
+use crate::analysis::{self, limits::AnalysisLimits, AnalysisError};
+use crate::models::{
+    backfill::BackfillOptions,
+    job::{ClaimedJobProject, JobEvent, JobEventKind, JobProjectStatus},
+};
+use crate::services::{
+    cache::{invalidateProjectStats, Cache},
+    git::{self, GitError, GitService},
+    postgres::DatabaseError,
+    repository::{JobStore, Repository},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+pub const UPDATE_PROJECTS_STATS: &str = "update_projects_stats";
+pub const SCHEDULED_UPDATE: &str = "scheduled_update";
+pub const BACKFILL_PROJECT_STATS: &str = "backfill_project_stats";
+
+#[derive(Debug, Clone)]
+pub struct JobOptions {
+    /// How many projects are updated at the same time.
+    pub workers: usize,
+    /// How many times a project is tried before it is marked as failed.
+    pub maxAttempts: i32,
+    /// The delay before the first retry, doubled on every following one.
+    pub retryDelay: Duration,
+    /// The longest delay before a retry.
+    pub maxRetryDelay: Duration,
+    /// How often idle workers look for due retries and for jobs of other instances.
+    pub pollInterval: Duration,
+    /// When a running project is considered abandoned by a crashed worker. A backfill
+    /// ends before it.
+    pub staleAfter: Duration,
+    /// How often the scheduler queues the projects that are due. `None` disables it.
+    pub scheduleInterval: Option<Duration>,
+    /// How long the clone and the analysis of a project may take.
+    pub analysisTimeout: Option<Duration>,
+    pub maxRepositoryBytes: Option<u64>,
+    pub maxFiles: Option<usize>,
+}
+
+impl JobOptions {
+    /// Reads `JOB_WORKERS`, `JOB_MAX_ATTEMPTS`, `JOB_RETRY_DELAY_SECONDS`,
+    /// `JOB_MAX_RETRY_DELAY_SECONDS`, `JOB_POLL_INTERVAL_SECONDS`, `JOB_STALE_AFTER_SECONDS`,
+    /// `SCHEDULER_INTERVAL_SECONDS`, `ANALYSIS_TIMEOUT_SECONDS`,
+    /// `ANALYSIS_MAX_REPOSITORY_MB` and `ANALYSIS_MAX_FILES`, where 0 disables
+    /// the scheduler or the limit.
+    pub fn new() -> Self {
+        let var = |name: &str, default: u64| {
+            return std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default);
+        };
+        return Self {
+            workers: var("JOB_WORKERS", 4) as usize,
+            maxAttempts: var("JOB_MAX_ATTEMPTS", 3) as i32,
+            retryDelay: Duration::from_secs(var("JOB_RETRY_DELAY_SECONDS", 60)),
+            maxRetryDelay: Duration::from_secs(var("JOB_MAX_RETRY_DELAY_SECONDS", 6 * 3600)),
+            pollInterval: Duration::from_secs(var("JOB_POLL_INTERVAL_SECONDS", 5)),
+            staleAfter: Duration::from_secs(var("JOB_STALE_AFTER_SECONDS", 3600)),
+            scheduleInterval: Some(var("SCHEDULER_INTERVAL_SECONDS", 60))
+                .filter(|v| *v > 0)
+                .map(Duration::from_secs),
+            analysisTimeout: Some(var("ANALYSIS_TIMEOUT_SECONDS", 1800))
+                .filter(|v| *v > 0)
+                .map(Duration::from_secs),
+            maxRepositoryBytes: Some(var("ANALYSIS_MAX_REPOSITORY_MB", 2048))
+                .filter(|v| *v > 0)
+                .map(|v| v << 20),
+            maxFiles: Some(var("ANALYSIS_MAX_FILES", 100_000) as usize).filter(|v| *v > 0),
+        };
+    }
+
+    /// The delay before the retry that follows the given attempt, starting from 1.
+    pub fn retryDelayAfter(&self, attempts: i32) -> Duration {
+        let exponent = attempts.saturating_sub(1).max(0) as u32;
+        let factor = 2u32.checked_pow(exponent).unwrap_or(u32::MAX);
+        return self
+            .retryDelay
+            .saturating_mul(factor)
+            .min(self.maxRetryDelay);
+    }
+
+    /// The limits of an analysis that starts now.
+    pub fn limits(&self) -> AnalysisLimits {
+        return AnalysisLimits {
+            deadline: self.analysisTimeout.map(|v| Instant::now() + v),
+            maxRepositoryBytes: self.maxRepositoryBytes,
+            maxFiles: self.maxFiles,
+            ..Default::default()
+        };
+    }
+
+    /// The deadline of a backfill that starts now, shared by all its revisions. It
+    /// comes before `staleAfter`, so that no other worker claims the running project.
+    pub fn backfillDeadline(&self) -> Instant {
+        return Instant::now() + self.staleAfter - self.staleAfter / 10;
+    }
+
+    /// The limits of an analysis that starts now and ends by `deadline` at the latest.
+    pub fn limitsUntil(&self, deadline: Instant) -> AnalysisLimits {
+        let mut limits = self.limits();
+        limits.deadline = Some(limits.deadline.map_or(deadline, |v| v.min(deadline)));
+        return limits;
+    }
+}
+
+impl Default for JobOptions {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// Why the update of a project failed.
+enum UpdateError {
+    /// A limit was exceeded, which a retry would not change.
+    Limit(JobProjectStatus, String),
+    Other(String),
+}
+
+impl From<GitError> for UpdateError {
+    fn from(e: GitError) -> Self {
+        return match e {
+            GitError::TimedOut => UpdateError::Limit(JobProjectStatus::TimedOut, e.to_string()),
+            GitError::TooLarge { .. } => {
+                UpdateError::Limit(JobProjectStatus::TooLarge, e.to_string())
+            }
+            e => UpdateError::Other(e.to_string()),
+        };
+    }
+}
+
+impl From<AnalysisError> for UpdateError {
+    fn from(e: AnalysisError) -> Self {
+        return match e {
+            AnalysisError::TimedOut => {
+                UpdateError::Limit(JobProjectStatus::TimedOut, e.to_string())
+            }
+            AnalysisError::TooManyFiles { .. } => {
+                UpdateError::Limit(JobProjectStatus::TooManyFiles, e.to_string())
+            }
+            AnalysisError::TooLarge { .. } => {
+                UpdateError::Limit(JobProjectStatus::TooLarge, e.to_string())
+            }
+            e => UpdateError::Other(format!("Failed to analyze: {e}")),
+        };
+    }
+}
+
+impl From<String> for UpdateError {
+    fn from(e: String) -> Self {
+        return UpdateError::Other(e);
+    }
+}
+
+impl From<DatabaseError> for UpdateError {
+    fn from(e: DatabaseError) -> Self {
+        return UpdateError::Other(e.to_string());
+    }
+}
+
+/// Runs the jobs of the `jobs` table with a fixed number of workers.
+#[derive(Clone)]
+pub struct JobService {
+    repository: Arc<dyn Repository>,
+    /// The queue of the jobs, shared by the workers of every instance.
+    jobStore: Arc<dyn JobStore>,
+    gitService: GitService,
+    /// Whose stats pages are invalidated after an update.
+    cache: Arc<dyn Cache>,
+    options: JobOptions,
+    /// Wakes up the idle workers when a job is queued.
+    queued: Arc<tokio::sync::Notify>,
+    /// The job events of every instance, as received by `spawnEventListener`.
+    events: tokio::sync::broadcast::Sender<JobEvent>,
+}
+
+impl JobService {
+    pub fn new(
+        repository: Arc<dyn Repository>,
+        jobStore: Arc<dyn JobStore>,
+        gitService: GitService,
+        cache: Arc<dyn Cache>,
+        options: JobOptions,
+    ) -> Self {
+        return Self {
+            repository,
+            jobStore,
+            gitService,
+            cache,
+            options,
+            queued: Arc::new(tokio::sync::Notify::new()),
+            events: tokio::sync::broadcast::channel(1024).0,
+        };
+    }
+
+    /// Queues an update of the stats of the given projects and returns the job id.
+    pub async fn enqueueUpdate(&self, projectIds: &[i32]) -> Result<i32, DatabaseError> {
+        let id = self
+            .jobStore
+            .createJob(UPDATE_PROJECTS_STATS, projectIds)
+            .await?;
+        self.queued.notify_waiters();
+        return Ok(id);
+    }
+
+    /// Queues a backfill of the stats of a project from its past revisions and
+    /// returns the job id. At most 100 revisions are analyzed, 10 by default.
+    pub async fn enqueueBackfill(
+        &self,
+        projectId: i32,
+        options: &BackfillOptions,
+    ) -> Result<i32, DatabaseError> {
+        let options = BackfillOptions {
+            every: options.every,
+            limit: Some(options.limit.unwrap_or(10).min(100)),
+        };
+        let id = self
+            .jobStore
+            .createBackfillJob(BACKFILL_PROJECT_STATS, projectId, &options)
+            .await?;
+        self.queued.notify_waiters();
+        return Ok(id);
+    }
+
+    /// The limits of an analysis that starts now.
+    pub fn limits(&self) -> AnalysisLimits {
+        return self.options.limits();
+    }
+
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<JobEvent> {
+        return self.events.subscribe();
+    }
+
+    /// Forwards the job events that any instance sends through the job store to the subscribers.
+    pub fn spawnEventListener(&self) {
+        let jobService = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let result = jobService
+                    .jobStore
+                    .listenJobEvents(&jobService.events)
+                    .await;
+                if let Err(e) = result {
+                    let _ = jobService
+                        .repository
+                        .logError(&format!("JobService.spawnEventListener: {e}"))
+                        .await;
+                }
+                tokio::time::sleep(jobService.options.pollInterval).await;
+            }
+        });
+    }
+
+    pub fn spawnWorkers(&self) {
+        for _ in 0..self.options.workers.max(1) {
+            let jobService = self.clone();
+            tokio::spawn(async move { jobService.work().await });
+        }
+    }
+
+    /// Periodically queues the projects whose next run is due. Every instance
+    /// runs a scheduler, and the database makes sure that a project is queued once.
+    pub fn spawnScheduler(&self) {
+        let Some(interval) = self.options.scheduleInterval else {
+            return;
+        };
+        let jobService = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let result = jobService
+                    .jobStore
+                    .scheduleDueProjects(SCHEDULED_UPDATE)
+                    .await;
+                match result {
+                    Ok(Some(_)) => jobService.queued.notify_waiters(),
+                    Ok(None) => {}
+                    Err(e) => {
+                        let _ = jobService.repository.logError(&e.to_string()).await;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn work(&self) {
+        loop {
+            let claimed = self.jobStore.claimJobProject(self.options.staleAfter).await;
+            match claimed {
+                Ok(Some(jobProject)) => self.process(jobProject).await,
+                Ok(None) => {
+                    tokio::select! {
+                        _ = self.queued.notified() => {}
+                        _ = tokio::time::sleep(self.options.pollInterval) => {}
+                    }
+                }
+                Err(e) => {
+                    let _ = self.repository.logError(&e.to_string()).await;
+                    tokio::time::sleep(self.options.pollInterval).await;
+                }
+            }
+        }
+    }
+
+    async fn process(&self, jobProject: ClaimedJobProject) {
+        let id = jobProject.job_project_id;
+        let result = if jobProject.kind == BACKFILL_PROJECT_STATS {
+            self.backfillProject(&jobProject).await
+        } else {
+            self.updateProject(&jobProject).await
+        };
+        let (status, error) = match result {
+            Ok(status) => (status, None),
+            Err(UpdateError::Limit(status, e)) => (status, Some(e)),
+            Err(UpdateError::Other(e)) if jobProject.attempts < self.options.maxAttempts => {
+                let delay = self.options.retryDelayAfter(jobProject.attempts);
+                let result = self.jobStore.retryJobProject(id, &e, delay).await;
+                if let Err(e) = result {
+                    let _ = self.repository.logError(&e.to_string()).await;
+                }
+                let kind = JobEventKind::Retrying;
+                self.publish(&jobProject, kind, Some(JobProjectStatus::Queued), Some(e))
+                    .await;
+                return;
+            }
+            Err(UpdateError::Other(e)) => (JobProjectStatus::Failed, Some(e)),
+        };
+
+        let result = self
+            .jobStore
+            .finishJobProject(id, status, error.as_deref())
+            .await;
+        let completed = match result {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = self.repository.logError(&e.to_string()).await;
+                return;
+            }
+        };
+        let kind = match status {
+            JobProjectStatus::Succeeded => JobEventKind::Stored,
+            JobProjectStatus::Skipped => JobEventKind::Skipped,
+            _ => JobEventKind::Failed,
+        };
+        self.publish(&jobProject, kind, Some(status), error).await;
+        if completed {
+            self.publishJob(jobProject.job_id, JobEventKind::Completed)
+                .await;
+        }
+    }
+
+    async fn publish(
+        &self,
+        jobProject: &ClaimedJobProject,
+        kind: JobEventKind,
+        status: Option<JobProjectStatus>,
+        error: Option<String>,
+    ) {
+        let event = JobEvent {
+            job_id: jobProject.job_id,
+            kind,
+            project_id: Some(jobProject.project.id),
+            name: Some(jobProject.project.name.clone()),
+            status: status.map(|v| v.asStr().to_owned()),
+            // Notification payloads are limited to 8000 bytes.
+            error: error.map(|v| v.chars().take(1000).collect()),
+            progress: Default::default(),
+        };
+        self.notify(event).await;
+    }
+
+    async fn publishJob(&self, jobId: i32, kind: JobEventKind) {
+        let event = JobEvent {
+            job_id: jobId,
+            kind,
+            project_id: None,
+            name: None,
+            status: None,
+            error: None,
+            progress: Default::default(),
+        };
+        self.notify(event).await;
+    }
+
+    /// Sends an event with the current progress of its job.
+    async fn notify(&self, mut event: JobEvent) {
+        let result = async {
+            event.progress = self.jobStore.getJobProgressById(event.job_id).await?;
+            return self.jobStore.notifyJobEvent(&event).await;
+        }
+        .await;
+        if let Err(e) = result {
+            let _ = self.repository.logError(&e.to_string()).await;
+        }
+    }
+
+    /// Syncs the repository of a project, then analyzes and stores it unless HEAD
+    /// has not moved since the last analysis. The clone and the analysis run on
+    /// blocking threads that stop by themselves once a limit is exceeded or the
+    /// wait for them is given up. The directory of the project stays locked until both are done.
+    async fn updateProject(
+        &self,
+        jobProject: &ClaimedJobProject,
+    ) -> Result<JobProjectStatus, UpdateError> {
+        let project = &jobProject.project;
+        let lock = self.gitService.lockProject(project).await?;
+        self.publish(jobProject, JobEventKind::Cloning, None, None)
+            .await;
+        let limits = self.limits();
+        let syncTask = {
+            let gitService = self.gitService.clone();
+            let project = project.clone();
+            let lock = lock.clone();
+            let limits = limits.clone();
+            tokio::task::spawn_blocking(move || {
+                let _lock = lock;
+                let projectPath = gitService.sync(&project, &limits)?;
+                let head = git::head(&projectPath)?;
+                return Ok::<_, GitError>((projectPath, head));
+            })
+        };
+        let (projectPath, head) = withinDeadline(&limits, syncTask)
+            .await?
+            .map_err(|e| format!("The git task failed: {e}"))??;
+
+        let lastCommitSha = self
+            .repository
+            .getLatestCommitShaByProjectId(project.id)
+            .await?;
+        if lastCommitSha.as_deref() == Some(head.sha.as_str()) {
+            return Ok(JobProjectStatus::Skipped);
+        }
+
+        self.publish(jobProject, JobEventKind::Analyzing, None, None)
+            .await;
+        let analysisTask = {
+            let limits = limits.clone();
+            tokio::task::spawn_blocking(move || {
+                let _lock = lock;
+                return analysis::analyzeProjectWithLimits(&projectPath, &limits);
+            })
+        };
+        let mut projectAnalysis = withinDeadline(&limits, analysisTask)
+            .await?
+            .map_err(|e| format!("The analysis task failed: {e}"))??;
+        projectAnalysis.commit_sha = Some(head.sha);
+        projectAnalysis.commit_branch = head.branch;
+        projectAnalysis.commit_date = Some(head.date);
+
+        self.repository
+            .updateProjectStats(project.id, &projectAnalysis)
+            .await
+            .map_err(|e| format!("Failed to store the stats: {e}"))?;
+        self.invalidateStats(project.id).await;
+        return Ok(JobProjectStatus::Succeeded);
+    }
+
+    /// Syncs the complete history of a project, then inserts the stats of its past
+    /// tags or commits, dated by each commit. Every revision is analyzed within its
+    /// own limits, and one that fails is logged and skipped. The whole backfill ends
+    /// by one deadline, and the revisions that are left then time it out.
+    async fn backfillProject(
+        &self,
+        jobProject: &ClaimedJobProject,
+    ) -> Result<JobProjectStatus, UpdateError> {
+        let project = &jobProject.project;
+        let lock = self.gitService.lockProject(project).await?;
+        self.publish(jobProject, JobEventKind::Cloning, None, None)
+            .await;
+        let deadline = self.options.backfillDeadline();
+        let limits = self.options.limitsUntil(deadline);
+        let every = jobProject.backfill_every.map(|v| v as usize);
+        let limit = jobProject.backfill_limit.unwrap_or(10) as usize;
+        let syncTask = {
+            let gitService = self.gitService.clone();
+            let project = project.clone();
+            let lock = lock.clone();
+            let limits = limits.clone();
+            tokio::task::spawn_blocking(move || {
+                let _lock = lock;
+                let projectPath = gitService.syncHistory(&project, &limits)?;
+                let revisions = match every {
+                    Some(every) => git::commits(&projectPath, every, limit)?,
+                    None => git::tags(&projectPath, limit)?,
+                };
+                return Ok::<_, GitError>((projectPath, revisions));
+            })
+        };
+        let (projectPath, revisions) = withinDeadline(&limits, syncTask)
+            .await?
+            .map_err(|e| format!("The git task failed: {e}"))??;
+
+        self.publish(jobProject, JobEventKind::Analyzing, None, None)
+            .await;
+        let mut inserted = false;
+        let total = revisions.len();
+        let mut left = 0;
+        for (i, revision) in revisions.into_iter().enumerate() {
+            if Instant::now() >= deadline {
+                left = total - i;
+                break;
+            }
+            let limits = self.options.limitsUntil(deadline);
+            let task = {
+                let projectPath = projectPath.clone();
+                let revision = revision.clone();
+                let lock = lock.clone();
+                let limits = limits.clone();
+                tokio::task::spawn_blocking(move || {
+                    let _lock = lock;
+                    return analyzeRevision(&projectPath, &revision, &limits);
+                })
+            };
+            let result = match withinDeadline(&limits, task).await {
+                Ok(Ok(result)) => result,
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(UpdateError::Limit(_, e) | UpdateError::Other(e)) => Err(e),
+            };
+            let projectAnalysis = match result {
+                Ok(v) => v,
+                Err(e) => {
+                    let _ = self
+                        .repository
+                        .logError(&format!("JobService.backfillProject: {revision}: {e}"))
+                        .await;
+                    continue;
+                }
+            };
+            inserted |= self
+                .repository
+                .insertHistoricalProjectStats(project.id, &projectAnalysis)
+                .await?;
+        }
+
+        if inserted {
+            self.invalidateStats(project.id).await;
+        }
+        if left > 0 {
+            return Err(UpdateError::Limit(
+                JobProjectStatus::TimedOut,
+                format!("The backfill timed out with {left} of {total} revisions left"),
+            ));
+        }
+        return Ok(JobProjectStatus::Succeeded);
+    }
+
+    /// Drops the cached stats pages of the provider of a project.
+    async fn invalidateStats(&self, projectId: i32) {
+        if let Ok(Some(stored)) = self
+            .repository
+            .getProjectById(projectId)
+            .await
+            .map(|v| v.into_iter().next())
+        {
+            invalidateProjectStats(&*self.cache, &*self.repository, &[stored.provider_id]).await;
+        }
+    }
+}
+
+/// Analyzes a copy of the files of a revision in a directory of its own, leaving
+/// the working tree alone.
+fn analyzeRevision(
+    projectPath: &std::path::Path,
+    revision: &str,
+    limits: &AnalysisLimits,
+) -> Result<analysis::ProjectAnalysis, String> {
+    let commit = git::commit(projectPath, revision).map_err(|e| e.to_string())?;
+    let exportDir = tempfile::Builder::new()
+        .prefix("unsaferust_backfill_")
+        .tempdir()
+        .map_err(|e| e.to_string())?;
+    git::export(projectPath, &commit.sha, exportDir.path()).map_err(|e| e.to_string())?;
+    let mut projectAnalysis =
+        analysis::analyzeProjectWithLimits(exportDir.path(), limits).map_err(|e| e.to_string())?;
+    projectAnalysis.commit_sha = Some(commit.sha);
+    projectAnalysis.commit_date = Some(commit.date);
+    return Ok(projectAnalysis);
+}
+
+/// Waits for a blocking task until the deadline of the limits. A task that is
+/// still running then is cancelled through the limits, so that it stops at its
+/// next check instead of holding a blocking thread; only a transfer that stalls
+/// without any progress keeps it until the connection gives up.
+async fn withinDeadline<T>(
+    limits: &AnalysisLimits,
+    task: tokio::task::JoinHandle<T>,
+) -> Result<Result<T, tokio::task::JoinError>, UpdateError> {
+    let Some(deadline) = limits.deadline else {
+        return Ok(task.await);
+    };
+    return tokio::time::timeout_at(deadline.into(), task)
+        .await
+        .map_err(|_| {
+            limits.cancel();
+            return UpdateError::Limit(
+                JobProjectStatus::TimedOut,
+                AnalysisError::TimedOut.to_string(),
+            );
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testRetryDelayAfter() {
+        let options = JobOptions {
+            retryDelay: Duration::from_secs(60),
+            maxRetryDelay: Duration::from_secs(3600),
+            ..JobOptions::new()
+        };
+        assert_eq!(options.retryDelayAfter(1), Duration::from_secs(60));
+        assert_eq!(options.retryDelayAfter(3), Duration::from_secs(240));
+        assert_eq!(options.retryDelayAfter(7), Duration::from_secs(3600));
+        // No overflow with many attempts or a long delay.
+        assert_eq!(options.retryDelayAfter(40), Duration::from_secs(3600));
+        let options = JobOptions {
+            retryDelay: Duration::MAX,
+            maxRetryDelay: Duration::MAX,
+            ..options
+        };
+        assert_eq!(options.retryDelayAfter(i32::MAX), Duration::MAX);
+        assert_eq!(options.retryDelayAfter(0), Duration::MAX);
+    }
+
+    #[test]
+    fn testLimitsUntil() {
+        let options = JobOptions {
+            staleAfter: Duration::from_secs(3600),
+            analysisTimeout: Some(Duration::from_secs(1800)),
+            ..JobOptions::new()
+        };
+        let deadline = options.backfillDeadline();
+        assert!(deadline < Instant::now() + options.staleAfter);
+
+        let later = Instant::now() + Duration::from_secs(7200);
+        let limits = options.limitsUntil(later);
+        assert!(limits.deadline.unwrap() <= Instant::now() + Duration::from_secs(1800));
+        let limits = options.limitsUntil(deadline);
+        assert!(limits.deadline.unwrap() <= Instant::now() + Duration::from_secs(1800));
+        let sooner = Instant::now() + Duration::from_secs(60);
+        assert_eq!(options.limitsUntil(sooner).deadline, Some(sooner));
+
+        // Without a timeout, only the deadline of the backfill is left.
+        let options = JobOptions {
+            analysisTimeout: None,
+            ..options
+        };
+        assert_eq!(options.limitsUntil(deadline).deadline, Some(deadline));
+    }
+}
@@ -0,0 +1,866 @@
+//! A `Repository` in memory, with the constraints of the Postgres schema.
+//! Nothing outlives the process.
+
+use crate::analysis::{site::ProjectUnsafeSite, ProjectAnalysis};
+use crate::models::{
+    pagination::{ProjectStatsQuery, ProjectStatsSort},
+    project::{
+        FileSafetyCoverage, Project, ProjectCategoryStats, ProjectCrate, ProjectInput,
+        ProjectStats, ProjectStatsDTO, ProjectStatsWithMeta, ProjectWithUrl, UnsafeSiteDTO,
+    },
+    provider::{Provider, ProviderInput},
+};
+use crate::services::{postgres::DatabaseError, repository::Repository};
+use async_trait::async_trait;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// A row of project_stats, with the categories of its analysis.
+struct StatsRow {
+    id: i32,
+    stats: ProjectStats,
+    /// Whether the row is the analysis of a past commit, dated by the commit.
+    historical: bool,
+    categories: Vec<ProjectCategoryStats>,
+}
+
+/// An unsafe site of the latest analysis of a project.
+struct SiteRow {
+    project_id: i32,
+    commit_sha: Option<String>,
+    site: ProjectUnsafeSite,
+}
+
+#[derive(Default)]
+struct Tables {
+    providers: Vec<Provider>,
+    projects: Vec<Project>,
+    /// In the order of the ids.
+    stats: Vec<StatsRow>,
+    crates: Vec<ProjectCrate>,
+    sites: Vec<SiteRow>,
+    errors: Vec<String>,
+    lastProviderId: i32,
+    lastProjectId: i32,
+    lastStatsId: i32,
+}
+
+impl Tables {
+    fn checkProject(
+        &self,
+        method: &str,
+        id: Option<i32>,
+        input: &ProjectInput,
+    ) -> Result<(), DatabaseError> {
+        if !self.providers.iter().any(|v| v.id == input.provider_id) {
+            return Err(DatabaseError::Reference(format!(
+                "InMemoryRepository.{method} failed: provider {} does not exist",
+                input.provider_id
+            )));
+        }
+        if self
+            .projects
+            .iter()
+            .any(|v| v.name == input.name && Some(v.id) != id)
+        {
+            return Err(DatabaseError::Duplicate(format!(
+                "InMemoryRepository.{method} failed: project {} exists",
+                input.name
+            )));
+        }
+        return Ok(());
+    }
+
+    fn checkStats(&self, method: &str, project_id: i32) -> Result<(), DatabaseError> {
+        if !self.projects.iter().any(|v| v.id == project_id) {
+            return Err(DatabaseError::Reference(format!(
+                "InMemoryRepository.{method} failed: project {project_id} does not exist"
+            )));
+        }
+        return Ok(());
+    }
+
+    /// The project of the stats and the url of its repository.
+    fn withProject<'a>(&'a self, stats: &'a ProjectStats) -> Option<(&'a Project, String)> {
+        let project = self.projects.iter().find(|v| v.id == stats.project_id)?;
+        let provider = self
+            .providers
+            .iter()
+            .find(|v| v.id == project.provider_id)?;
+        let url = format!("{}/{}/{}", provider.url, project.namespace, project.name);
+        return Some((project, url));
+    }
+
+    /// The newest stats of a project, by date and then by id. A current row comes
+    /// before the historical ones, whose dates are the dates of their commits.
+    fn latestStats(&self, project_id: i32) -> Option<&StatsRow> {
+        return self
+            .stats
+            .iter()
+            .filter(|v| v.stats.project_id == project_id)
+            .max_by(|a, b| {
+                return b
+                    .historical
+                    .cmp(&a.historical)
+                    .then(a.stats.created_at.cmp(&b.stats.created_at))
+                    .then(a.id.cmp(&b.id));
+            });
+    }
+
+    /// Inserts a row of project_stats, dated by `date`.
+    fn insertStats(
+        &mut self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+        historical: bool,
+        date: String,
+    ) {
+        self.lastStatsId += 1;
+        let row = StatsRow {
+            id: self.lastStatsId,
+            stats: projectStats(project_id, analysis, date.clone(), date),
+            historical,
+            categories: categoryStats(analysis),
+        };
+        self.stats.push(row);
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryRepository {
+    tables: Mutex<Tables>,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// The logged errors, oldest first.
+    pub fn errors(&self) -> Vec<String> {
+        return self.tables().errors.clone();
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        // The tables stay consistent, since no method panics halfway through a write.
+        return self.tables.lock().unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+/// The unsafe lines in percent of the code lines, `None` without code.
+fn unsafeRatio(stats: &ProjectStats) -> Option<f64> {
+    if stats.line_counts.code_lines == 0 {
+        return None;
+    }
+    return Some(100.0 * f64::from(stats.unsafe_lines) / f64::from(stats.line_counts.code_lines));
+}
+
+/// The documented unsafe blocks and functions in percent, rounded to 2 decimals.
+fn safetyCoverage(documented: i32, undocumented: i32) -> Option<f64> {
+    if documented + undocumented == 0 {
+        return None;
+    }
+    let coverage = 100.0 * f64::from(documented) / f64::from(documented + undocumented);
+    return Some((coverage * 100.0).round() / 100.0);
+}
+
+fn projectStats(
+    project_id: i32,
+    analysis: &ProjectAnalysis,
+    createdAt: String,
+    updatedAt: String,
+) -> ProjectStats {
+    let production = analysis.production();
+    let usage = analysis.unsafe_usage;
+    return ProjectStats::new(
+        project_id,
+        analysis.line_counts,
+        usage.unsafe_block_lines,
+        usage,
+        safetyCoverage(usage.documented_unsafe, usage.undocumented_unsafe),
+        production.line_counts.code_lines,
+        production.unsafe_usage.unsafe_block_lines,
+        analysis.unsafe_code_lint.asStr().to_owned(),
+        analysis.commit_sha.clone(),
+        analysis.commit_branch.clone(),
+        analysis.commit_date.clone(),
+        createdAt,
+        updatedAt,
+    );
+}
+
+/// The categories of an analysis, by name.
+fn categoryStats(analysis: &ProjectAnalysis) -> Vec<ProjectCategoryStats> {
+    let mut categories: Vec<ProjectCategoryStats> = analysis
+        .categories
+        .iter()
+        .map(|(category, stats)| ProjectCategoryStats {
+            category: category.asStr().to_owned(),
+            line_counts: stats.line_counts,
+            unsafe_lines: stats.unsafe_usage.unsafe_block_lines,
+            unsafe_usage: stats.unsafe_usage,
+        })
+        .collect();
+    categories.sort_by(|a, b| a.category.cmp(&b.category));
+    return categories;
+}
+
+fn today() -> String {
+    return chrono::Utc::now().date_naive().to_string();
+}
+
+/// The date of a commit, or today for an unknown one.
+fn commitDay(commitDate: Option<&str>) -> String {
+    return commitDate
+        .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+        .map(|v| v.with_timezone(&chrono::Utc).date_naive().to_string())
+        .unwrap_or_else(today);
+}
+
+#[async_trait]
+impl Repository for InMemoryRepository {
+    async fn logError(&self, error: &str) -> Result<(), DatabaseError> {
+        self.tables().errors.push(error.to_owned());
+        return Ok(());
+    }
+
+    async fn getProviders(&self) -> Result<Vec<Provider>, DatabaseError> {
+        let providers = self.tables().providers.clone();
+        return Ok(providers);
+    }
+
+    async fn getProviderById(&self, id: i32) -> Result<Vec<Provider>, DatabaseError> {
+        let tables = self.tables();
+        let providers = tables.providers.iter().filter(|v| v.id == id);
+        return Ok(providers.cloned().collect());
+    }
+
+    async fn insertProvider(&self, input: &ProviderInput) -> Result<Provider, DatabaseError> {
+        let mut tables = self.tables();
+        if tables.providers.iter().any(|v| v.url == input.url) {
+            return Err(DatabaseError::Duplicate(format!(
+                "InMemoryRepository.insertProvider failed: provider {} exists",
+                input.url
+            )));
+        }
+        tables.lastProviderId += 1;
+        let provider = Provider::new(tables.lastProviderId, &input.url);
+        tables.providers.push(provider.clone());
+        return Ok(provider);
+    }
+
+    async fn updateProvider(
+        &self,
+        id: i32,
+        input: &ProviderInput,
+    ) -> Result<Option<Provider>, DatabaseError> {
+        let mut tables = self.tables();
+        if tables
+            .providers
+            .iter()
+            .any(|v| v.url == input.url && v.id != id)
+        {
+            return Err(DatabaseError::Duplicate(format!(
+                "InMemoryRepository.updateProvider failed: provider {} exists",
+                input.url
+            )));
+        }
+        let provider = tables.providers.iter_mut().find(|v| v.id == id);
+        return Ok(provider.map(|v| {
+            v.url = input.url.clone();
+            return v.clone();
+        }));
+    }
+
+    async fn deleteProvider(&self, id: i32) -> Result<bool, DatabaseError> {
+        let mut tables = self.tables();
+        if tables.projects.iter().any(|v| v.provider_id == id) {
+            return Err(DatabaseError::Reference(format!(
+                "InMemoryRepository.deleteProvider failed: provider {id} has projects"
+            )));
+        }
+        let count = tables.providers.len();
+        tables.providers.retain(|v| v.id != id);
+        return Ok(tables.providers.len() < count);
+    }
+
+    async fn createProvider(&self, url: &str) -> Result<(), DatabaseError> {
+        let input = ProviderInput {
+            url: url.to_owned(),
+        };
+        return match self.insertProvider(&input).await {
+            Ok(_) | Err(DatabaseError::Duplicate(_)) => Ok(()),
+            Err(e) => Err(e),
+        };
+    }
+
+    async fn getProjects(&self) -> Result<Vec<Project>, DatabaseError> {
+        let projects = self.tables().projects.clone();
+        return Ok(projects);
+    }
+
+    async fn getProjectById(&self, id: i32) -> Result<Vec<Project>, DatabaseError> {
+        let tables = self.tables();
+        let projects = tables.projects.iter().filter(|v| v.id == id);
+        return Ok(projects.cloned().collect());
+    }
+
+    async fn getProjectsWithUrl(&self) -> Result<Vec<ProjectWithUrl>, DatabaseError> {
+        let tables = self.tables();
+        let projects = tables
+            .projects
+            .iter()
+            .filter_map(|project| {
+                let provider = tables
+                    .providers
+                    .iter()
+                    .find(|v| v.id == project.provider_id)?;
+                return Some(ProjectWithUrl {
+                    id: project.id,
+                    namespace: project.namespace.clone(),
+                    name: project.name.clone(),
+                    url: provider.url.clone(),
+                });
+            })
+            .collect();
+        return Ok(projects);
+    }
+
+    async fn insertProject(&self, input: &ProjectInput) -> Result<Project, DatabaseError> {
+        let mut tables = self.tables();
+        tables.checkProject("insertProject", None, input)?;
+        tables.lastProjectId += 1;
+        let project = Project {
+            id: tables.lastProjectId,
+            provider_id: input.provider_id,
+            namespace: input.namespace.clone(),
+            name: input.name.clone(),
+        };
+        tables.projects.push(project.clone());
+        return Ok(project);
+    }
+
+    async fn updateProject(
+        &self,
+        id: i32,
+        input: &ProjectInput,
+    ) -> Result<Option<Project>, DatabaseError> {
+        let mut tables = self.tables();
+        if !tables.projects.iter().any(|v| v.id == id) {
+            return Ok(None);
+        }
+        tables.checkProject("updateProject", Some(id), input)?;
+        let project = tables.projects.iter_mut().find(|v| v.id == id);
+        return Ok(project.map(|v| {
+            v.provider_id = input.provider_id;
+            v.namespace = input.namespace.clone();
+            v.name = input.name.clone();
+            return v.clone();
+        }));
+    }
+
+    async fn deleteProject(&self, id: i32) -> Result<bool, DatabaseError> {
+        let mut tables = self.tables();
+        let count = tables.projects.len();
+        tables.projects.retain(|v| v.id != id);
+        tables.stats.retain(|v| v.stats.project_id != id);
+        tables.crates.retain(|v| v.project_id != id);
+        tables.sites.retain(|v| v.project_id != id);
+        return Ok(tables.projects.len() < count);
+    }
+
+    async fn createProject(
+        &self,
+        providerUrl: &str,
+        namespace: &str,
+        name: &str,
+    ) -> Result<(), DatabaseError> {
+        let providerId = self
+            .tables()
+            .providers
+            .iter()
+            .find(|v| v.url == providerUrl)
+            .map(|v| v.id)
+            .ok_or_else(|| {
+                DatabaseError::Other(format!(
+                    "InMemoryRepository.createProject failed: provider {providerUrl} does not exist"
+                ))
+            })?;
+        let input = ProjectInput {
+            provider_id: providerId,
+            namespace: namespace.to_owned(),
+            name: name.to_owned(),
+        };
+        return match self.insertProject(&input).await {
+            Ok(_) | Err(DatabaseError::Duplicate(_)) => Ok(()),
+            Err(e) => Err(e),
+        };
+    }
+
+    async fn getProjectsStats(
+        &self,
+        query: &ProjectStatsQuery,
+    ) -> Result<ProjectStatsWithMeta, DatabaseError> {
+        let tables = self.tables();
+        let name = query.name.as_ref().map(|v| v.to_lowercase());
+        let mut rows: Vec<(&ProjectStats, &Project, String)> = tables
+            .stats
+            .iter()
+            .filter(|v| {
+                tables
+                    .latestStats(v.stats.project_id)
+                    .is_some_and(|latest| latest.id == v.id)
+            })
+            .map(|v| &v.stats)
+            .filter_map(|v| {
+                let (project, url) = tables.withProject(v)?;
+                return Some((v, project, url));
+            })
+            .filter(|(stats, project, _)| {
+                let ratio = unsafeRatio(stats);
+                return name
+                    .as_ref()
+                    .is_none_or(|v| project.name.to_lowercase().contains(v))
+                    && query
+                        .unsafe_code_lint
+                        .is_none_or(|v| stats.unsafe_code_lint == v)
+                    && query
+                        .min_unsafe_ratio
+                        .is_none_or(|v| ratio.is_some_and(|ratio| ratio >= v))
+                    && query
+                        .max_unsafe_ratio
+                        .is_none_or(|v| ratio.is_some_and(|ratio| ratio <= v))
+                    && query
+                        .min_code_lines
+                        .is_none_or(|v| stats.line_counts.code_lines >= v)
+                    && query.provider_id.is_none_or(|v| project.provider_id == v)
+                    && query
+                        .updated_since
+                        .as_ref()
+                        .is_none_or(|v| &stats.updated_at >= v);
+            })
+            .collect();
+
+        let direction = |ordering: Ordering| match query.descending {
+            true => ordering.reverse(),
+            false => ordering,
+        };
+        rows.sort_by(|(a, aProject, _), (b, bProject, _)| {
+            let ordering = match query.sort {
+                ProjectStatsSort::Name => direction(aProject.name.cmp(&bProject.name)),
+                ProjectStatsSort::CodeLines => {
+                    direction(a.line_counts.code_lines.cmp(&b.line_counts.code_lines))
+                }
+                ProjectStatsSort::UnsafeLines => direction(a.unsafe_lines.cmp(&b.unsafe_lines)),
+                // The projects without code come last in both directions.
+                ProjectStatsSort::UnsafeRatio => match (unsafeRatio(a), unsafeRatio(b)) {
+                    (Some(a), Some(b)) => direction(a.total_cmp(&b)),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                ProjectStatsSort::UpdatedAt => direction(a.updated_at.cmp(&b.updated_at)),
+            };
+            return ordering
+                .then_with(|| aProject.name.cmp(&bProject.name))
+                .then_with(|| a.project_id.cmp(&b.project_id));
+        });
+
+        let total = rows.len() as i64;
+        let limit = query.limit as usize;
+        let projectStats = rows
+            .into_iter()
+            .skip(limit * query.page as usize)
+            .take(limit)
+            .map(|(stats, project, url)| {
+                return ProjectStatsDTO::new(
+                    stats.project_id,
+                    project.name.clone(),
+                    url,
+                    stats.line_counts,
+                    stats.unsafe_lines,
+                    stats.unsafe_usage,
+                    stats.safety_coverage,
+                    stats.production_code_lines,
+                    stats.production_unsafe_lines,
+                    stats.unsafe_code_lint.clone(),
+                    stats.commit_sha.clone(),
+                    stats.commit_branch.clone(),
+                    stats.commit_date.clone(),
+                    stats.created_at.clone(),
+                    stats.updated_at.clone(),
+                );
+            })
+            .collect();
+        return Ok(ProjectStatsWithMeta {
+            projectStats,
+            meta: total,
+        });
+    }
+
+    async fn getProjectsStatsById(&self, id: i32) -> Result<Vec<ProjectStats>, DatabaseError> {
+        let tables = self.tables();
+        let mut stats: Vec<ProjectStats> = tables
+            .stats
+            .iter()
+            .rev()
+            .filter(|v| v.stats.project_id == id)
+            .map(|v| v.stats.clone())
+            .collect();
+        stats.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        return Ok(stats);
+    }
+
+    async fn updateProjectStats(
+        &self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+    ) -> Result<(), DatabaseError> {
+        let mut tables = self.tables();
+        tables.checkStats("updateProjectStats", project_id)?;
+        let today = today();
+        let existing = tables.stats.iter_mut().find(|v| {
+            return v.stats.project_id == project_id
+                && !v.historical
+                && v.stats.unsafe_lines == analysis.unsafe_usage.unsafe_block_lines;
+        });
+        match existing {
+            Some(row) => {
+                let createdAt = row.stats.created_at.clone();
+                row.stats = projectStats(project_id, analysis, createdAt, today.clone());
+                row.categories = categoryStats(analysis);
+            }
+            None => tables.insertStats(project_id, analysis, false, today.clone()),
+        }
+
+        let mut createdAt: BTreeMap<String, String> = tables
+            .crates
+            .iter()
+            .filter(|v| v.project_id == project_id)
+            .map(|v| (v.name.clone(), v.created_at.clone()))
+            .collect();
+        tables.crates.retain(|v| v.project_id != project_id);
+        for projectCrate in &analysis.crates {
+            let crateAnalysis = &projectCrate.analysis;
+            let usage = crateAnalysis.unsafe_usage;
+            let production = crateAnalysis.production();
+            tables.crates.push(ProjectCrate {
+                project_id,
+                name: projectCrate.name.clone(),
+                path: projectCrate.path.clone(),
+                line_counts: crateAnalysis.line_counts,
+                unsafe_lines: usage.unsafe_block_lines,
+                unsafe_usage: usage,
+                safety_coverage: safetyCoverage(usage.documented_unsafe, usage.undocumented_unsafe),
+                production_code_lines: production.line_counts.code_lines,
+                production_unsafe_lines: production.unsafe_usage.unsafe_block_lines,
+                unsafe_code_lint: crateAnalysis.unsafe_code_lint.asStr().to_owned(),
+                created_at: createdAt
+                    .remove(&projectCrate.name)
+                    .unwrap_or_else(|| today.clone()),
+                updated_at: today.clone(),
+            });
+        }
+
+        // The sites always reflect the latest analysis.
+        tables.sites.retain(|v| v.project_id != project_id);
+        for site in &analysis.unsafe_sites {
+            tables.sites.push(SiteRow {
+                project_id,
+                commit_sha: analysis.commit_sha.clone(),
+                site: site.clone(),
+            });
+        }
+        return Ok(());
+    }
+
+    async fn insertHistoricalProjectStats(
+        &self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+    ) -> Result<bool, DatabaseError> {
+        let mut tables = self.tables();
+        tables.checkStats("insertHistoricalProjectStats", project_id)?;
+        let exists = analysis.commit_sha.is_some()
+            && tables.stats.iter().any(|v| {
+                return v.stats.project_id == project_id
+                    && v.historical
+                    && v.stats.commit_sha == analysis.commit_sha;
+            });
+        if exists {
+            return Ok(false);
+        }
+        let date = commitDay(analysis.commit_date.as_deref());
+        tables.insertStats(project_id, analysis, true, date);
+        return Ok(true);
+    }
+
+    async fn getLatestCommitShaByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Option<String>, DatabaseError> {
+        let tables = self.tables();
+        let commitSha = tables
+            .stats
+            .iter()
+            .filter(|v| v.stats.project_id == id && !v.historical)
+            .max_by(|a, b| {
+                return a
+                    .stats
+                    .updated_at
+                    .cmp(&b.stats.updated_at)
+                    .then(a.id.cmp(&b.id));
+            })
+            .and_then(|v| v.stats.commit_sha.clone());
+        return Ok(commitSha);
+    }
+
+    async fn getProjectCategoryStatsById(
+        &self,
+        id: i32,
+    ) -> Result<Vec<ProjectCategoryStats>, DatabaseError> {
+        let tables = self.tables();
+        let categories = tables
+            .latestStats(id)
+            .map(|v| v.categories.clone())
+            .unwrap_or_default();
+        return Ok(categories);
+    }
+
+    async fn getProjectCratesById(&self, id: i32) -> Result<Vec<ProjectCrate>, DatabaseError> {
+        let tables = self.tables();
+        let mut crates: Vec<ProjectCrate> = tables
+            .crates
+            .iter()
+            .filter(|v| v.project_id == id)
+            .cloned()
+            .collect();
+        crates.sort_by(|a, b| a.path.cmp(&b.path));
+        return Ok(crates);
+    }
+
+    async fn getUnsafeSitesByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Vec<UnsafeSiteDTO>, DatabaseError> {
+        let projects = self.getProjectsWithUrl().await?;
+        let Some(project) = projects.into_iter().find(|v| v.id == id) else {
+            return Ok(Vec::new());
+        };
+        let tables = self.tables();
+        let mut sites: Vec<UnsafeSiteDTO> = tables
+            .sites
+            .iter()
+            .filter(|v| v.project_id == id)
+            .map(|v| {
+                let site = &v.site;
+                return UnsafeSiteDTO {
+                    file_path: site.file_path.clone(),
+                    start_line: site.site.start_line,
+                    end_line: site.site.end_line,
+                    kind: site.site.kind.asStr().to_owned(),
+                    item_path: site.site.item_path.clone(),
+                    category: site.category.asStr().to_owned(),
+                    documented: site.site.documented,
+                    commit_sha: v.commit_sha.clone(),
+                    permalink: UnsafeSiteDTO::permalink(
+                        &project,
+                        v.commit_sha.as_deref(),
+                        &site.file_path,
+                        site.site.start_line,
+                        site.site.end_line,
+                    ),
+                };
+            })
+            .collect();
+        // Stable, so the sites on the same line keep the order of the analysis.
+        sites.sort_by(|a, b| {
+            return a
+                .file_path
+                .cmp(&b.file_path)
+                .then(a.start_line.cmp(&b.start_line));
+        });
+        return Ok(sites);
+    }
+
+    async fn getSafetyCoverageByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Vec<FileSafetyCoverage>, DatabaseError> {
+        let tables = self.tables();
+        // The documented and the undocumented sites of every file.
+        let mut files: BTreeMap<&str, (i32, i32)> = BTreeMap::new();
+        for row in tables.sites.iter().filter(|v| v.project_id == id) {
+            let Some(documented) = row.site.site.documented else {
+                continue;
+            };
+            let counts = files.entry(&row.site.file_path).or_default();
+            match documented {
+                true => counts.0 += 1,
+                false => counts.1 += 1,
+            }
+        }
+        let coverage = files
+            .into_iter()
+            .map(
+                |(file_path, (documented, undocumented))| FileSafetyCoverage {
+                    file_path: file_path.to_owned(),
+                    documented_unsafe: documented,
+                    undocumented_unsafe: undocumented,
+                    safety_coverage: safetyCoverage(documented, undocumented),
+                },
+            )
+            .collect();
+        return Ok(coverage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{lines::LineCounts, UnsafeUsage};
+
+    fn analysis(code_lines: i32, unsafe_blocks: i32) -> ProjectAnalysis {
+        return ProjectAnalysis {
+            line_counts: LineCounts {
+                code_lines,
+                ..Default::default()
+            },
+            unsafe_usage: UnsafeUsage {
+                unsafe_blocks,
+                unsafe_block_lines: unsafe_blocks,
+                documented_unsafe: unsafe_blocks,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+    }
+
+    fn projectInput(provider_id: i32, name: &str) -> ProjectInput {
+        return ProjectInput {
+            provider_id,
+            namespace: "namespace".to_owned(),
+            name: name.to_owned(),
+        };
+    }
+
+    #[tokio::test]
+    async fn testInMemoryRepositoryConstraints() {
+        let repository = InMemoryRepository::new();
+        let input = ProviderInput {
+            url: "https://github.com".to_owned(),
+        };
+        let provider = repository.insertProvider(&input).await.unwrap();
+        assert!(matches!(
+            repository.insertProvider(&input).await,
+            Err(DatabaseError::Duplicate(_))
+        ));
+        repository.createProvider(&input.url).await.unwrap();
+        assert_eq!(repository.getProviders().await.unwrap().len(), 1);
+
+        let project = repository
+            .insertProject(&projectInput(provider.id, "clap"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository
+                .insertProject(&projectInput(provider.id, "clap"))
+                .await,
+            Err(DatabaseError::Duplicate(_))
+        ));
+        assert!(matches!(
+            repository.insertProject(&projectInput(2, "serde")).await,
+            Err(DatabaseError::Reference(_))
+        ));
+        assert!(matches!(
+            repository
+                .updateProject(project.id, &projectInput(2, "clap"))
+                .await,
+            Err(DatabaseError::Reference(_))
+        ));
+        assert!(repository
+            .updateProject(3, &projectInput(provider.id, "clap"))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            repository.deleteProvider(provider.id).await,
+            Err(DatabaseError::Reference(_))
+        ));
+
+        repository
+            .updateProjectStats(project.id, &analysis(100, 1))
+            .await
+            .unwrap();
+        assert!(repository.deleteProject(project.id).await.unwrap());
+        assert!(!repository.deleteProject(project.id).await.unwrap());
+        assert!(repository
+            .getProjectsStatsById(project.id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(repository.deleteProvider(provider.id).await.unwrap());
+        assert!(matches!(
+            repository
+                .updateProjectStats(project.id, &analysis(100, 1))
+                .await,
+            Err(DatabaseError::Reference(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn testInMemoryRepositoryProjectStats() {
+        let repository = InMemoryRepository::new();
+        repository
+            .createProvider("https://github.com")
+            .await
+            .unwrap();
+        for (name, code_lines, unsafe_blocks) in [("a", 100, 5), ("b", 1000, 1), ("c", 0, 0)] {
+            repository
+                .createProject("https://github.com", "namespace", name)
+                .await
+                .unwrap();
+            let id = repository.getProjects().await.unwrap().len() as i32;
+            repository
+                .updateProjectStats(id, &analysis(code_lines, unsafe_blocks))
+                .await
+                .unwrap();
+        }
+        // The same unsafe lines update the stats, instead of adding to the history.
+        repository
+            .updateProjectStats(1, &analysis(200, 5))
+            .await
+            .unwrap();
+        let stats = repository.getProjectsStatsById(1).await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].line_counts.code_lines, 200);
+        assert_eq!(stats[0].safety_coverage, Some(100.0));
+
+        let query = ProjectStatsQuery {
+            sort: ProjectStatsSort::UnsafeRatio,
+            descending: true,
+            limit: 50,
+            ..Default::default()
+        };
+        let result = repository.getProjectsStats(&query).await.unwrap();
+        let names: Vec<&str> = result
+            .projectStats
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(result.meta, 3);
+        assert_eq!(result.projectStats[0].url, "https://github.com/namespace/a");
+
+        let query = ProjectStatsQuery {
+            min_unsafe_ratio: Some(1.0),
+            limit: 1,
+            page: 1,
+            ..query
+        };
+        let result = repository.getProjectsStats(&query).await.unwrap();
+        assert_eq!(result.meta, 1);
+        assert!(result.projectStats.is_empty());
+    }
+}
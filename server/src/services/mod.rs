@@ -1,2 +1,8 @@
+pub mod cache;
+pub mod git;
+pub mod jobs;
+pub mod memory;
 pub mod postgres;
 pub mod redis;
+pub mod repository;
+pub mod sqlite;
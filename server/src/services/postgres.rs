@@ -1,10 +1,76 @@
-use crate::models::{configuration::DatabaseSettings, project::*, provider::Provider};
-use axum::http::StatusCode;
-use sqlx::Row;
-use sqlx::{postgres::PgPoolOptions, Error, PgPool};
-use std::{fs::OpenOptions, io::Write};
+use crate::analysis::{lines::LineCounts, ProjectAnalysis, UnsafeUsage};
+use crate::models::{
+    backfill::BackfillOptions,
+    configuration::DatabaseSettings,
+    job::*,
+    pagination::{ProjectStatsQuery, ProjectStatsSort},
+    project::*,
+    provider::{Provider, ProviderInput},
+    token::{ApiToken, ApiTokenScope},
+};
+use crate::services::repository::{JobStore, Repository, TokenStore};
 use crate::utils::getDate;
+use async_trait::async_trait;
+use sqlx::postgres::{PgListener, PgPoolOptions};
+use sqlx::{Error, PgPool, Postgres, QueryBuilder, Transaction};
+use sqlx::{FromRow, Row};
+use std::{fs::OpenOptions, io::Write};
+use tokio::sync::broadcast;
+
+/// The channel of the `pg_notify` job events.
+pub const JOB_EVENTS_CHANNEL: &str = "job_events";
+
+/// The number of projects of a job in each status, over `job_projects jp`.
+const JOB_PROGRESS_COLUMNS: &str = "
+        count(jp.id)::int as total
+        ,(count(jp.id) filter (where jp.status = 'queued'))::int as queued
+        ,(count(jp.id) filter (where jp.status = 'running'))::int as running
+        ,(count(jp.id) filter (where jp.status = 'succeeded'))::int as succeeded
+        ,(count(jp.id) filter (where jp.status = 'skipped'))::int as skipped
+        ,(count(jp.id) filter (where jp.status not in ('queued', 'running', 'succeeded', 'skipped')))::int as failed";
+
+/// Why a query failed. Only the writes can fail with a constraint.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DatabaseError {
+    /// A unique constraint failed, e.g. the name of a project is taken.
+    Duplicate(String),
+    /// A foreign key failed, e.g. the provider of a new project is missing,
+    /// or a deleted provider still has projects.
+    Reference(String),
+    Other(String),
+}
+
+impl DatabaseError {
+    /// Maps the constraint errors of Postgres and SQLite, whose codes are the extended ones.
+    /// SQLite reports a failed foreign key of a statement with `returning` as a generic error,
+    /// so its message is checked too.
+    pub(crate) fn new(method: &str, e: Error) -> Self {
+        let code = e
+            .as_database_error()
+            .and_then(|v| v.code())
+            .map(|v| v.into_owned());
+        let foreignKey = e
+            .as_database_error()
+            .is_some_and(|v| v.message() == "FOREIGN KEY constraint failed");
+        let message = format!("DatabaseService.{method} failed: {:?}", e);
+        return match code.as_deref() {
+            Some("23505" | "2067" | "1555") => DatabaseError::Duplicate(message),
+            Some("23503" | "787") => DatabaseError::Reference(message),
+            _ if foreignKey => DatabaseError::Reference(message),
+            _ => DatabaseError::Other(message),
+        };
+    }
+}
 
+impl std::fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            DatabaseError::Duplicate(v) | DatabaseError::Reference(v) | DatabaseError::Other(v) => {
+                write!(f, "{v}")
+            }
+        };
+    }
+}
 
 #[derive(Clone)]
 pub struct PostgresService {
@@ -13,10 +79,8 @@ pub struct PostgresService {
 
 impl PostgresService {
     pub async fn new(con: Option<PgPool>) -> Self {
-        if con.is_some() {
-            return Self {
-                connection: con.unwrap(),
-            };
+        if let Some(connection) = con {
+            return Self { connection };
         }
 
         // Prepare the variables that the run method needs.
@@ -69,17 +133,287 @@ impl PostgresService {
 
         let mut file = OpenOptions::new()
             .create(true)
-            .write(true)
             .append(true)
             .open(errorFile)
             .map_err(|_| "")?;
 
         let date = getDate();
-        file.write_all(format!("{date}: {error}\n").as_bytes()).map_err(|_| "")?;
+        file.write_all(format!("{date}: {error}\n").as_bytes())
+            .map_err(|_| "")?;
+        return Ok(());
+    }
+
+    pub async fn updateProjectStatsById(&self, project_id: i32, analysis: &ProjectAnalysis) {
+        let result = self.upsertProjectStats(project_id, analysis).await;
+        if let Err(e) = result {
+            let _ = self
+                .logError(&format!("Failed to update projectStats with e: {:?}", e))
+                .await;
+        }
+    }
+
+    /// Writes the project_stats row of an analysis and its categories, returning its id.
+    /// A historical row is dated by its commit and inserted once per commit, while a
+    /// current row updates the current row with the same unsafe_lines.
+    async fn insertProjectStatsRow(
+        transaction: &mut Transaction<'_, Postgres>,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+        historical: bool,
+    ) -> Result<Option<i32>, Error> {
+        let production = analysis.production();
+        let (conflict, date) = if historical {
+            (
+                "(project_id, commit_sha) where historical do nothing",
+                "coalesce(cast($21 as date), current_date)",
+            )
+        } else {
+            (
+                "(project_id, unsafe_lines) where not historical do update
+            set updated_at = current_date,
+                code_lines = excluded.code_lines,
+                comment_lines = excluded.comment_lines,
+                doc_comment_lines = excluded.doc_comment_lines,
+                blank_lines = excluded.blank_lines,
+                unsafe_blocks = excluded.unsafe_blocks,
+                unsafe_functions = excluded.unsafe_functions,
+                unsafe_impls = excluded.unsafe_impls,
+                unsafe_traits = excluded.unsafe_traits,
+                extern_blocks = excluded.extern_blocks,
+                no_mangle_items = excluded.no_mangle_items,
+                unsafe_block_lines = excluded.unsafe_block_lines,
+                documented_unsafe = excluded.documented_unsafe,
+                undocumented_unsafe = excluded.undocumented_unsafe,
+                production_code_lines = excluded.production_code_lines,
+                production_unsafe_lines = excluded.production_unsafe_lines,
+                unsafe_code_lint = excluded.unsafe_code_lint,
+                commit_sha = excluded.commit_sha,
+                commit_branch = excluded.commit_branch,
+                commit_date = excluded.commit_date",
+                "current_date",
+            )
+        };
+        let query = format!(
+            "
+            insert into project_stats (
+                project_id, unsafe_lines,
+                code_lines, comment_lines, doc_comment_lines, blank_lines,
+                unsafe_blocks, unsafe_functions, unsafe_impls, unsafe_traits,
+                extern_blocks, no_mangle_items, unsafe_block_lines,
+                documented_unsafe, undocumented_unsafe,
+                production_code_lines, production_unsafe_lines, unsafe_code_lint,
+                commit_sha, commit_branch, commit_date, historical, created_at, updated_at
+            )
+            values (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+                $19, $20, cast($21 as timestamptz), $22, {date}, {date}
+            )
+            on conflict {conflict}
+            returning id"
+        );
+        let projectStatsId: Option<(i32,)> = sqlx::query_as(&query)
+            .bind(project_id)
+            .bind(analysis.unsafe_usage.unsafe_block_lines)
+            .bind(analysis.line_counts.code_lines)
+            .bind(analysis.line_counts.comment_lines)
+            .bind(analysis.line_counts.doc_comment_lines)
+            .bind(analysis.line_counts.blank_lines)
+            .bind(analysis.unsafe_usage.unsafe_blocks)
+            .bind(analysis.unsafe_usage.unsafe_functions)
+            .bind(analysis.unsafe_usage.unsafe_impls)
+            .bind(analysis.unsafe_usage.unsafe_traits)
+            .bind(analysis.unsafe_usage.extern_blocks)
+            .bind(analysis.unsafe_usage.no_mangle_items)
+            .bind(analysis.unsafe_usage.unsafe_block_lines)
+            .bind(analysis.unsafe_usage.documented_unsafe)
+            .bind(analysis.unsafe_usage.undocumented_unsafe)
+            .bind(production.line_counts.code_lines)
+            .bind(production.unsafe_usage.unsafe_block_lines)
+            .bind(analysis.unsafe_code_lint.asStr())
+            .bind(&analysis.commit_sha)
+            .bind(&analysis.commit_branch)
+            .bind(&analysis.commit_date)
+            .bind(historical)
+            .fetch_optional(&mut *transaction)
+            .await?;
+        let Some((projectStatsId,)) = projectStatsId else {
+            return Ok(None);
+        };
+
+        sqlx::query("delete from project_stats_categories where project_stats_id = $1")
+            .bind(projectStatsId)
+            .execute(&mut *transaction)
+            .await?;
+        for (category, stats) in &analysis.categories {
+            sqlx::query(
+                "
+                insert into project_stats_categories (
+                    project_stats_id, category, unsafe_lines,
+                    code_lines, comment_lines, doc_comment_lines, blank_lines,
+                    unsafe_blocks, unsafe_functions, unsafe_impls, unsafe_traits,
+                    extern_blocks, no_mangle_items, unsafe_block_lines,
+                    documented_unsafe, undocumented_unsafe
+                )
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)",
+            )
+            .bind(projectStatsId)
+            .bind(category.asStr())
+            .bind(stats.unsafe_usage.unsafe_block_lines)
+            .bind(stats.line_counts.code_lines)
+            .bind(stats.line_counts.comment_lines)
+            .bind(stats.line_counts.doc_comment_lines)
+            .bind(stats.line_counts.blank_lines)
+            .bind(stats.unsafe_usage.unsafe_blocks)
+            .bind(stats.unsafe_usage.unsafe_functions)
+            .bind(stats.unsafe_usage.unsafe_impls)
+            .bind(stats.unsafe_usage.unsafe_traits)
+            .bind(stats.unsafe_usage.extern_blocks)
+            .bind(stats.unsafe_usage.no_mangle_items)
+            .bind(stats.unsafe_usage.unsafe_block_lines)
+            .bind(stats.unsafe_usage.documented_unsafe)
+            .bind(stats.unsafe_usage.undocumented_unsafe)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        return Ok(Some(projectStatsId));
+    }
+
+    /// Updates the stats with the same unsafe_lines if there are any, else inserts new ones.
+    pub(crate) async fn upsertProjectStats(
+        &self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+    ) -> Result<(), Error> {
+        let mut transaction = self.connection.begin().await?;
+        Self::insertProjectStatsRow(&mut transaction, project_id, analysis, false).await?;
+
+        let crateNames: Vec<&str> = analysis.crates.iter().map(|v| v.name.as_str()).collect();
+        sqlx::query("delete from project_crates where project_id = $1 and name <> all($2)")
+            .bind(project_id)
+            .bind(&crateNames[..])
+            .execute(&mut transaction)
+            .await?;
+        for projectCrate in &analysis.crates {
+            let crateAnalysis = &projectCrate.analysis;
+            let production = crateAnalysis.production();
+            sqlx::query(
+                "
+                insert into project_crates (
+                    project_id, name, path, unsafe_lines,
+                    code_lines, comment_lines, doc_comment_lines, blank_lines,
+                    unsafe_blocks, unsafe_functions, unsafe_impls, unsafe_traits,
+                    extern_blocks, no_mangle_items, unsafe_block_lines,
+                    documented_unsafe, undocumented_unsafe,
+                    production_code_lines, production_unsafe_lines, unsafe_code_lint
+                )
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+                on conflict (project_id, name) do update
+                set updated_at = current_date,
+                    path = excluded.path,
+                    unsafe_lines = excluded.unsafe_lines,
+                    code_lines = excluded.code_lines,
+                    comment_lines = excluded.comment_lines,
+                    doc_comment_lines = excluded.doc_comment_lines,
+                    blank_lines = excluded.blank_lines,
+                    unsafe_blocks = excluded.unsafe_blocks,
+                    unsafe_functions = excluded.unsafe_functions,
+                    unsafe_impls = excluded.unsafe_impls,
+                    unsafe_traits = excluded.unsafe_traits,
+                    extern_blocks = excluded.extern_blocks,
+                    no_mangle_items = excluded.no_mangle_items,
+                    unsafe_block_lines = excluded.unsafe_block_lines,
+                    documented_unsafe = excluded.documented_unsafe,
+                    undocumented_unsafe = excluded.undocumented_unsafe,
+                    production_code_lines = excluded.production_code_lines,
+                    production_unsafe_lines = excluded.production_unsafe_lines,
+                    unsafe_code_lint = excluded.unsafe_code_lint",
+            )
+            .bind(project_id)
+            .bind(&projectCrate.name)
+            .bind(&projectCrate.path)
+            .bind(crateAnalysis.unsafe_usage.unsafe_block_lines)
+            .bind(crateAnalysis.line_counts.code_lines)
+            .bind(crateAnalysis.line_counts.comment_lines)
+            .bind(crateAnalysis.line_counts.doc_comment_lines)
+            .bind(crateAnalysis.line_counts.blank_lines)
+            .bind(crateAnalysis.unsafe_usage.unsafe_blocks)
+            .bind(crateAnalysis.unsafe_usage.unsafe_functions)
+            .bind(crateAnalysis.unsafe_usage.unsafe_impls)
+            .bind(crateAnalysis.unsafe_usage.unsafe_traits)
+            .bind(crateAnalysis.unsafe_usage.extern_blocks)
+            .bind(crateAnalysis.unsafe_usage.no_mangle_items)
+            .bind(crateAnalysis.unsafe_usage.unsafe_block_lines)
+            .bind(crateAnalysis.unsafe_usage.documented_unsafe)
+            .bind(crateAnalysis.unsafe_usage.undocumented_unsafe)
+            .bind(production.line_counts.code_lines)
+            .bind(production.unsafe_usage.unsafe_block_lines)
+            .bind(crateAnalysis.unsafe_code_lint.asStr())
+            .execute(&mut transaction)
+            .await?;
+        }
+
+        // The sites always reflect the latest analysis.
+        sqlx::query("delete from unsafe_sites where project_id = $1")
+            .bind(project_id)
+            .execute(&mut transaction)
+            .await?;
+        // Stay below the limit of 65535 bind parameters per statement.
+        for sites in analysis.unsafe_sites.chunks(1000) {
+            let mut queryBuilder = QueryBuilder::new(
+                "insert into unsafe_sites (
+                    project_id, commit_sha, file_path, start_line, end_line,
+                    kind, item_path, category, documented
+                ) ",
+            );
+            queryBuilder.push_values(sites, |mut row, site| {
+                row.push_bind(project_id)
+                    .push_bind(&analysis.commit_sha)
+                    .push_bind(&site.file_path)
+                    .push_bind(site.site.start_line)
+                    .push_bind(site.site.end_line)
+                    .push_bind(site.site.kind.asStr())
+                    .push_bind(&site.site.item_path)
+                    .push_bind(site.category.asStr())
+                    .push_bind(site.site.documented);
+            });
+            queryBuilder.build().execute(&mut transaction).await?;
+        }
+
+        transaction.commit().await?;
         return Ok(());
     }
 
-    pub async fn logError(&self, error: &str) -> Result<(), String> {
+    async fn insertJob(
+        transaction: &mut Transaction<'_, Postgres>,
+        kind: &str,
+        projectIds: &[i32],
+    ) -> Result<i32, Error> {
+        let (id,): (i32,) = sqlx::query_as("insert into jobs (kind) values ($1) returning id")
+            .bind(kind)
+            .fetch_one(&mut *transaction)
+            .await?;
+        sqlx::query("insert into job_projects (job_id, project_id) select $1, unnest($2::int[])")
+            .bind(id)
+            .bind(projectIds)
+            .execute(&mut *transaction)
+            .await?;
+        // A job without projects has nothing to wait for.
+        if projectIds.is_empty() {
+            sqlx::query(
+                "update jobs set status = 'completed', started_at = now(), finished_at = now() where id = $1",
+            )
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        return Ok(id);
+    }
+}
+
+#[async_trait]
+impl Repository for PostgresService {
+    async fn logError(&self, error: &str) -> Result<(), DatabaseError> {
         let dbResult = sqlx::query("insert into error_log(error) Values($1)")
             .bind(error)
             .execute(&self.connection)
@@ -95,7 +429,84 @@ impl PostgresService {
         return Ok(());
     }
 
-    pub async fn getProjectsWithUrl(&self) -> Result<Vec<ProjectWithUrl>, StatusCode> {
+    async fn getProviders(&self) -> Result<Vec<Provider>, DatabaseError> {
+        let providers: Vec<Provider> = sqlx::query_as("select * from providers")
+            .fetch_all(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("getProviders", e))?;
+        return Ok(providers);
+    }
+
+    async fn getProviderById(&self, id: i32) -> Result<Vec<Provider>, DatabaseError> {
+        let providers: Vec<Provider> = sqlx::query_as("select * from providers where id = $1")
+            .bind(id)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("getProviderById", e))?;
+        return Ok(providers);
+    }
+
+    async fn insertProvider(&self, input: &ProviderInput) -> Result<Provider, DatabaseError> {
+        let provider: Provider =
+            sqlx::query_as("insert into providers (url) values ($1) returning *")
+                .bind(&input.url)
+                .fetch_one(&self.connection)
+                .await
+                .map_err(|e| DatabaseError::new("insertProvider", e))?;
+        return Ok(provider);
+    }
+
+    async fn updateProvider(
+        &self,
+        id: i32,
+        input: &ProviderInput,
+    ) -> Result<Option<Provider>, DatabaseError> {
+        let provider: Option<Provider> =
+            sqlx::query_as("update providers set url = $2 where id = $1 returning *")
+                .bind(id)
+                .bind(&input.url)
+                .fetch_optional(&self.connection)
+                .await
+                .map_err(|e| DatabaseError::new("updateProvider", e))?;
+        return Ok(provider);
+    }
+
+    async fn deleteProvider(&self, id: i32) -> Result<bool, DatabaseError> {
+        let result = sqlx::query("delete from providers where id = $1")
+            .bind(id)
+            .execute(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("deleteProvider", e))?;
+        return Ok(result.rows_affected() > 0);
+    }
+
+    async fn createProvider(&self, url: &str) -> Result<(), DatabaseError> {
+        sqlx::query("insert into providers (url) values ($1) on conflict (url) do nothing")
+            .bind(url)
+            .execute(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("createProvider", e))?;
+        return Ok(());
+    }
+
+    async fn getProjects(&self) -> Result<Vec<Project>, DatabaseError> {
+        let projects: Vec<Project> = sqlx::query_as("select * from projects")
+            .fetch_all(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("getProjects", e))?;
+        return Ok(projects);
+    }
+
+    async fn getProjectById(&self, id: i32) -> Result<Vec<Project>, DatabaseError> {
+        let projects: Vec<Project> = sqlx::query_as("select * from projects where id = $1")
+            .bind(id)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("getProjectById", e))?;
+        return Ok(projects);
+    }
+
+    async fn getProjectsWithUrl(&self) -> Result<Vec<ProjectWithUrl>, DatabaseError> {
         let result: Result<Vec<ProjectWithUrl>, Error> = sqlx::query_as(
             "
             select
@@ -105,89 +516,200 @@ impl PostgresService {
             inner join providers on providers.id = projects.provider_id
          ",
         )
-            .fetch_all(&self.connection)
-            .await;
-        if let Err(e) = result {
-            let error = format!(
-                "DatabaseService::getProjectsWithUrl failed to retrieve data, with error: {:?}",
-                e
-            );
-            let _ = self.logError(&error).await;
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        };
-        return Ok(result.unwrap());
+        .fetch_all(&self.connection)
+        .await;
+        return result.map_err(|e| DatabaseError::new("getProjectsWithUrl", e));
     }
 
-    pub async fn getProjectsStatsById(&self, id: i32) -> Result<Vec<ProjectStats>, String> {
-        let projectStats: Vec<ProjectStats> = sqlx::query_as(
+    async fn insertProject(&self, input: &ProjectInput) -> Result<Project, DatabaseError> {
+        let project: Project = sqlx::query_as(
             "
-        select
-        project_id
-        ,code_lines
-        ,unsafe_lines
-        ,COALESCE(cast(created_at as text), '') as created_at
-        ,COALESCE(cast(updated_at as text), '') as updated_at
-        from project_stats
-        where project_id = $1
-        order by created_at desc",
+        insert into projects (provider_id, namespace, name)
+        values ($1, $2, $3)
+        returning id, provider_id, namespace, name",
         )
+        .bind(input.provider_id)
+        .bind(&input.namespace)
+        .bind(&input.name)
+        .fetch_one(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("insertProject", e))?;
+        return Ok(project);
+    }
+
+    async fn updateProject(
+        &self,
+        id: i32,
+        input: &ProjectInput,
+    ) -> Result<Option<Project>, DatabaseError> {
+        let project: Option<Project> = sqlx::query_as(
+            "
+        update projects
+        set provider_id = $2
+        ,namespace = $3
+        ,name = $4
+        where id = $1
+        returning id, provider_id, namespace, name",
+        )
+        .bind(id)
+        .bind(input.provider_id)
+        .bind(&input.namespace)
+        .bind(&input.name)
+        .fetch_optional(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("updateProject", e))?;
+        return Ok(project);
+    }
+
+    async fn deleteProject(&self, id: i32) -> Result<bool, DatabaseError> {
+        let result = sqlx::query("delete from projects where id = $1")
             .bind(id)
-            .fetch_all(&self.connection)
+            .execute(&self.connection)
             .await
-            .map_err(|e| format!("DatabaseService.getProjectsStats failed: {:?}", e))?;
-        return Ok(projectStats);
+            .map_err(|e| DatabaseError::new("deleteProject", e))?;
+        return Ok(result.rows_affected() > 0);
     }
 
-    pub async fn getProjectsStats(
+    async fn createProject(
         &self,
+        providerUrl: &str,
+        namespace: &str,
         name: &str,
-        name_filtering: &str,
-        limit: u32,
-        page: u32,
-    ) -> Result<ProjectStatsWithMeta, StatusCode> {
-        let query = format!(
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "
+        insert into projects (provider_id, namespace, name)
+        values ((select id from providers where url = $1), $2, $3)
+        on conflict (name) do nothing",
+        )
+        .bind(providerUrl)
+        .bind(namespace)
+        .bind(name)
+        .execute(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("createProject", e))?;
+        return Ok(());
+    }
+
+    async fn getProjectsStats(
+        &self,
+        query: &ProjectStatsQuery,
+    ) -> Result<ProjectStatsWithMeta, DatabaseError> {
+        let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(
             "
 select t.project_id
      , t.name
      , t.url
      , t.code_lines
+     , t.comment_lines
+     , t.doc_comment_lines
+     , t.blank_lines
      , t.unsafe_lines
+     , t.unsafe_blocks
+     , t.unsafe_functions
+     , t.unsafe_impls
+     , t.unsafe_traits
+     , t.extern_blocks
+     , t.no_mangle_items
+     , t.unsafe_block_lines
+     , t.documented_unsafe
+     , t.undocumented_unsafe
+     , round(100.0 * t.documented_unsafe / nullif(t.documented_unsafe + t.undocumented_unsafe, 0), 2)::float8 as safety_coverage
+     , t.production_code_lines
+     , t.production_unsafe_lines
+     , t.unsafe_code_lint
+     , t.commit_sha
+     , t.commit_branch
+     , t.commit_date
      , t.created_at
      , t.updated_at
      , COUNT(project_id) OVER () as total
 from (
-     select RANK() OVER (partition by ps.project_id ORDER BY ps.created_at desc) as rank_order
+     select row_number() over (partition by ps.project_id order by ps.historical, ps.created_at desc, ps.id desc) as rank_order
           , ps.project_id
           , p.name
+          , p.provider_id
           , concat(providers.url, '/', p.namespace, '/', p.name)                 as url
           , ps.code_lines
+          , ps.comment_lines
+          , ps.doc_comment_lines
+          , ps.blank_lines
           , ps.unsafe_lines
+          , ps.unsafe_blocks
+          , ps.unsafe_functions
+          , ps.unsafe_impls
+          , ps.unsafe_traits
+          , ps.extern_blocks
+          , ps.no_mangle_items
+          , ps.unsafe_block_lines
+          , ps.documented_unsafe
+          , ps.undocumented_unsafe
+          , ps.production_code_lines
+          , ps.production_unsafe_lines
+          , ps.unsafe_code_lint
+          , ps.commit_sha
+          , ps.commit_branch
+          , (100.0 * ps.unsafe_lines / nullif(ps.code_lines, 0))::float8        as unsafe_ratio
+          , ps.updated_at                                                        as updated_on
+          , cast(ps.commit_date as text)                                           as commit_date
           , COALESCE(cast(ps.created_at as text), '')                            as created_at
           , COALESCE(cast(ps.updated_at as text), '')                            as updated_at
      from project_stats as ps
      inner join projects as p on p.id = ps.project_id
-     inner join providers on providers.id = p.provider_id
-     order by p.name) as t
-where t.rank_order = 1
-{name_filtering}
-limit {limit} offset ({limit} * {page});"
+     inner join providers on providers.id = p.provider_id) as t
+where t.rank_order = 1",
         );
-        let rowsResult = sqlx::query(query.as_ref())
-            .bind(name)
-            .fetch_all(&self.connection)
-            .await;
-
-        if let Err(e) = rowsResult {
-            let _ = self
-                .logError(&format!(
-                    "DatabaseService::getProjectsStats() failed to get rows: {:?}",
-                    e
-                ))
-                .await;
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        // A literal substring, so that `%` and `_` in the name are not wildcards.
+        if let Some(name) = &query.name {
+            builder
+                .push(" and t.name ilike concat('%', ")
+                .push_bind(escapeLike(name))
+                .push(r", '%') escape '\'");
+        }
+        if let Some(unsafeCodeLint) = query.unsafe_code_lint {
+            builder
+                .push(" and t.unsafe_code_lint = ")
+                .push_bind(unsafeCodeLint);
+        }
+        if let Some(ratio) = query.min_unsafe_ratio {
+            builder.push(" and t.unsafe_ratio >= ").push_bind(ratio);
+        }
+        if let Some(ratio) = query.max_unsafe_ratio {
+            builder.push(" and t.unsafe_ratio <= ").push_bind(ratio);
+        }
+        if let Some(codeLines) = query.min_code_lines {
+            builder.push(" and t.code_lines >= ").push_bind(codeLines);
+        }
+        if let Some(providerId) = query.provider_id {
+            builder.push(" and t.provider_id = ").push_bind(providerId);
+        }
+        if let Some(date) = &query.updated_since {
+            builder
+                .push(" and t.updated_on >= ")
+                .push_bind(date)
+                .push("::date");
+        }
+        // The column is one of a fixed set, never the input.
+        let column = match query.sort {
+            ProjectStatsSort::Name => "t.name",
+            ProjectStatsSort::CodeLines => "t.code_lines",
+            ProjectStatsSort::UnsafeLines => "t.unsafe_lines",
+            ProjectStatsSort::UnsafeRatio => "t.unsafe_ratio",
+            ProjectStatsSort::UpdatedAt => "t.updated_on",
         };
+        let direction = if query.descending { "desc" } else { "asc" };
+        builder.push(format!(
+            " order by {column} {direction} nulls last, t.name, t.project_id"
+        ));
+        let limit = i64::from(query.limit);
+        builder
+            .push(" limit ")
+            .push_bind(limit)
+            .push(" offset ")
+            .push_bind(limit * i64::from(query.page));
+        let rowsResult = builder.build().fetch_all(&self.connection).await;
 
-        let rows = rowsResult.unwrap();
+        let rows = rowsResult.map_err(|e| DatabaseError::new("getProjectsStats", e))?;
         let projectStats: Vec<ProjectStatsDTO> = rows
             .iter()
             .map(|row| {
@@ -195,8 +717,16 @@ limit {limit} offset ({limit} * {page});"
                     row.get("project_id"),
                     row.get("name"),
                     row.get("url"),
-                    row.get("code_lines"),
+                    LineCounts::from_row(row).expect("LineCounts::from_row failed"),
                     row.get("unsafe_lines"),
+                    UnsafeUsage::from_row(row).expect("UnsafeUsage::from_row failed"),
+                    row.get("safety_coverage"),
+                    row.get("production_code_lines"),
+                    row.get("production_unsafe_lines"),
+                    row.get("unsafe_code_lint"),
+                    row.get("commit_sha"),
+                    row.get("commit_branch"),
+                    row.get("commit_date"),
                     row.get("created_at"),
                     row.get("updated_at"),
                 );
@@ -217,113 +747,648 @@ limit {limit} offset ({limit} * {page});"
         return Ok(result);
     }
 
-    pub async fn updateProjectStatsById(
+    async fn getProjectsStatsById(&self, id: i32) -> Result<Vec<ProjectStats>, DatabaseError> {
+        let projectStats: Vec<ProjectStats> = sqlx::query_as(
+            "
+        select
+        project_id
+        ,code_lines
+        ,comment_lines
+        ,doc_comment_lines
+        ,blank_lines
+        ,unsafe_lines
+        ,unsafe_blocks
+        ,unsafe_functions
+        ,unsafe_impls
+        ,unsafe_traits
+        ,extern_blocks
+        ,no_mangle_items
+        ,unsafe_block_lines
+        ,documented_unsafe
+        ,undocumented_unsafe
+        ,round(100.0 * documented_unsafe / nullif(documented_unsafe + undocumented_unsafe, 0), 2)::float8 as safety_coverage
+        ,production_code_lines
+        ,production_unsafe_lines
+        ,unsafe_code_lint
+        ,commit_sha
+        ,commit_branch
+        ,cast(commit_date as text) as commit_date
+        ,COALESCE(cast(created_at as text), '') as created_at
+        ,COALESCE(cast(updated_at as text), '') as updated_at
+        from project_stats
+        where project_id = $1
+        order by created_at desc",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectsStats", e))?;
+        return Ok(projectStats);
+    }
+
+    async fn updateProjectStats(
         &self,
         project_id: i32,
-        code_lines: i32,
-        unsafe_lines: i32,
-    ) {
-        let query = format!(
+        analysis: &ProjectAnalysis,
+    ) -> Result<(), DatabaseError> {
+        self.upsertProjectStats(project_id, analysis)
+            .await
+            .map_err(|e| DatabaseError::new("updateProjectStats", e))?;
+        return Ok(());
+    }
+
+    async fn insertHistoricalProjectStats(
+        &self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+    ) -> Result<bool, DatabaseError> {
+        let result: Result<bool, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let projectStatsId =
+                Self::insertProjectStatsRow(&mut transaction, project_id, analysis, true).await?;
+            transaction.commit().await?;
+            return Ok(projectStatsId.is_some());
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("insertHistoricalProjectStats", e));
+    }
+
+    async fn getLatestCommitShaByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Option<String>, DatabaseError> {
+        let commitSha: Option<(Option<String>,)> = sqlx::query_as(
             "
-            DO
-            $do$
-                BEGIN
-                    if exists(
-                                select project_id
-                                from project_stats
-                                where project_id = {project_id}
-                                and unsafe_lines = {unsafe_lines}
-                              )
-                    then
-                        update project_stats
-                        set updated_at = current_date,
-                        code_lines = {code_lines}
-                        where project_id = {project_id}
-                        and unsafe_lines = {unsafe_lines};
-                    else
-                        insert into project_stats (project_id, code_lines, unsafe_lines)
-                        VALUES ({project_id}, {code_lines}, {unsafe_lines});
-                    end if;
-                END
-            $do$"
-        );
+        select commit_sha
+        from project_stats
+        where project_id = $1 and not historical
+        order by updated_at desc, id desc
+        limit 1",
+        )
+        .bind(id)
+        .fetch_optional(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getLatestCommitShaByProjectId", e))?;
+        return Ok(commitSha.and_then(|v| v.0));
+    }
 
-        let result = sqlx::query(&query).execute(&self.connection).await;
-        if let Err(e) = result {
-            let _ = self
-                .logError(&format!("Failed to update projectStats with e: {:?}", e))
-                .await;
+    async fn getProjectCategoryStatsById(
+        &self,
+        id: i32,
+    ) -> Result<Vec<ProjectCategoryStats>, DatabaseError> {
+        let categoryStats: Vec<ProjectCategoryStats> = sqlx::query_as(
+            "
+        select *
+        from project_stats_categories
+        where project_stats_id = (
+            select id
+            from project_stats
+            where project_id = $1
+            order by historical, created_at desc, id desc
+            limit 1
+        )
+        order by category",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectCategoryStatsById", e))?;
+        return Ok(categoryStats);
+    }
+
+    async fn getProjectCratesById(&self, id: i32) -> Result<Vec<ProjectCrate>, DatabaseError> {
+        let crates: Vec<ProjectCrate> = sqlx::query_as(
+            "
+        select
+        project_id
+        ,name
+        ,path
+        ,code_lines
+        ,comment_lines
+        ,doc_comment_lines
+        ,blank_lines
+        ,unsafe_lines
+        ,unsafe_blocks
+        ,unsafe_functions
+        ,unsafe_impls
+        ,unsafe_traits
+        ,extern_blocks
+        ,no_mangle_items
+        ,unsafe_block_lines
+        ,documented_unsafe
+        ,undocumented_unsafe
+        ,round(100.0 * documented_unsafe / nullif(documented_unsafe + undocumented_unsafe, 0), 2)::float8 as safety_coverage
+        ,production_code_lines
+        ,production_unsafe_lines
+        ,unsafe_code_lint
+        ,COALESCE(cast(created_at as text), '') as created_at
+        ,COALESCE(cast(updated_at as text), '') as updated_at
+        from project_crates
+        where project_id = $1
+        order by path",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectCratesById", e))?;
+        return Ok(crates);
+    }
+
+    async fn getUnsafeSitesByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Vec<UnsafeSiteDTO>, DatabaseError> {
+        let rows = sqlx::query(
+            "
+        select
+        s.file_path
+        ,s.start_line
+        ,s.end_line
+        ,s.kind
+        ,s.item_path
+        ,s.category
+        ,s.documented
+        ,s.commit_sha
+        ,p.id
+        ,p.namespace
+        ,p.name
+        ,pr.url
+        from unsafe_sites s
+        inner join projects p on p.id = s.project_id
+        inner join providers pr on pr.id = p.provider_id
+        where s.project_id = $1
+        order by s.file_path, s.start_line, s.id",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getUnsafeSitesByProjectId", e))?;
+
+        let sites = rows
+            .iter()
+            .map(|row| {
+                let project =
+                    ProjectWithUrl::from_row(row).expect("ProjectWithUrl::from_row failed");
+                let file_path: String = row.get("file_path");
+                let start_line: i32 = row.get("start_line");
+                let end_line: i32 = row.get("end_line");
+                let commit_sha: Option<String> = row.get("commit_sha");
+                let permalink = UnsafeSiteDTO::permalink(
+                    &project,
+                    commit_sha.as_deref(),
+                    &file_path,
+                    start_line,
+                    end_line,
+                );
+                return UnsafeSiteDTO {
+                    file_path,
+                    start_line,
+                    end_line,
+                    kind: row.get("kind"),
+                    item_path: row.get("item_path"),
+                    category: row.get("category"),
+                    documented: row.get("documented"),
+                    commit_sha,
+                    permalink,
+                };
+            })
+            .collect();
+        return Ok(sites);
+    }
+
+    async fn getSafetyCoverageByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Vec<FileSafetyCoverage>, DatabaseError> {
+        let coverage: Vec<FileSafetyCoverage> = sqlx::query_as(
+            "
+        select
+        t.file_path
+        ,t.documented_unsafe
+        ,t.undocumented_unsafe
+        ,round(100.0 * t.documented_unsafe / nullif(t.documented_unsafe + t.undocumented_unsafe, 0), 2)::float8 as safety_coverage
+        from (
+            select
+            file_path
+            ,count(*) filter (where documented)::int as documented_unsafe
+            ,count(*) filter (where not documented)::int as undocumented_unsafe
+            from unsafe_sites
+            where project_id = $1 and documented is not null
+            group by file_path
+        ) as t
+        order by t.file_path",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getSafetyCoverageByProjectId", e))?;
+        return Ok(coverage);
+    }
+}
+
+#[async_trait]
+impl JobStore for PostgresService {
+    async fn createJob(&self, kind: &str, projectIds: &[i32]) -> Result<i32, DatabaseError> {
+        let result: Result<i32, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let id = Self::insertJob(&mut transaction, kind, projectIds).await?;
+            transaction.commit().await?;
+            return Ok(id);
         }
+        .await;
+        return result.map_err(|e| DatabaseError::new("createJob", e));
     }
 
-    pub async fn getProviders(&self) -> Result<Vec<Provider>, String> {
-        let providers: Vec<Provider> = sqlx::query_as("select * from providers")
-            .fetch_all(&self.connection)
-            .await
-            .map_err(|e| format!("DatabaseService.get_providers failed: {:?}", e))?;
-        return Ok(providers);
+    async fn createBackfillJob(
+        &self,
+        kind: &str,
+        projectId: i32,
+        options: &BackfillOptions,
+    ) -> Result<i32, DatabaseError> {
+        let result: Result<i32, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let id = Self::insertJob(&mut transaction, kind, &[projectId]).await?;
+            sqlx::query("update jobs set backfill_every = $2, backfill_limit = $3 where id = $1")
+                .bind(id)
+                .bind(options.every.map(|v| v as i32))
+                .bind(options.limit.map(|v| v as i32))
+                .execute(&mut transaction)
+                .await?;
+            transaction.commit().await?;
+            return Ok(id);
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("createBackfillJob", e));
     }
 
-    pub async fn getProviderById(&self, id: i32) -> Result<Vec<Provider>, String> {
-        let providers: Vec<Provider> = sqlx::query_as("select * from providers where id = $1")
-            .bind(id)
-            .fetch_all(&self.connection)
-            .await
-            .map_err(|e| format!("DatabaseService.get_provider_by_id failed: {:?}", e))?;
-        return Ok(providers);
+    /// Queues a job over the projects whose next run is due, and moves their next
+    /// run by their interval. Returns None if no project is due. The due projects
+    /// are locked until the job is committed, so when several instances share the
+    /// database, each project is queued by only one of them.
+    async fn scheduleDueProjects(&self, kind: &str) -> Result<Option<i32>, DatabaseError> {
+        let result: Result<Option<i32>, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let projectIds: Vec<(i32,)> = sqlx::query_as(
+                "
+            select id
+            from projects
+            where next_run_at <= now()
+            order by id
+            for update skip locked",
+            )
+            .fetch_all(&mut transaction)
+            .await?;
+            if projectIds.is_empty() {
+                return Ok(None);
+            }
+            let projectIds: Vec<i32> = projectIds.into_iter().map(|v| v.0).collect();
+
+            sqlx::query(
+                "
+            update projects
+            set last_run_at = now()
+            ,next_run_at = now() + make_interval(hours => update_interval_hours)
+            where id = any($1)",
+            )
+            .bind(&projectIds[..])
+            .execute(&mut transaction)
+            .await?;
+            let id = Self::insertJob(&mut transaction, kind, &projectIds).await?;
+            transaction.commit().await?;
+            return Ok(Some(id));
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("scheduleDueProjects", e));
     }
 
-    pub async fn getProjects(&self) -> Result<Vec<Project>, String> {
-        let projects: Vec<Project> = sqlx::query_as("select * from projects")
-            .fetch_all(&self.connection)
-            .await
-            .map_err(|e| format!("DatabaseService.get_projects failed: {:?}", e))?;
-        return Ok(projects);
+    /// Marks the next due project of any job as running and returns it. Projects
+    /// that have been running for longer than `staleAfter` are claimed again,
+    /// since their worker is gone. `skip locked` lets every worker of every
+    /// server instance claim a different project.
+    async fn claimJobProject(
+        &self,
+        staleAfter: std::time::Duration,
+    ) -> Result<Option<ClaimedJobProject>, DatabaseError> {
+        let claimed: Option<ClaimedJobProject> = sqlx::query_as(
+            "
+        with claimed as (
+            update job_projects
+            set status = 'running', attempts = attempts + 1, started_at = now(), finished_at = null
+            where id = (
+                select id
+                from job_projects
+                where (status = 'queued' and run_after <= now())
+                or (status = 'running' and started_at < now() - make_interval(secs => $1))
+                order by run_after, id
+                for update skip locked
+                limit 1
+            )
+            returning id, job_id, project_id, attempts
+        ), started as (
+            update jobs
+            set status = 'running', started_at = coalesce(started_at, now())
+            where id = (select job_id from claimed) and status = 'queued'
+        )
+        select
+        c.id as job_project_id
+        ,c.job_id
+        ,j.kind
+        ,j.backfill_every
+        ,j.backfill_limit
+        ,c.attempts
+        ,p.id
+        ,p.namespace
+        ,p.name
+        ,pr.url
+        from claimed c
+        inner join jobs j on j.id = c.job_id
+        inner join projects p on p.id = c.project_id
+        inner join providers pr on pr.id = p.provider_id",
+        )
+        .bind(staleAfter.as_secs_f64())
+        .fetch_optional(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("claimJobProject", e))?;
+        return Ok(claimed);
     }
 
-    pub async fn getProjectById(&self, id: i32) -> Result<Vec<Project>, String> {
-        let projects: Vec<Project> = sqlx::query_as("select * from projects where id = $1")
-            .bind(id)
-            .fetch_all(&self.connection)
-            .await
-            .map_err(|e| format!("DatabaseService.get_project_by_id failed: {:?}", e))?;
-        return Ok(projects);
+    async fn finishJobProject(
+        &self,
+        jobProjectId: i32,
+        status: JobProjectStatus,
+        error: Option<&str>,
+    ) -> Result<bool, DatabaseError> {
+        let result: Result<bool, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let (jobId,): (i32,) = sqlx::query_as(
+                "
+            update job_projects
+            set status = $2, error = $3, finished_at = now()
+            where id = $1
+            returning job_id",
+            )
+            .bind(jobProjectId)
+            .bind(status.asStr())
+            .bind(error)
+            .fetch_one(&mut transaction)
+            .await?;
+            let completed = sqlx::query(
+                "
+            update jobs
+            set status = 'completed', finished_at = now()
+            where id = $1 and status <> 'completed' and not exists (
+                select 1 from job_projects where job_id = $1 and status in ('queued', 'running')
+            )",
+            )
+            .bind(jobId)
+            .execute(&mut transaction)
+            .await?;
+            transaction.commit().await?;
+            return Ok(completed.rows_affected() > 0);
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("finishJobProject", e));
     }
 
-    pub async fn createProject(
+    async fn retryJobProject(
         &self,
-        providerUrl: &str,
-        namespace: &str,
-        name: &str,
-    ) -> Result<(), StatusCode> {
-        let result = sqlx::query(&format!(
+        jobProjectId: i32,
+        error: &str,
+        delay: std::time::Duration,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "
+        update job_projects
+        set status = 'queued', error = $2, run_after = now() + make_interval(secs => $3)
+        where id = $1",
+        )
+        .bind(jobProjectId)
+        .bind(error)
+        .bind(delay.as_secs_f64())
+        .execute(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("retryJobProject", e))?;
+        return Ok(());
+    }
+
+    async fn getJobProgressById(&self, id: i32) -> Result<JobProgress, DatabaseError> {
+        let progress: JobProgress = sqlx::query_as(&format!(
             "
-                    do
-                    $do$
-                    begin
-                    if not exists (select id from projects where name = '{name}') then
-                        insert into projects (provider_id, namespace, name)
-                         VALUES (
-                         (select id from providers where url = '{providerUrl}'),
-                         '{namespace}',
-                         '{name}'
-                         );
-                    end if;
-                    end
-                    $do$
-        "
+        select
+        {JOB_PROGRESS_COLUMNS}
+        from job_projects jp
+        where jp.job_id = $1"
         ))
+        .bind(id)
+        .fetch_one(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getJobProgressById", e))?;
+        return Ok(progress);
+    }
+
+    async fn getJobById(&self, id: i32) -> Result<Option<JobDTO>, DatabaseError> {
+        let job: Option<Job> = sqlx::query_as(&format!(
+            "
+        select
+        j.id
+        ,j.kind
+        ,j.status
+        ,{JOB_PROGRESS_COLUMNS}
+        ,cast(j.created_at as text) as created_at
+        ,cast(j.started_at as text) as started_at
+        ,cast(j.finished_at as text) as finished_at
+        from jobs j
+        left join job_projects jp on jp.job_id = j.id
+        where j.id = $1
+        group by j.id"
+        ))
+        .bind(id)
+        .fetch_optional(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getJobById", e))?;
+        let Some(job) = job else {
+            return Ok(None);
+        };
+
+        let projects: Vec<JobProject> = sqlx::query_as(
+            "
+        select
+        jp.project_id
+        ,p.namespace
+        ,p.name
+        ,jp.status
+        ,jp.attempts
+        ,jp.error
+        ,cast(jp.run_after as text) as run_after
+        ,cast(jp.started_at as text) as started_at
+        ,cast(jp.finished_at as text) as finished_at
+        from job_projects jp
+        inner join projects p on p.id = jp.project_id
+        where jp.job_id = $1
+        order by jp.id",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getJobById", e))?;
+        return Ok(Some(JobDTO { job, projects }));
+    }
+
+    async fn notifyJobEvent(&self, event: &JobEvent) -> Result<(), DatabaseError> {
+        let payload = serde_json::to_string(event).map_err(|e| {
+            DatabaseError::Other(format!("DatabaseService.notifyJobEvent failed: {:?}", e))
+        })?;
+        sqlx::query("select pg_notify($1, $2)")
+            .bind(JOB_EVENTS_CHANNEL)
+            .bind(payload)
             .execute(&self.connection)
-            .await;
+            .await
+            .map_err(|e| DatabaseError::new("notifyJobEvent", e))?;
+        return Ok(());
+    }
 
-        if let Err(e) = result {
-            let _ = self
-                .logError(&format!("DatabaseService::createProject failed: {:?}", e))
-                .await;
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    async fn listenJobEvents(
+        &self,
+        events: &broadcast::Sender<JobEvent>,
+    ) -> Result<(), DatabaseError> {
+        let result: Result<(), Error> = async {
+            let mut listener = PgListener::connect_with(&self.connection).await?;
+            listener.listen(JOB_EVENTS_CHANNEL).await?;
+            loop {
+                let notification = listener.recv().await?;
+                if let Ok(event) = serde_json::from_str(notification.payload()) {
+                    let _ = events.send(event);
+                }
+            }
         }
+        .await;
+        return result.map_err(|e| DatabaseError::new("listenJobEvents", e));
+    }
+
+    async fn setProjectUpdateInterval(
+        &self,
+        namespace: &str,
+        name: &str,
+        updateIntervalHours: i32,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "
+        update projects
+        set update_interval_hours = $3
+        ,next_run_at = coalesce(last_run_at + make_interval(hours => $3), next_run_at)
+        where namespace = $1 and name = $2",
+        )
+        .bind(namespace)
+        .bind(name)
+        .bind(updateIntervalHours)
+        .execute(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("setProjectUpdateInterval", e))?;
+        return Ok(());
+    }
+
+    async fn getProjectsSchedule(
+        &self,
+        id: Option<i32>,
+    ) -> Result<Vec<ProjectSchedule>, DatabaseError> {
+        let schedule: Vec<ProjectSchedule> = sqlx::query_as(
+            "
+        select
+        p.id as project_id
+        ,p.namespace
+        ,p.name
+        ,p.update_interval_hours
+        ,cast(p.last_run_at as text) as last_run_at
+        ,cast(p.next_run_at as text) as next_run_at
+        ,(
+            select jp.status
+            from job_projects jp
+            where jp.project_id = p.id
+            order by jp.id desc
+            limit 1
+        ) as last_status
+        from projects p
+        where $1::int is null or p.id = $1
+        order by p.next_run_at, p.id",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectsSchedule", e))?;
+        return Ok(schedule);
+    }
+}
+
+#[async_trait]
+impl TokenStore for PostgresService {
+    async fn createApiToken(
+        &self,
+        name: &str,
+        scope: ApiTokenScope,
+        tokenHash: &str,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query("insert into api_tokens (name, scope, token_hash) values ($1, $2, $3)")
+            .bind(name)
+            .bind(scope.asStr())
+            .bind(tokenHash)
+            .execute(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("createApiToken", e))?;
         return Ok(());
     }
+
+    async fn getApiTokens(&self) -> Result<Vec<ApiToken>, DatabaseError> {
+        let tokens: Vec<ApiToken> = sqlx::query_as(
+            "
+        select
+        id
+        ,name
+        ,scope
+        ,cast(created_at as text) as created_at
+        ,cast(last_used_at as text) as last_used_at
+        ,cast(revoked_at as text) as revoked_at
+        from api_tokens
+        order by id",
+        )
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getApiTokens", e))?;
+        return Ok(tokens);
+    }
+
+    async fn revokeApiToken(&self, name: &str) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(
+            "update api_tokens set revoked_at = now() where name = $1 and revoked_at is null",
+        )
+        .bind(name)
+        .execute(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("revokeApiToken", e))?;
+        return Ok(result.rows_affected() > 0);
+    }
+
+    async fn useApiToken(&self, tokenHash: &str) -> Result<Option<ApiTokenScope>, DatabaseError> {
+        let scope: Option<(String,)> = sqlx::query_as(
+            "
+        update api_tokens
+        set last_used_at = now()
+        where token_hash = $1 and revoked_at is null
+        returning scope",
+        )
+        .bind(tokenHash)
+        .fetch_optional(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("useApiToken", e))?;
+        return Ok(scope.and_then(|v| v.0.parse().ok()));
+    }
+}
+
+/// Escapes the wildcards of a `like` pattern, with `\` as the escape character.
+fn escapeLike(value: &str) -> String {
+    return value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
 }
 
 #[cfg(test)]
@@ -331,6 +1396,12 @@ mod tests {
     use super::*;
     use crate::utils;
 
+    #[test]
+    fn testEscapeLike() {
+        assert_eq!(escapeLike("foo"), "foo");
+        assert_eq!(escapeLike(r"50%_a\b"), r"50\%\_a\\b");
+    }
+
     #[tokio::test]
     async fn testLogErrorToFilesystem() {
         let databaseService = PostgresService::new(None).await;
@@ -1,46 +1,134 @@
-use redis::{AsyncCommands, RedisError};
-use std::ops::DerefMut;
+use crate::services::cache::{Cache, CacheError};
+use async_trait::async_trait;
+use redis::{FromRedisValue, RedisError};
+use std::time::{Duration, Instant};
+
+/// The time to wait for Redis before skipping the cache.
+const TIMEOUT: Duration = Duration::from_secs(1);
+/// The time before a lost connection is retried.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+struct Connection {
+    connection: Option<redis::aio::Connection>,
+    /// Set when the connection is lost, until it is retried.
+    retryAt: Option<Instant>,
+}
 
 #[derive(Clone)]
 pub struct RedisService {
-    pub connection: std::sync::Arc<tokio::sync::Mutex<redis::aio::Connection>>,
+    client: redis::Client,
+    /// The time to live of the values and of the tags.
+    ttl: Duration,
+    connection: std::sync::Arc<tokio::sync::Mutex<Connection>>,
 }
 
 impl RedisService {
-    pub async fn new() -> RedisService {
+    /// Connects to `REDIS_HOST`. Fails if Redis is down, so that the caller can cache in memory.
+    pub async fn new(ttl: Duration) -> Result<RedisService, RedisError> {
         let redisHost = std::env::var("REDIS_HOST").expect("env::var REDIS_HOST failed");
-        let redisClient = redis::Client::open(format!("redis://{redisHost}"))
-            .expect("Failed to create Redis client");
-        let connection: redis::aio::Connection = redisClient
-            .get_async_connection()
-            .await
-            .expect("RedisService::new() failed");
-        let connection = std::sync::Arc::new(tokio::sync::Mutex::new(connection));
-        return RedisService { connection };
+        return RedisService::connect(&format!("redis://{redisHost}"), ttl).await;
     }
 
-    // Todo: Add delete
-    // Todo: Add tests
+    pub async fn connect(url: &str, ttl: Duration) -> Result<RedisService, RedisError> {
+        let client = redis::Client::open(url)?;
+        let connection = client.get_async_connection().await?;
+        let connection = Connection {
+            connection: Some(connection),
+            retryAt: None,
+        };
+        return Ok(RedisService {
+            client,
+            ttl,
+            connection: std::sync::Arc::new(tokio::sync::Mutex::new(connection)),
+        });
+    }
 
-    pub async fn setKey(&self, key: &str, value: &str) -> Result<String, RedisError> {
+    /// Runs the commands, reconnecting first if the connection was lost. A lost connection
+    /// is not retried before `RETRY_INTERVAL`, so that the requests are not slowed down
+    /// while Redis is away.
+    async fn query<T: FromRedisValue>(&self, commands: &redis::Pipeline) -> Result<T, CacheError> {
         let mut guard = self.connection.lock().await;
-        let connection = guard.deref_mut();
-        let value: String = connection.set(key, value).await?;
-        return Ok(value);
+        if guard.connection.is_none() {
+            if guard.retryAt.is_some_and(|v| Instant::now() < v) {
+                return Err(CacheError::Unavailable);
+            }
+            let connection = tokio::time::timeout(TIMEOUT, self.client.get_async_connection())
+                .await
+                .unwrap_or_else(|_| Err((redis::ErrorKind::IoError, "Timed out").into()));
+            match connection {
+                Ok(v) => {
+                    guard.connection = Some(v);
+                    guard.retryAt = None;
+                }
+                Err(e) => {
+                    guard.retryAt = Some(Instant::now() + RETRY_INTERVAL);
+                    return Err(CacheError::Failed(format!("Redis reconnect failed: {e}")));
+                }
+            }
+        }
+
+        let connection = guard.connection.as_mut().unwrap();
+        let result = tokio::time::timeout(TIMEOUT, commands.query_async(connection))
+            .await
+            .unwrap_or_else(|_| Err((redis::ErrorKind::IoError, "Timed out").into()));
+        if let Err(e) = &result {
+            if e.is_io_error() || e.is_connection_dropped() {
+                guard.connection = None;
+                guard.retryAt = Some(Instant::now() + RETRY_INTERVAL);
+            }
+        }
+        return result.map_err(|e| CacheError::Failed(format!("Redis command failed: {e}")));
     }
+}
 
-    pub async fn getKey(&self, key: &str) -> Result<String, RedisError> {
-        let mut guard = self.connection.lock().await;
-        let connection = guard.deref_mut();
-        let value: String = connection.get(key).await?;
+#[async_trait]
+impl Cache for RedisService {
+    async fn getKey(&self, key: &str) -> Result<Option<String>, CacheError> {
+        let (value,) = self.query(redis::pipe().cmd("GET").arg(key)).await?;
         return Ok(value);
     }
 
-    pub async fn flush(&self) -> Result<String, RedisError> {
-        let mut guard = self.connection.lock().await;
-        let connection = guard.deref_mut();
-        let value = redis::cmd("FLUSHDB").query_async(connection).await?;
-        return Ok(value);
+    /// Every tag is a set of keys, which lives as long as its newest key.
+    async fn setKey(&self, key: &str, value: &str, tags: &[String]) -> Result<(), CacheError> {
+        let ttl = self.ttl.as_secs().max(1);
+        let mut commands = redis::pipe();
+        commands.atomic();
+        commands
+            .cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("EX")
+            .arg(ttl)
+            .ignore();
+        for tag in tags {
+            commands.cmd("SADD").arg(tag).arg(key).ignore();
+            commands.cmd("EXPIRE").arg(tag).arg(ttl).ignore();
+        }
+        let result = self.query(&commands).await;
+        return result;
+    }
+
+    /// Only the keys that were read are removed from the tags, so that the keys that are
+    /// stored in the meantime stay tagged.
+    async fn invalidateTags(&self, tags: &[String]) -> Result<(), CacheError> {
+        for tag in tags {
+            let (keys,): (Vec<String>,) =
+                self.query(redis::pipe().cmd("SMEMBERS").arg(tag)).await?;
+            if keys.is_empty() {
+                continue;
+            }
+            let mut commands = redis::pipe();
+            commands.atomic();
+            commands.cmd("DEL").arg(&keys).ignore();
+            commands.cmd("SREM").arg(tag).arg(&keys).ignore();
+            self.query::<()>(&commands).await?;
+        }
+        return Ok(());
+    }
+
+    async fn flush(&self) -> Result<(), CacheError> {
+        let result = self.query(redis::pipe().cmd("FLUSHDB").ignore()).await;
+        return result;
     }
 }
 
@@ -51,21 +139,77 @@ mod tests {
 
     #[tokio::test]
     async fn testRedisSetAndGet() {
-        let redisService = RedisService::new().await;
+        let redisService = RedisService::new(Duration::from_secs(60)).await.unwrap();
         let timestamp = utils::getTimestamp();
         let key = format!("{}_key", timestamp);
         let value = format!("{}_value", timestamp);
 
+        // Miss
+        let result = redisService.getKey(&key).await;
+        assert!(matches!(result, Ok(None)));
+
         // Setter
-        let result = redisService.setKey(&key, &value).await;
+        let result = redisService.setKey(&key, &value, &[]).await;
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "OK");
 
         // Getter
         let result = redisService.getKey(&key).await;
         assert!(result.is_ok());
         let retrievedValue = result.unwrap();
-        assert_eq!(value, retrievedValue);
+        assert_eq!(Some(value), retrievedValue);
+    }
+
+    #[tokio::test]
+    async fn testRedisInvalidateTags() {
+        let redisService = RedisService::new(Duration::from_secs(60)).await.unwrap();
+        let timestamp = utils::getTimestamp();
+        let tag = format!("{}_tag", timestamp);
+        let otherTag = format!("{}_other_tag", timestamp);
+        let keys = [1, 2, 3].map(|v| format!("{}_key_{}", timestamp, v));
+        redisService
+            .setKey(&keys[0], "value", std::slice::from_ref(&tag))
+            .await
+            .unwrap();
+        redisService
+            .setKey(&keys[1], "value", &[tag.clone(), otherTag.clone()])
+            .await
+            .unwrap();
+        redisService
+            .setKey(&keys[2], "value", std::slice::from_ref(&otherTag))
+            .await
+            .unwrap();
+
+        // Only the keys of the tag are removed.
+        redisService
+            .invalidateTags(std::slice::from_ref(&tag))
+            .await
+            .unwrap();
+        assert!(redisService.getKey(&keys[0]).await.unwrap().is_none());
+        assert!(redisService.getKey(&keys[1]).await.unwrap().is_none());
+        assert!(redisService.getKey(&keys[2]).await.unwrap().is_some());
+        // A tag without keys.
+        assert!(redisService.invalidateTags(&[tag]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn testRedisConnectionLost() {
+        // A server that closes the connection, and then goes away.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            drop(socket);
+        });
+        let redisService = RedisService::connect(&url, Duration::from_secs(60))
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        // The first command fails, and the next ones skip Redis until it is retried.
+        let result = redisService.getKey("key").await;
+        assert!(matches!(result, Err(CacheError::Failed(_))), "{result:?}");
+        let result = redisService.setKey("key", "value", &[]).await;
+        assert!(matches!(result, Err(CacheError::Unavailable)), "{result:?}");
     }
 
     #[tokio::test]
@@ -73,24 +217,21 @@ mod tests {
         // This test creates global mutation and makes the previous test fail.
         // Run it only locally if you need to.
 
-        // let redisService = RedisService::new().await;
+        // let redisService = RedisService::new(Duration::from_secs(60)).await.unwrap();
         // let timestamp = utils::getTimestamp();
         // let key = format!("{}_key", timestamp);
         // let value = format!("{}_value", timestamp);
         //
         // // Setter
-        // let result = redisService.setKey(&key, &value).await;
+        // let result = redisService.setKey(&key, &value, &[]).await;
         // assert!(result.is_ok());
-        // assert_eq!(result.unwrap(), "OK");
         //
         // // Flush
         // let result = redisService.flush().await;
         // assert!(result.is_ok());
-        // assert_eq!(result.unwrap(), "OK");
         //
         // // Getter
         // let result = redisService.getKey(&key).await;
-        // assert!(result.is_err());
-        // assert!(format!("{:?}", result).contains("nil"));
+        // assert!(matches!(result, Ok(None)));
     }
 }
@@ -0,0 +1,217 @@
+//! The storage of the server. `Repository` holds the providers, projects, stats and errors,
+//! and is implemented by `PostgresService`, `SqliteService`, and `InMemoryRepository` for the
+//! tests and the tooling without a database. `JobStore` holds the jobs and the schedule of the
+//! updates, and `TokenStore` the API tokens, which only the databases implement.
+
+use crate::analysis::ProjectAnalysis;
+use crate::models::{
+    backfill::BackfillOptions,
+    job::{ClaimedJobProject, JobDTO, JobEvent, JobProgress, JobProjectStatus},
+    pagination::ProjectStatsQuery,
+    project::{
+        FileSafetyCoverage, Project, ProjectCategoryStats, ProjectCrate, ProjectInput,
+        ProjectSchedule, ProjectStats, ProjectStatsWithMeta, ProjectWithUrl, UnsafeSiteDTO,
+    },
+    provider::{Provider, ProviderInput},
+    token::{ApiToken, ApiTokenScope},
+};
+use crate::services::postgres::DatabaseError;
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+#[async_trait]
+pub trait Repository: Send + Sync {
+    /// Stores an error for the maintainers.
+    async fn logError(&self, error: &str) -> Result<(), DatabaseError>;
+
+    async fn getProviders(&self) -> Result<Vec<Provider>, DatabaseError>;
+
+    async fn getProviderById(&self, id: i32) -> Result<Vec<Provider>, DatabaseError>;
+
+    /// Fails with `DatabaseError::Duplicate` if the url is taken.
+    async fn insertProvider(&self, input: &ProviderInput) -> Result<Provider, DatabaseError>;
+
+    /// Returns `None` if there is no provider with the id.
+    async fn updateProvider(
+        &self,
+        id: i32,
+        input: &ProviderInput,
+    ) -> Result<Option<Provider>, DatabaseError>;
+
+    /// Fails with `DatabaseError::Reference` if the provider still has projects.
+    async fn deleteProvider(&self, id: i32) -> Result<bool, DatabaseError>;
+
+    /// Inserts the provider unless it exists.
+    async fn createProvider(&self, url: &str) -> Result<(), DatabaseError>;
+
+    async fn getProjects(&self) -> Result<Vec<Project>, DatabaseError>;
+
+    async fn getProjectById(&self, id: i32) -> Result<Vec<Project>, DatabaseError>;
+
+    async fn getProjectsWithUrl(&self) -> Result<Vec<ProjectWithUrl>, DatabaseError>;
+
+    /// Fails with `DatabaseError::Reference` if the provider is missing,
+    /// and with `DatabaseError::Duplicate` if the name is taken.
+    async fn insertProject(&self, input: &ProjectInput) -> Result<Project, DatabaseError>;
+
+    /// Returns `None` if there is no project with the id.
+    async fn updateProject(
+        &self,
+        id: i32,
+        input: &ProjectInput,
+    ) -> Result<Option<Project>, DatabaseError>;
+
+    /// Deletes a project with its stats.
+    async fn deleteProject(&self, id: i32) -> Result<bool, DatabaseError>;
+
+    /// Inserts the project unless one with the name exists.
+    async fn createProject(
+        &self,
+        providerUrl: &str,
+        namespace: &str,
+        name: &str,
+    ) -> Result<(), DatabaseError>;
+
+    /// Returns a page of the latest stats of the projects.
+    async fn getProjectsStats(
+        &self,
+        query: &ProjectStatsQuery,
+    ) -> Result<ProjectStatsWithMeta, DatabaseError>;
+
+    /// Returns every stats of a project, newest first.
+    async fn getProjectsStatsById(&self, id: i32) -> Result<Vec<ProjectStats>, DatabaseError>;
+
+    /// Stores the stats of the latest analysis of a project. The stats with the same
+    /// unsafe_lines are updated, so that the history only records the changes.
+    async fn updateProjectStats(
+        &self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+    ) -> Result<(), DatabaseError>;
+
+    /// Inserts the stats of a past commit of a project, dated by the commit.
+    /// Returns false if the commit already has historical stats.
+    async fn insertHistoricalProjectStats(
+        &self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+    ) -> Result<bool, DatabaseError>;
+
+    /// Returns the commit of the latest analysis of a project, if it was a git repository.
+    async fn getLatestCommitShaByProjectId(&self, id: i32)
+        -> Result<Option<String>, DatabaseError>;
+
+    /// Returns the per category stats of the latest stats of a project.
+    async fn getProjectCategoryStatsById(
+        &self,
+        id: i32,
+    ) -> Result<Vec<ProjectCategoryStats>, DatabaseError>;
+
+    /// Returns the crates of the latest analysis of a project, by path.
+    async fn getProjectCratesById(&self, id: i32) -> Result<Vec<ProjectCrate>, DatabaseError>;
+
+    /// Returns the unsafe sites of the latest analysis of a project, by file and line.
+    async fn getUnsafeSitesByProjectId(&self, id: i32)
+        -> Result<Vec<UnsafeSiteDTO>, DatabaseError>;
+
+    /// Returns the share of the unsafe blocks and functions with a safety
+    /// justification, for every file of the latest analysis of a project.
+    async fn getSafetyCoverageByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Vec<FileSafetyCoverage>, DatabaseError>;
+}
+
+/// The queue of the update jobs, shared by the workers of every instance, and the
+/// schedule of the projects.
+#[async_trait]
+pub trait JobStore: Send + Sync {
+    /// Queues a job over the given projects and returns its id.
+    async fn createJob(&self, kind: &str, projectIds: &[i32]) -> Result<i32, DatabaseError>;
+
+    /// Queues a backfill of the history of a project and returns the job id.
+    async fn createBackfillJob(
+        &self,
+        kind: &str,
+        projectId: i32,
+        options: &BackfillOptions,
+    ) -> Result<i32, DatabaseError>;
+
+    /// Queues a job over the projects whose next run is due, and moves their next
+    /// run by their interval. Returns None if no project is due. A due project is
+    /// queued by only one of the instances that share the store.
+    async fn scheduleDueProjects(&self, kind: &str) -> Result<Option<i32>, DatabaseError>;
+
+    /// Marks the next due project of any job as running and returns it. Projects
+    /// that have been running for longer than `staleAfter` are claimed again,
+    /// since their worker is gone. Every worker claims a different project.
+    async fn claimJobProject(
+        &self,
+        staleAfter: Duration,
+    ) -> Result<Option<ClaimedJobProject>, DatabaseError>;
+
+    /// Records the outcome of a claimed project, and completes its job if it was
+    /// the last one. Returns whether the job was completed.
+    async fn finishJobProject(
+        &self,
+        jobProjectId: i32,
+        status: JobProjectStatus,
+        error: Option<&str>,
+    ) -> Result<bool, DatabaseError>;
+
+    /// Queues a claimed project again after a failed attempt.
+    async fn retryJobProject(
+        &self,
+        jobProjectId: i32,
+        error: &str,
+        delay: Duration,
+    ) -> Result<(), DatabaseError>;
+
+    async fn getJobProgressById(&self, id: i32) -> Result<JobProgress, DatabaseError>;
+
+    async fn getJobById(&self, id: i32) -> Result<Option<JobDTO>, DatabaseError>;
+
+    /// Sends a job event to the listeners of every instance.
+    async fn notifyJobEvent(&self, event: &JobEvent) -> Result<(), DatabaseError>;
+
+    /// Forwards the job events of every instance to the sender, until the
+    /// connection fails.
+    async fn listenJobEvents(
+        &self,
+        events: &broadcast::Sender<JobEvent>,
+    ) -> Result<(), DatabaseError>;
+
+    /// Sets how often the scheduler queues an update of a project.
+    async fn setProjectUpdateInterval(
+        &self,
+        namespace: &str,
+        name: &str,
+        updateIntervalHours: i32,
+    ) -> Result<(), DatabaseError>;
+
+    /// Returns the schedule of a project, or of every project without an id.
+    async fn getProjectsSchedule(
+        &self,
+        id: Option<i32>,
+    ) -> Result<Vec<ProjectSchedule>, DatabaseError>;
+}
+
+/// The API tokens of the admin endpoints, stored by their hash.
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    async fn createApiToken(
+        &self,
+        name: &str,
+        scope: ApiTokenScope,
+        tokenHash: &str,
+    ) -> Result<(), DatabaseError>;
+
+    async fn getApiTokens(&self) -> Result<Vec<ApiToken>, DatabaseError>;
+
+    /// Returns false if there is no active token with this name.
+    async fn revokeApiToken(&self, name: &str) -> Result<bool, DatabaseError>;
+
+    /// Returns the scope of the active token with this hash, and records its use.
+    async fn useApiToken(&self, tokenHash: &str) -> Result<Option<ApiTokenScope>, DatabaseError>;
+}
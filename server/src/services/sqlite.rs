@@ -0,0 +1,1197 @@
+//! The storage in SQLite, for running without Postgres, e.g. on a laptop or a CI box.
+//! Its schema is in `migrations-sqlite`. The file belongs to a single instance, so the
+//! job events go through a channel in the process instead of the database.
+
+use crate::analysis::ProjectAnalysis;
+use crate::models::{
+    backfill::BackfillOptions,
+    job::*,
+    pagination::{ProjectStatsQuery, ProjectStatsSort},
+    project::{
+        FileSafetyCoverage, Project, ProjectCategoryStats, ProjectCrate, ProjectInput,
+        ProjectSchedule, ProjectStats, ProjectStatsDTO, ProjectStatsWithMeta, ProjectWithUrl,
+        UnsafeSiteDTO,
+    },
+    provider::{Provider, ProviderInput},
+    token::{ApiToken, ApiTokenScope},
+};
+use crate::services::{
+    postgres::DatabaseError,
+    repository::{JobStore, Repository, TokenStore},
+};
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{Error, FromRow, QueryBuilder, Row, Sqlite, Transaction};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// The number of projects of a job in each status, over `job_projects jp`.
+const JOB_PROGRESS_COLUMNS: &str = "
+        count(jp.id) as total
+        ,count(jp.id) filter (where jp.status = 'queued') as queued
+        ,count(jp.id) filter (where jp.status = 'running') as running
+        ,count(jp.id) filter (where jp.status = 'succeeded') as succeeded
+        ,count(jp.id) filter (where jp.status = 'skipped') as skipped
+        ,count(jp.id) filter (where jp.status not in ('queued', 'running', 'succeeded', 'skipped')) as failed";
+
+/// The row of a write that returns one. The writes outside of a transaction fetch all
+/// their rows, since SQLite commits them only once their statement is stepped to the end,
+/// which `fetch_one` leaves to the connection after it has returned.
+fn single<T>(mut rows: Vec<T>) -> Result<T, Error> {
+    return rows.pop().ok_or(Error::RowNotFound);
+}
+
+#[derive(Clone)]
+pub struct SqliteService {
+    pub connection: SqlitePool,
+    /// The job events, from `notifyJobEvent` to `listenJobEvents`.
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl SqliteService {
+    pub async fn new(con: Option<SqlitePool>) -> Self {
+        let events = broadcast::channel(1024).0;
+        if let Some(connection) = con {
+            return Self { connection, events };
+        }
+
+        let url =
+            std::env::var("SQLITE_URL").unwrap_or_else(|_| "sqlite://unsaferust.db".to_owned());
+        let options = SqliteConnectOptions::from_str(&url)
+            .expect("Failed to parse SQLITE_URL")
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let connection = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .expect("SqlitePoolOptions initialization failed");
+
+        return Self { connection, events };
+    }
+
+    async fn insertJob(
+        transaction: &mut Transaction<'_, Sqlite>,
+        kind: &str,
+        projectIds: &[i32],
+    ) -> Result<i32, Error> {
+        let (id,): (i32,) = sqlx::query_as("insert into jobs (kind) values (?) returning id")
+            .bind(kind)
+            .fetch_one(&mut *transaction)
+            .await?;
+        for projectIds in projectIds.chunks(400) {
+            let mut queryBuilder =
+                QueryBuilder::new("insert into job_projects (job_id, project_id) ");
+            queryBuilder.push_values(projectIds, |mut row, projectId| {
+                row.push_bind(id).push_bind(projectId);
+            });
+            queryBuilder.build().execute(&mut *transaction).await?;
+        }
+        // A job without projects has nothing to wait for.
+        if projectIds.is_empty() {
+            sqlx::query(
+                "update jobs set status = 'completed', started_at = datetime('now'), finished_at = datetime('now') where id = ?",
+            )
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        return Ok(id);
+    }
+
+    /// Writes the project_stats row of an analysis and its categories, returning its id.
+    /// A historical row is dated by its commit and inserted once per commit, while a
+    /// current row updates the current row with the same unsafe_lines.
+    async fn insertProjectStatsRow(
+        transaction: &mut Transaction<'_, Sqlite>,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+        historical: bool,
+    ) -> Result<Option<i32>, Error> {
+        let production = analysis.production();
+        let (conflict, date) = if historical {
+            (
+                "(project_id, commit_sha) where historical do nothing",
+                "coalesce(date(?21), date('now'))",
+            )
+        } else {
+            (
+                "(project_id, unsafe_lines) where not historical do update
+            set updated_at = date('now'),
+                code_lines = excluded.code_lines,
+                comment_lines = excluded.comment_lines,
+                doc_comment_lines = excluded.doc_comment_lines,
+                blank_lines = excluded.blank_lines,
+                unsafe_blocks = excluded.unsafe_blocks,
+                unsafe_functions = excluded.unsafe_functions,
+                unsafe_impls = excluded.unsafe_impls,
+                unsafe_traits = excluded.unsafe_traits,
+                extern_blocks = excluded.extern_blocks,
+                no_mangle_items = excluded.no_mangle_items,
+                unsafe_block_lines = excluded.unsafe_block_lines,
+                documented_unsafe = excluded.documented_unsafe,
+                undocumented_unsafe = excluded.undocumented_unsafe,
+                production_code_lines = excluded.production_code_lines,
+                production_unsafe_lines = excluded.production_unsafe_lines,
+                unsafe_code_lint = excluded.unsafe_code_lint,
+                commit_sha = excluded.commit_sha,
+                commit_branch = excluded.commit_branch,
+                commit_date = excluded.commit_date",
+                "date('now')",
+            )
+        };
+        let query = format!(
+            "
+            insert into project_stats (
+                project_id, unsafe_lines,
+                code_lines, comment_lines, doc_comment_lines, blank_lines,
+                unsafe_blocks, unsafe_functions, unsafe_impls, unsafe_traits,
+                extern_blocks, no_mangle_items, unsafe_block_lines,
+                documented_unsafe, undocumented_unsafe,
+                production_code_lines, production_unsafe_lines, unsafe_code_lint,
+                commit_sha, commit_branch, commit_date, historical, created_at, updated_at
+            )
+            values (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18,
+                ?19, ?20, datetime(?21), ?22, {date}, {date}
+            )
+            on conflict {conflict}
+            returning id"
+        );
+        let projectStatsId: Option<(i32,)> = sqlx::query_as(&query)
+            .bind(project_id)
+            .bind(analysis.unsafe_usage.unsafe_block_lines)
+            .bind(analysis.line_counts.code_lines)
+            .bind(analysis.line_counts.comment_lines)
+            .bind(analysis.line_counts.doc_comment_lines)
+            .bind(analysis.line_counts.blank_lines)
+            .bind(analysis.unsafe_usage.unsafe_blocks)
+            .bind(analysis.unsafe_usage.unsafe_functions)
+            .bind(analysis.unsafe_usage.unsafe_impls)
+            .bind(analysis.unsafe_usage.unsafe_traits)
+            .bind(analysis.unsafe_usage.extern_blocks)
+            .bind(analysis.unsafe_usage.no_mangle_items)
+            .bind(analysis.unsafe_usage.unsafe_block_lines)
+            .bind(analysis.unsafe_usage.documented_unsafe)
+            .bind(analysis.unsafe_usage.undocumented_unsafe)
+            .bind(production.line_counts.code_lines)
+            .bind(production.unsafe_usage.unsafe_block_lines)
+            .bind(analysis.unsafe_code_lint.asStr())
+            .bind(&analysis.commit_sha)
+            .bind(&analysis.commit_branch)
+            .bind(&analysis.commit_date)
+            .bind(historical)
+            .fetch_optional(&mut *transaction)
+            .await?;
+        let Some((projectStatsId,)) = projectStatsId else {
+            return Ok(None);
+        };
+
+        sqlx::query("delete from project_stats_categories where project_stats_id = ?")
+            .bind(projectStatsId)
+            .execute(&mut *transaction)
+            .await?;
+        for (category, stats) in &analysis.categories {
+            sqlx::query(
+                "
+                insert into project_stats_categories (
+                    project_stats_id, category, unsafe_lines,
+                    code_lines, comment_lines, doc_comment_lines, blank_lines,
+                    unsafe_blocks, unsafe_functions, unsafe_impls, unsafe_traits,
+                    extern_blocks, no_mangle_items, unsafe_block_lines,
+                    documented_unsafe, undocumented_unsafe
+                )
+                values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(projectStatsId)
+            .bind(category.asStr())
+            .bind(stats.unsafe_usage.unsafe_block_lines)
+            .bind(stats.line_counts.code_lines)
+            .bind(stats.line_counts.comment_lines)
+            .bind(stats.line_counts.doc_comment_lines)
+            .bind(stats.line_counts.blank_lines)
+            .bind(stats.unsafe_usage.unsafe_blocks)
+            .bind(stats.unsafe_usage.unsafe_functions)
+            .bind(stats.unsafe_usage.unsafe_impls)
+            .bind(stats.unsafe_usage.unsafe_traits)
+            .bind(stats.unsafe_usage.extern_blocks)
+            .bind(stats.unsafe_usage.no_mangle_items)
+            .bind(stats.unsafe_usage.unsafe_block_lines)
+            .bind(stats.unsafe_usage.documented_unsafe)
+            .bind(stats.unsafe_usage.undocumented_unsafe)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        return Ok(Some(projectStatsId));
+    }
+}
+
+#[async_trait]
+impl Repository for SqliteService {
+    async fn logError(&self, error: &str) -> Result<(), DatabaseError> {
+        sqlx::query("insert into error_log (error) values (?)")
+            .bind(error)
+            .execute(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("logError", e))?;
+        return Ok(());
+    }
+
+    async fn getProviders(&self) -> Result<Vec<Provider>, DatabaseError> {
+        let providers: Vec<Provider> = sqlx::query_as("select * from providers")
+            .fetch_all(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("getProviders", e))?;
+        return Ok(providers);
+    }
+
+    async fn getProviderById(&self, id: i32) -> Result<Vec<Provider>, DatabaseError> {
+        let providers: Vec<Provider> = sqlx::query_as("select * from providers where id = ?")
+            .bind(id)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("getProviderById", e))?;
+        return Ok(providers);
+    }
+
+    async fn insertProvider(&self, input: &ProviderInput) -> Result<Provider, DatabaseError> {
+        let provider: Provider =
+            sqlx::query_as("insert into providers (url) values (?) returning *")
+                .bind(&input.url)
+                .fetch_all(&self.connection)
+                .await
+                .and_then(single)
+                .map_err(|e| DatabaseError::new("insertProvider", e))?;
+        return Ok(provider);
+    }
+
+    async fn updateProvider(
+        &self,
+        id: i32,
+        input: &ProviderInput,
+    ) -> Result<Option<Provider>, DatabaseError> {
+        let mut providers: Vec<Provider> =
+            sqlx::query_as("update providers set url = ? where id = ? returning *")
+                .bind(&input.url)
+                .bind(id)
+                .fetch_all(&self.connection)
+                .await
+                .map_err(|e| DatabaseError::new("updateProvider", e))?;
+        return Ok(providers.pop());
+    }
+
+    async fn deleteProvider(&self, id: i32) -> Result<bool, DatabaseError> {
+        let result = sqlx::query("delete from providers where id = ?")
+            .bind(id)
+            .execute(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("deleteProvider", e))?;
+        return Ok(result.rows_affected() > 0);
+    }
+
+    async fn createProvider(&self, url: &str) -> Result<(), DatabaseError> {
+        sqlx::query("insert into providers (url) values (?) on conflict (url) do nothing")
+            .bind(url)
+            .execute(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("createProvider", e))?;
+        return Ok(());
+    }
+
+    async fn getProjects(&self) -> Result<Vec<Project>, DatabaseError> {
+        let projects: Vec<Project> =
+            sqlx::query_as("select id, provider_id, namespace, name from projects")
+                .fetch_all(&self.connection)
+                .await
+                .map_err(|e| DatabaseError::new("getProjects", e))?;
+        return Ok(projects);
+    }
+
+    async fn getProjectById(&self, id: i32) -> Result<Vec<Project>, DatabaseError> {
+        let projects: Vec<Project> =
+            sqlx::query_as("select id, provider_id, namespace, name from projects where id = ?")
+                .bind(id)
+                .fetch_all(&self.connection)
+                .await
+                .map_err(|e| DatabaseError::new("getProjectById", e))?;
+        return Ok(projects);
+    }
+
+    async fn getProjectsWithUrl(&self) -> Result<Vec<ProjectWithUrl>, DatabaseError> {
+        let projects: Vec<ProjectWithUrl> = sqlx::query_as(
+            "
+            select
+            projects.id
+            , projects.namespace
+            , projects.name
+            , providers.url
+            from projects
+            inner join providers on providers.id = projects.provider_id",
+        )
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectsWithUrl", e))?;
+        return Ok(projects);
+    }
+
+    async fn insertProject(&self, input: &ProjectInput) -> Result<Project, DatabaseError> {
+        let project: Project = sqlx::query_as(
+            "
+        insert into projects (provider_id, namespace, name)
+        values (?, ?, ?)
+        returning id, provider_id, namespace, name",
+        )
+        .bind(input.provider_id)
+        .bind(&input.namespace)
+        .bind(&input.name)
+        .fetch_all(&self.connection)
+        .await
+        .and_then(single)
+        .map_err(|e| DatabaseError::new("insertProject", e))?;
+        return Ok(project);
+    }
+
+    async fn updateProject(
+        &self,
+        id: i32,
+        input: &ProjectInput,
+    ) -> Result<Option<Project>, DatabaseError> {
+        let mut projects: Vec<Project> = sqlx::query_as(
+            "
+        update projects
+        set provider_id = ?
+        ,namespace = ?
+        ,name = ?
+        where id = ?
+        returning id, provider_id, namespace, name",
+        )
+        .bind(input.provider_id)
+        .bind(&input.namespace)
+        .bind(&input.name)
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("updateProject", e))?;
+        return Ok(projects.pop());
+    }
+
+    async fn deleteProject(&self, id: i32) -> Result<bool, DatabaseError> {
+        let result = sqlx::query("delete from projects where id = ?")
+            .bind(id)
+            .execute(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("deleteProject", e))?;
+        return Ok(result.rows_affected() > 0);
+    }
+
+    async fn createProject(
+        &self,
+        providerUrl: &str,
+        namespace: &str,
+        name: &str,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "
+        insert into projects (provider_id, namespace, name)
+        values ((select id from providers where url = ?), ?, ?)
+        on conflict (name) do nothing",
+        )
+        .bind(providerUrl)
+        .bind(namespace)
+        .bind(name)
+        .execute(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("createProject", e))?;
+        return Ok(());
+    }
+
+    async fn getProjectsStats(
+        &self,
+        query: &ProjectStatsQuery,
+    ) -> Result<ProjectStatsWithMeta, DatabaseError> {
+        let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "
+select t.*
+     , round(100.0 * t.documented_unsafe / nullif(t.documented_unsafe + t.undocumented_unsafe, 0), 2) as safety_coverage
+     , count(t.project_id) over () as total
+from (
+     select row_number() over (partition by ps.project_id order by ps.historical, ps.created_at desc, ps.id desc) as rank_order
+          , ps.project_id
+          , p.name
+          , p.provider_id
+          , providers.url || '/' || p.namespace || '/' || p.name as url
+          , ps.code_lines
+          , ps.comment_lines
+          , ps.doc_comment_lines
+          , ps.blank_lines
+          , ps.unsafe_lines
+          , ps.unsafe_blocks
+          , ps.unsafe_functions
+          , ps.unsafe_impls
+          , ps.unsafe_traits
+          , ps.extern_blocks
+          , ps.no_mangle_items
+          , ps.unsafe_block_lines
+          , ps.documented_unsafe
+          , ps.undocumented_unsafe
+          , ps.production_code_lines
+          , ps.production_unsafe_lines
+          , ps.unsafe_code_lint
+          , ps.commit_sha
+          , ps.commit_branch
+          , ps.commit_date
+          , ps.created_at
+          , ps.updated_at
+          , 100.0 * ps.unsafe_lines / nullif(ps.code_lines, 0) as unsafe_ratio
+     from project_stats ps
+     inner join projects p on p.id = ps.project_id
+     inner join providers on providers.id = p.provider_id) as t
+where t.rank_order = 1",
+        );
+        if let Some(name) = &query.name {
+            builder
+                .push(" and instr(lower(t.name), lower(")
+                .push_bind(name)
+                .push(")) > 0");
+        }
+        if let Some(unsafeCodeLint) = query.unsafe_code_lint {
+            builder
+                .push(" and t.unsafe_code_lint = ")
+                .push_bind(unsafeCodeLint);
+        }
+        if let Some(ratio) = query.min_unsafe_ratio {
+            builder.push(" and t.unsafe_ratio >= ").push_bind(ratio);
+        }
+        if let Some(ratio) = query.max_unsafe_ratio {
+            builder.push(" and t.unsafe_ratio <= ").push_bind(ratio);
+        }
+        if let Some(codeLines) = query.min_code_lines {
+            builder.push(" and t.code_lines >= ").push_bind(codeLines);
+        }
+        if let Some(providerId) = query.provider_id {
+            builder.push(" and t.provider_id = ").push_bind(providerId);
+        }
+        if let Some(updatedSince) = &query.updated_since {
+            builder
+                .push(" and t.updated_at >= ")
+                .push_bind(updatedSince);
+        }
+        // The column is one of a fixed set, never the input.
+        let column = match query.sort {
+            ProjectStatsSort::Name => "t.name",
+            ProjectStatsSort::CodeLines => "t.code_lines",
+            ProjectStatsSort::UnsafeLines => "t.unsafe_lines",
+            ProjectStatsSort::UnsafeRatio => "t.unsafe_ratio",
+            ProjectStatsSort::UpdatedAt => "t.updated_at",
+        };
+        let direction = if query.descending { "desc" } else { "asc" };
+        builder.push(format!(
+            " order by {column} {direction} nulls last, t.name, t.project_id"
+        ));
+        let limit = i64::from(query.limit);
+        builder
+            .push(" limit ")
+            .push_bind(limit)
+            .push(" offset ")
+            .push_bind(limit * i64::from(query.page));
+
+        let rows = builder
+            .build()
+            .fetch_all(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("getProjectsStats", e))?;
+        let projectStats = rows
+            .iter()
+            .map(ProjectStatsDTO::from_row)
+            .collect::<Result<Vec<ProjectStatsDTO>, Error>>()
+            .map_err(|e| DatabaseError::new("getProjectsStats", e))?;
+        let total: i64 = rows.first().map_or(0, |v| v.get("total"));
+
+        return Ok(ProjectStatsWithMeta {
+            projectStats,
+            meta: total,
+        });
+    }
+
+    async fn getProjectsStatsById(&self, id: i32) -> Result<Vec<ProjectStats>, DatabaseError> {
+        let projectStats: Vec<ProjectStats> = sqlx::query_as(
+            "
+        select
+        *
+        ,round(100.0 * documented_unsafe / nullif(documented_unsafe + undocumented_unsafe, 0), 2) as safety_coverage
+        from project_stats
+        where project_id = ?
+        order by created_at desc, id desc",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectsStatsById", e))?;
+        return Ok(projectStats);
+    }
+
+    async fn updateProjectStats(
+        &self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+    ) -> Result<(), DatabaseError> {
+        let result: Result<(), Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            Self::insertProjectStatsRow(&mut transaction, project_id, analysis, false).await?;
+
+            let mut deleteCrates =
+                QueryBuilder::new("delete from project_crates where project_id = ");
+            deleteCrates
+                .push_bind(project_id)
+                .push(" and name not in (");
+            let mut names = deleteCrates.separated(", ");
+            for projectCrate in &analysis.crates {
+                names.push_bind(&projectCrate.name);
+            }
+            deleteCrates.push(")");
+            deleteCrates.build().execute(&mut transaction).await?;
+            for projectCrate in &analysis.crates {
+                let crateAnalysis = &projectCrate.analysis;
+                let production = crateAnalysis.production();
+                sqlx::query(
+                    "
+                insert into project_crates (
+                    project_id, name, path, unsafe_lines,
+                    code_lines, comment_lines, doc_comment_lines, blank_lines,
+                    unsafe_blocks, unsafe_functions, unsafe_impls, unsafe_traits,
+                    extern_blocks, no_mangle_items, unsafe_block_lines,
+                    documented_unsafe, undocumented_unsafe,
+                    production_code_lines, production_unsafe_lines, unsafe_code_lint
+                )
+                values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                on conflict (project_id, name) do update
+                set updated_at = date('now'),
+                    path = excluded.path,
+                    unsafe_lines = excluded.unsafe_lines,
+                    code_lines = excluded.code_lines,
+                    comment_lines = excluded.comment_lines,
+                    doc_comment_lines = excluded.doc_comment_lines,
+                    blank_lines = excluded.blank_lines,
+                    unsafe_blocks = excluded.unsafe_blocks,
+                    unsafe_functions = excluded.unsafe_functions,
+                    unsafe_impls = excluded.unsafe_impls,
+                    unsafe_traits = excluded.unsafe_traits,
+                    extern_blocks = excluded.extern_blocks,
+                    no_mangle_items = excluded.no_mangle_items,
+                    unsafe_block_lines = excluded.unsafe_block_lines,
+                    documented_unsafe = excluded.documented_unsafe,
+                    undocumented_unsafe = excluded.undocumented_unsafe,
+                    production_code_lines = excluded.production_code_lines,
+                    production_unsafe_lines = excluded.production_unsafe_lines,
+                    unsafe_code_lint = excluded.unsafe_code_lint",
+                )
+                .bind(project_id)
+                .bind(&projectCrate.name)
+                .bind(&projectCrate.path)
+                .bind(crateAnalysis.unsafe_usage.unsafe_block_lines)
+                .bind(crateAnalysis.line_counts.code_lines)
+                .bind(crateAnalysis.line_counts.comment_lines)
+                .bind(crateAnalysis.line_counts.doc_comment_lines)
+                .bind(crateAnalysis.line_counts.blank_lines)
+                .bind(crateAnalysis.unsafe_usage.unsafe_blocks)
+                .bind(crateAnalysis.unsafe_usage.unsafe_functions)
+                .bind(crateAnalysis.unsafe_usage.unsafe_impls)
+                .bind(crateAnalysis.unsafe_usage.unsafe_traits)
+                .bind(crateAnalysis.unsafe_usage.extern_blocks)
+                .bind(crateAnalysis.unsafe_usage.no_mangle_items)
+                .bind(crateAnalysis.unsafe_usage.unsafe_block_lines)
+                .bind(crateAnalysis.unsafe_usage.documented_unsafe)
+                .bind(crateAnalysis.unsafe_usage.undocumented_unsafe)
+                .bind(production.line_counts.code_lines)
+                .bind(production.unsafe_usage.unsafe_block_lines)
+                .bind(crateAnalysis.unsafe_code_lint.asStr())
+                .execute(&mut transaction)
+                .await?;
+            }
+
+            // The sites always reflect the latest analysis.
+            sqlx::query("delete from unsafe_sites where project_id = ?")
+                .bind(project_id)
+                .execute(&mut transaction)
+                .await?;
+            // Stay below the limit of 999 bind parameters per statement of older SQLite versions.
+            for sites in analysis.unsafe_sites.chunks(100) {
+                let mut queryBuilder = QueryBuilder::new(
+                    "insert into unsafe_sites (
+                    project_id, commit_sha, file_path, start_line, end_line,
+                    kind, item_path, category, documented
+                ) ",
+                );
+                queryBuilder.push_values(sites, |mut row, site| {
+                    row.push_bind(project_id)
+                        .push_bind(&analysis.commit_sha)
+                        .push_bind(&site.file_path)
+                        .push_bind(site.site.start_line)
+                        .push_bind(site.site.end_line)
+                        .push_bind(site.site.kind.asStr())
+                        .push_bind(&site.site.item_path)
+                        .push_bind(site.category.asStr())
+                        .push_bind(site.site.documented);
+                });
+                queryBuilder.build().execute(&mut transaction).await?;
+            }
+
+            transaction.commit().await?;
+            return Ok(());
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("updateProjectStats", e));
+    }
+
+    async fn insertHistoricalProjectStats(
+        &self,
+        project_id: i32,
+        analysis: &ProjectAnalysis,
+    ) -> Result<bool, DatabaseError> {
+        let result: Result<bool, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let projectStatsId =
+                Self::insertProjectStatsRow(&mut transaction, project_id, analysis, true).await?;
+            transaction.commit().await?;
+            return Ok(projectStatsId.is_some());
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("insertHistoricalProjectStats", e));
+    }
+
+    async fn getLatestCommitShaByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Option<String>, DatabaseError> {
+        let commitSha: Option<(Option<String>,)> = sqlx::query_as(
+            "
+        select commit_sha
+        from project_stats
+        where project_id = ? and not historical
+        order by updated_at desc, id desc
+        limit 1",
+        )
+        .bind(id)
+        .fetch_optional(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getLatestCommitShaByProjectId", e))?;
+        return Ok(commitSha.and_then(|v| v.0));
+    }
+
+    async fn getProjectCategoryStatsById(
+        &self,
+        id: i32,
+    ) -> Result<Vec<ProjectCategoryStats>, DatabaseError> {
+        let categoryStats: Vec<ProjectCategoryStats> = sqlx::query_as(
+            "
+        select *
+        from project_stats_categories
+        where project_stats_id = (
+            select id
+            from project_stats
+            where project_id = ?
+            order by historical, created_at desc, id desc
+            limit 1
+        )
+        order by category",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectCategoryStatsById", e))?;
+        return Ok(categoryStats);
+    }
+
+    async fn getProjectCratesById(&self, id: i32) -> Result<Vec<ProjectCrate>, DatabaseError> {
+        let crates: Vec<ProjectCrate> = sqlx::query_as(
+            "
+        select
+        *
+        ,round(100.0 * documented_unsafe / nullif(documented_unsafe + undocumented_unsafe, 0), 2) as safety_coverage
+        from project_crates
+        where project_id = ?
+        order by path",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectCratesById", e))?;
+        return Ok(crates);
+    }
+
+    async fn getUnsafeSitesByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Vec<UnsafeSiteDTO>, DatabaseError> {
+        let rows = sqlx::query(
+            "
+        select
+        s.file_path
+        ,s.start_line
+        ,s.end_line
+        ,s.kind
+        ,s.item_path
+        ,s.category
+        ,s.documented
+        ,s.commit_sha
+        ,p.id
+        ,p.namespace
+        ,p.name
+        ,pr.url
+        from unsafe_sites s
+        inner join projects p on p.id = s.project_id
+        inner join providers pr on pr.id = p.provider_id
+        where s.project_id = ?
+        order by s.file_path, s.start_line, s.id",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getUnsafeSitesByProjectId", e))?;
+
+        let sites = rows
+            .iter()
+            .map(|row| {
+                let project =
+                    ProjectWithUrl::from_row(row).expect("ProjectWithUrl::from_row failed");
+                let file_path: String = row.get("file_path");
+                let start_line: i32 = row.get("start_line");
+                let end_line: i32 = row.get("end_line");
+                let commit_sha: Option<String> = row.get("commit_sha");
+                let permalink = UnsafeSiteDTO::permalink(
+                    &project,
+                    commit_sha.as_deref(),
+                    &file_path,
+                    start_line,
+                    end_line,
+                );
+                return UnsafeSiteDTO {
+                    file_path,
+                    start_line,
+                    end_line,
+                    kind: row.get("kind"),
+                    item_path: row.get("item_path"),
+                    category: row.get("category"),
+                    documented: row.get("documented"),
+                    commit_sha,
+                    permalink,
+                };
+            })
+            .collect();
+        return Ok(sites);
+    }
+
+    async fn getSafetyCoverageByProjectId(
+        &self,
+        id: i32,
+    ) -> Result<Vec<FileSafetyCoverage>, DatabaseError> {
+        let coverage: Vec<FileSafetyCoverage> = sqlx::query_as(
+            "
+        select
+        t.file_path
+        ,t.documented_unsafe
+        ,t.undocumented_unsafe
+        ,round(100.0 * t.documented_unsafe / nullif(t.documented_unsafe + t.undocumented_unsafe, 0), 2) as safety_coverage
+        from (
+            select
+            file_path
+            ,count(*) filter (where documented) as documented_unsafe
+            ,count(*) filter (where not documented) as undocumented_unsafe
+            from unsafe_sites
+            where project_id = ? and documented is not null
+            group by file_path
+        ) as t
+        order by t.file_path",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getSafetyCoverageByProjectId", e))?;
+        return Ok(coverage);
+    }
+}
+
+#[async_trait]
+impl JobStore for SqliteService {
+    async fn createJob(&self, kind: &str, projectIds: &[i32]) -> Result<i32, DatabaseError> {
+        let result: Result<i32, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let id = Self::insertJob(&mut transaction, kind, projectIds).await?;
+            transaction.commit().await?;
+            return Ok(id);
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("createJob", e));
+    }
+
+    async fn createBackfillJob(
+        &self,
+        kind: &str,
+        projectId: i32,
+        options: &BackfillOptions,
+    ) -> Result<i32, DatabaseError> {
+        let result: Result<i32, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let id = Self::insertJob(&mut transaction, kind, &[projectId]).await?;
+            sqlx::query("update jobs set backfill_every = ?, backfill_limit = ? where id = ?")
+                .bind(options.every.map(|v| v as i32))
+                .bind(options.limit.map(|v| v as i32))
+                .bind(id)
+                .execute(&mut transaction)
+                .await?;
+            transaction.commit().await?;
+            return Ok(id);
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("createBackfillJob", e));
+    }
+
+    /// The write comes first, so that the transaction takes the lock of the database
+    /// before it reads, and the due projects are queued once.
+    async fn scheduleDueProjects(&self, kind: &str) -> Result<Option<i32>, DatabaseError> {
+        let result: Result<Option<i32>, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let projectIds: Vec<(i32,)> = sqlx::query_as(
+                "
+            update projects
+            set last_run_at = datetime('now')
+            ,next_run_at = datetime('now', printf('+%d hours', update_interval_hours))
+            where next_run_at <= datetime('now')
+            returning id",
+            )
+            .fetch_all(&mut transaction)
+            .await?;
+            if projectIds.is_empty() {
+                return Ok(None);
+            }
+            let mut projectIds: Vec<i32> = projectIds.into_iter().map(|v| v.0).collect();
+            projectIds.sort_unstable();
+            let id = Self::insertJob(&mut transaction, kind, &projectIds).await?;
+            transaction.commit().await?;
+            return Ok(Some(id));
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("scheduleDueProjects", e));
+    }
+
+    /// The claim is a single update, which SQLite runs one at a time.
+    async fn claimJobProject(
+        &self,
+        staleAfter: Duration,
+    ) -> Result<Option<ClaimedJobProject>, DatabaseError> {
+        let result: Result<Option<ClaimedJobProject>, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let claimed: Option<(i32, i32)> = sqlx::query_as(
+                "
+            update job_projects
+            set status = 'running', attempts = attempts + 1, started_at = datetime('now'), finished_at = null
+            where id = (
+                select id
+                from job_projects
+                where (status = 'queued' and run_after <= datetime('now'))
+                or (status = 'running' and started_at < datetime('now', printf('-%f seconds', ?)))
+                order by run_after, id
+                limit 1
+            )
+            returning id, job_id",
+            )
+            .bind(staleAfter.as_secs_f64())
+            .fetch_optional(&mut transaction)
+            .await?;
+            let Some((jobProjectId, jobId)) = claimed else {
+                return Ok(None);
+            };
+            sqlx::query(
+                "
+            update jobs
+            set status = 'running', started_at = coalesce(started_at, datetime('now'))
+            where id = ? and status = 'queued'",
+            )
+            .bind(jobId)
+            .execute(&mut transaction)
+            .await?;
+            let claimed: ClaimedJobProject = sqlx::query_as(
+                "
+            select
+            jp.id as job_project_id
+            ,jp.job_id
+            ,j.kind
+            ,j.backfill_every
+            ,j.backfill_limit
+            ,jp.attempts
+            ,p.id
+            ,p.namespace
+            ,p.name
+            ,pr.url
+            from job_projects jp
+            inner join jobs j on j.id = jp.job_id
+            inner join projects p on p.id = jp.project_id
+            inner join providers pr on pr.id = p.provider_id
+            where jp.id = ?",
+            )
+            .bind(jobProjectId)
+            .fetch_one(&mut transaction)
+            .await?;
+            transaction.commit().await?;
+            return Ok(Some(claimed));
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("claimJobProject", e));
+    }
+
+    async fn finishJobProject(
+        &self,
+        jobProjectId: i32,
+        status: JobProjectStatus,
+        error: Option<&str>,
+    ) -> Result<bool, DatabaseError> {
+        let result: Result<bool, Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            let (jobId,): (i32,) = sqlx::query_as(
+                "
+            update job_projects
+            set status = ?, error = ?, finished_at = datetime('now')
+            where id = ?
+            returning job_id",
+            )
+            .bind(status.asStr())
+            .bind(error)
+            .bind(jobProjectId)
+            .fetch_one(&mut transaction)
+            .await?;
+            let completed = sqlx::query(
+                "
+            update jobs
+            set status = 'completed', finished_at = datetime('now')
+            where id = ?1 and status <> 'completed' and not exists (
+                select 1 from job_projects where job_id = ?1 and status in ('queued', 'running')
+            )",
+            )
+            .bind(jobId)
+            .execute(&mut transaction)
+            .await?;
+            transaction.commit().await?;
+            return Ok(completed.rows_affected() > 0);
+        }
+        .await;
+        return result.map_err(|e| DatabaseError::new("finishJobProject", e));
+    }
+
+    async fn retryJobProject(
+        &self,
+        jobProjectId: i32,
+        error: &str,
+        delay: Duration,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "
+        update job_projects
+        set status = 'queued', error = ?, run_after = datetime('now', printf('+%f seconds', ?))
+        where id = ?",
+        )
+        .bind(error)
+        .bind(delay.as_secs_f64())
+        .bind(jobProjectId)
+        .execute(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("retryJobProject", e))?;
+        return Ok(());
+    }
+
+    async fn getJobProgressById(&self, id: i32) -> Result<JobProgress, DatabaseError> {
+        let progress: JobProgress = sqlx::query_as(&format!(
+            "
+        select
+        {JOB_PROGRESS_COLUMNS}
+        from job_projects jp
+        where jp.job_id = ?"
+        ))
+        .bind(id)
+        .fetch_one(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getJobProgressById", e))?;
+        return Ok(progress);
+    }
+
+    async fn getJobById(&self, id: i32) -> Result<Option<JobDTO>, DatabaseError> {
+        let job: Option<Job> = sqlx::query_as(&format!(
+            "
+        select
+        j.id
+        ,j.kind
+        ,j.status
+        ,{JOB_PROGRESS_COLUMNS}
+        ,j.created_at
+        ,j.started_at
+        ,j.finished_at
+        from jobs j
+        left join job_projects jp on jp.job_id = j.id
+        where j.id = ?
+        group by j.id"
+        ))
+        .bind(id)
+        .fetch_optional(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getJobById", e))?;
+        let Some(job) = job else {
+            return Ok(None);
+        };
+
+        let projects: Vec<JobProject> = sqlx::query_as(
+            "
+        select
+        jp.project_id
+        ,p.namespace
+        ,p.name
+        ,jp.status
+        ,jp.attempts
+        ,jp.error
+        ,jp.run_after
+        ,jp.started_at
+        ,jp.finished_at
+        from job_projects jp
+        inner join projects p on p.id = jp.project_id
+        where jp.job_id = ?
+        order by jp.id",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getJobById", e))?;
+        return Ok(Some(JobDTO { job, projects }));
+    }
+
+    async fn notifyJobEvent(&self, event: &JobEvent) -> Result<(), DatabaseError> {
+        // Without a listener, the event has nobody to reach.
+        let _ = self.events.send(event.clone());
+        return Ok(());
+    }
+
+    async fn listenJobEvents(
+        &self,
+        events: &broadcast::Sender<JobEvent>,
+    ) -> Result<(), DatabaseError> {
+        let mut receiver = self.events.subscribe();
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let _ = events.send(event);
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+
+    async fn setProjectUpdateInterval(
+        &self,
+        namespace: &str,
+        name: &str,
+        updateIntervalHours: i32,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "
+        update projects
+        set update_interval_hours = ?3
+        ,next_run_at = coalesce(datetime(last_run_at, printf('+%d hours', ?3)), next_run_at)
+        where namespace = ?1 and name = ?2",
+        )
+        .bind(namespace)
+        .bind(name)
+        .bind(updateIntervalHours)
+        .execute(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("setProjectUpdateInterval", e))?;
+        return Ok(());
+    }
+
+    async fn getProjectsSchedule(
+        &self,
+        id: Option<i32>,
+    ) -> Result<Vec<ProjectSchedule>, DatabaseError> {
+        let schedule: Vec<ProjectSchedule> = sqlx::query_as(
+            "
+        select
+        p.id as project_id
+        ,p.namespace
+        ,p.name
+        ,p.update_interval_hours
+        ,p.last_run_at
+        ,p.next_run_at
+        ,(
+            select jp.status
+            from job_projects jp
+            where jp.project_id = p.id
+            order by jp.id desc
+            limit 1
+        ) as last_status
+        from projects p
+        where ?1 is null or p.id = ?1
+        order by p.next_run_at, p.id",
+        )
+        .bind(id)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getProjectsSchedule", e))?;
+        return Ok(schedule);
+    }
+}
+
+#[async_trait]
+impl TokenStore for SqliteService {
+    async fn createApiToken(
+        &self,
+        name: &str,
+        scope: ApiTokenScope,
+        tokenHash: &str,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query("insert into api_tokens (name, scope, token_hash) values (?, ?, ?)")
+            .bind(name)
+            .bind(scope.asStr())
+            .bind(tokenHash)
+            .execute(&self.connection)
+            .await
+            .map_err(|e| DatabaseError::new("createApiToken", e))?;
+        return Ok(());
+    }
+
+    async fn getApiTokens(&self) -> Result<Vec<ApiToken>, DatabaseError> {
+        let tokens: Vec<ApiToken> = sqlx::query_as(
+            "
+        select id, name, scope, created_at, last_used_at, revoked_at
+        from api_tokens
+        order by id",
+        )
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("getApiTokens", e))?;
+        return Ok(tokens);
+    }
+
+    async fn revokeApiToken(&self, name: &str) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(
+            "update api_tokens set revoked_at = datetime('now') where name = ? and revoked_at is null",
+        )
+        .bind(name)
+        .execute(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("revokeApiToken", e))?;
+        return Ok(result.rows_affected() > 0);
+    }
+
+    async fn useApiToken(&self, tokenHash: &str) -> Result<Option<ApiTokenScope>, DatabaseError> {
+        let mut scopes: Vec<(String,)> = sqlx::query_as(
+            "
+        update api_tokens
+        set last_used_at = datetime('now')
+        where token_hash = ? and revoked_at is null
+        returning scope",
+        )
+        .bind(tokenHash)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(|e| DatabaseError::new("useApiToken", e))?;
+        return Ok(scopes.pop().and_then(|v| v.0.parse().ok()));
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
-use std::time::{SystemTime, UNIX_EPOCH};
 use chrono::{DateTime, Utc};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn getTimestamp() -> u64 {
     let now = SystemTime::now();
@@ -14,4 +14,4 @@ pub fn getDate() -> String {
     let now: DateTime<Utc> = Utc::now();
     let date = now.format("%Y-%m-%dT%H:%M:%S");
     return format!("{date}");
-}
\ No newline at end of file
+}
@@ -0,0 +1,83 @@
+//! The databases of the tests. `TEST_BACKENDS` lists the backends to test, e.g.
+//! `postgres,sqlite`. Without it, SQLite is tested, and Postgres too if `DB_HOST` is set.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool};
+use sqlx::Executor;
+use sqlx::{Connection, PgConnection, PgPool};
+use std::str::FromStr;
+use unsaferust::models::configuration::{DatabaseBackend, DatabaseSettings};
+use uuid::Uuid;
+
+pub fn test_backends() -> Vec<DatabaseBackend> {
+    if let Ok(backends) = std::env::var("TEST_BACKENDS") {
+        return backends
+            .split(',')
+            .map(|v| v.trim().parse().expect("Failed to parse TEST_BACKENDS"))
+            .collect();
+    }
+    let mut backends = vec![DatabaseBackend::Sqlite];
+    if std::env::var("DB_HOST").is_ok() {
+        backends.push(DatabaseBackend::Postgres);
+    }
+    return backends;
+}
+
+/// Creates and migrates a new database of the backend, and returns its url.
+pub async fn create_database(backend: DatabaseBackend) -> String {
+    return match backend {
+        DatabaseBackend::Postgres => create_postgres_database().await,
+        DatabaseBackend::Sqlite => create_sqlite_database().await,
+    };
+}
+
+async fn create_postgres_database() -> String {
+    let db_settings = DatabaseSettings::new(
+        std::env::var("DB_USER").expect("env::var DB_USER failed"),
+        std::env::var("DB_PASSWORD").expect("env::var DB_PASSWORD failed"),
+        std::env::var("DB_PORT")
+            .unwrap_or_else(|_| "10".to_owned())
+            .parse()
+            .expect("Failed to parse DB_PORT"),
+        std::env::var("DB_HOST").expect("env::var DB_HOST failed"),
+        Uuid::new_v4().to_string(),
+        std::env::var("DB_MAX_CONNECTIONS")
+            .unwrap_or_else(|_| "10".to_owned())
+            .parse()
+            .expect("Failed to parse DB_MAX_CONNECTIONS"),
+    );
+    let mut connection = PgConnection::connect(&db_settings.get_connection_string_without_db())
+        .await
+        .expect("Failed to connect to Postgres");
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}";"#, db_settings.database_name).as_str())
+        .await
+        .expect("Failed to create database.");
+    let connection_pool = PgPool::connect(&db_settings.get_connection_string_with_db())
+        .await
+        .expect("Failed to connect to Postgres.");
+    sqlx::migrate!("./migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool.close().await;
+    return db_settings.get_connection_string_with_db();
+}
+
+/// The database is a file, in WAL mode so that the reads of the tests do not block the server.
+async fn create_sqlite_database() -> String {
+    let path = std::env::temp_dir().join(format!("unsaferust_{}.db", Uuid::new_v4()));
+    let url = format!("sqlite://{}", path.display());
+    let options = SqliteConnectOptions::from_str(&url)
+        .expect("Failed to parse the SQLite url")
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal);
+    let connection_pool = SqlitePool::connect_with(options)
+        .await
+        .expect("Failed to open SQLite");
+    sqlx::migrate!("./migrations-sqlite")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool.close().await;
+    return url;
+}
@@ -1,76 +1,181 @@
-#![allow(non_snake_case)]
+#![allow(non_snake_case, clippy::needless_return)]
+
+mod common;
 
 use hyper::StatusCode;
 use serde_json::Value;
-use sqlx::Executor;
-use sqlx::{Connection, PgConnection, PgPool};
+use sqlx::any::{AnyKind, AnyPool};
+use sqlx::{PgPool, SqlitePool};
 use std::net::TcpListener;
-use unsaferust::models::configuration::DatabaseSettings;
-use unsaferust::models::project::{Project, ProjectStats, ProjectStatsWithMeta};
+use std::sync::Arc;
+use std::time::Duration;
+use unsaferust::analysis::{
+    category::Category,
+    lines::LineCounts,
+    lints::UnsafeCodeLint,
+    site::{ProjectUnsafeSite, UnsafeKind, UnsafeSite},
+    CategoryStats, CrateAnalysis, ProjectAnalysis, UnsafeUsage,
+};
+use unsaferust::auth::{generateToken, hashToken};
+use unsaferust::error::Problem;
+use unsaferust::models::configuration::DatabaseBackend;
+use unsaferust::models::job::JobDTO;
+use unsaferust::models::project::{
+    FileSafetyCoverage, Project, ProjectCategoryStats, ProjectCrate, ProjectSchedule, ProjectStats,
+    ProjectStatsHistory, ProjectStatsWithMeta, UnsafeSiteDTO,
+};
 use unsaferust::models::provider::Provider;
-use unsaferust::services::postgres::PostgresService;
+use unsaferust::models::token::ApiTokenScope;
+use unsaferust::services::cache::{Cache, InMemoryCache};
+use unsaferust::services::git::GitService;
+use unsaferust::services::jobs::JobOptions;
+use unsaferust::services::memory::InMemoryRepository;
+use unsaferust::services::postgres::{DatabaseError, PostgresService};
 use unsaferust::services::redis::RedisService;
+use unsaferust::services::repository::{JobStore, Repository, TokenStore};
+use unsaferust::services::sqlite::SqliteService;
 use uuid::Uuid;
 
 lazy_static::lazy_static! { static ref CLIENT: reqwest::Client = reqwest::Client::new(); }
 
-async fn spawn_app() -> (String, PgPool) {
+async fn spawn_app(backend: DatabaseBackend) -> (String, AnyPool) {
+    let jobOptions = JobOptions {
+        workers: 2,
+        maxAttempts: 2,
+        retryDelay: std::time::Duration::from_millis(100),
+        maxRetryDelay: std::time::Duration::from_secs(1),
+        pollInterval: std::time::Duration::from_millis(100),
+        staleAfter: std::time::Duration::from_secs(3600),
+        scheduleInterval: None,
+        analysisTimeout: Some(std::time::Duration::from_secs(60)),
+        maxRepositoryBytes: None,
+        maxFiles: None,
+    };
+    return spawn_app_with(backend, jobOptions).await;
+}
+
+async fn spawn_app_with(backend: DatabaseBackend, jobOptions: JobOptions) -> (String, AnyPool) {
     let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
     // We retrieve the port assigned to us by the OS
     let port = listener.local_addr().unwrap().port();
-    let db_user = std::env::var("DB_USER").expect("env::var DB_USER failed");
-    let db_password = std::env::var("DB_PASSWORD").expect("env::var DB_PASSWORD failed");
-    let db_host = std::env::var("DB_HOST").expect("env::var DB_HOST failed");
-    let db_port = std::env::var("DB_PORT")
-        .unwrap_or_else(|_| "10".to_owned())
-        .parse()
-        .expect("Failed to parse DB_PORT");
-    let db_max_connections = std::env::var("DB_MAX_CONNECTIONS")
-        .unwrap_or_else(|_| "10".to_owned())
-        .parse()
-        .expect("Failed to parse DB_MAX_CONNECTIONS");
-    let db_name = Uuid::new_v4().to_string();
-
-    let db_settings = DatabaseSettings::new(
-        db_user,
-        db_password,
-        db_port,
-        db_host,
-        db_name,
-        db_max_connections,
-    );
-    let connection_pool = configure_database(&db_settings).await;
+    let url = common::create_database(backend).await;
+    let connection_pool = AnyPool::connect(&url)
+        .await
+        .expect("Failed to connect to the database");
 
-    let databaseService = PostgresService::new(Some(connection_pool.clone())).await;
-    let redisService = RedisService::new().await;
-    let server =
-        unsaferust::run(listener, redisService, databaseService).expect("Failed to bind address");
-    let _ = tokio::spawn(server);
+    let databaseService = database_service(&connection_pool).await;
+    let cache = Arc::new(
+        RedisService::new(Duration::from_secs(60))
+            .await
+            .expect("Failed to connect to Redis"),
+    );
+    let gitService = GitService::new(Some(git_workspace_dir()));
+    let server = unsaferust::run(
+        listener,
+        cache,
+        databaseService.clone(),
+        databaseService.clone(),
+        databaseService,
+        gitService,
+        jobOptions,
+    )
+    .expect("Failed to bind address");
+    tokio::spawn(server);
     // We return the application address to the caller!
     return (format!("http://127.0.0.1:{}", port), connection_pool);
 }
 
-pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
-    // Create database
-    let mut connection = PgConnection::connect(&config.get_connection_string_without_db())
-        .await
-        .expect("Failed to connect to Postgres");
-    connection
-        .execute(format!(r#"CREATE DATABASE "{}";"#, config.database_name).as_str())
-        .await
-        .expect("Failed to create database.");
-    // Migrate database
-    let connection_pool = PgPool::connect(&config.get_connection_string_with_db())
-        .await
-        .expect("Failed to connect to Postgres.");
-    sqlx::migrate!("./migrations")
-        .run(&connection_pool)
+/// Every store of a backend, which its service implements.
+trait Database: Repository + JobStore + TokenStore {}
+
+impl<T: Repository + JobStore + TokenStore> Database for T {}
+
+/// The service of the backend of `db`, on a pool of its own.
+async fn database_service(db: &AnyPool) -> Arc<dyn Database> {
+    let options = db.connect_options();
+    if let Some(options) = options.as_postgres() {
+        let connection_pool = PgPool::connect_with(options.clone())
+            .await
+            .expect("Failed to connect to Postgres.");
+        return Arc::new(PostgresService::new(Some(connection_pool)).await);
+    }
+    let options = options.as_sqlite().expect("Unknown database backend");
+    let connection_pool = SqlitePool::connect_with(options.clone())
         .await
-        .expect("Failed to migrate the database");
-    return connection_pool;
+        .expect("Failed to open SQLite");
+    return Arc::new(SqliteService::new(Some(connection_pool)).await);
+}
+
+/// The SQL of the time `seconds` from now, in the dialect of `db`.
+fn seconds_from_now(db: &AnyPool, seconds: i64) -> String {
+    return match db.any_kind() {
+        AnyKind::Postgres => format!("now() + make_interval(secs => {seconds})"),
+        AnyKind::Sqlite => format!("datetime('now', '{seconds:+} seconds')"),
+    };
+}
+
+/// Runs each test against every backend of `TEST_BACKENDS`, e.g. as `sqlite::test_health_check`.
+macro_rules! backend_tests {
+    ($($test:ident),* $(,)?) => {
+        backend_tests!(@backend postgres, Postgres, $($test),*);
+        backend_tests!(@backend sqlite, Sqlite, $($test),*);
+    };
+    (@backend $module:ident, $backend:ident, $($test:ident),*) => {
+        mod $module {
+            use unsaferust::models::configuration::DatabaseBackend;
+
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    if super::common::test_backends().contains(&DatabaseBackend::$backend) {
+                        super::$test(DatabaseBackend::$backend).await;
+                    }
+                }
+            )*
+        }
+    };
+}
+
+backend_tests!(
+    test_admin_authentication,
+    test_jobs_update_projects_stats,
+    test_jobs_events,
+    test_jobs_limits,
+    test_projects_schedule,
+    test_projects_get_all,
+    test_projects_create_with_sql_metacharacters,
+    test_projects_get_by_id,
+    test_projects_crud,
+    test_projects_get_unsafe_sites,
+    test_projects_get_safety_coverage,
+    test_project_stats_unsafe_code_lint,
+    test_project_stats_commit,
+    test_project_stats_backfill,
+    test_projects_get_crates,
+    test_health_check,
+    test_cors_preflight,
+    test_project_stats_pagination,
+    testProjectStatsPaginationWithName,
+    test_project_stats_sorting_and_filters,
+    test_project_stats_cache_invalidation,
+    test_project_stats_get_all,
+    test_project_stats_get_by_id,
+    test_project_stats_breakdown,
+    test_project_stats_categories,
+    test_error_responses,
+    test_non_existing_routes,
+    test_providers_get_all,
+    test_providers_get_by_id,
+    test_providers_crud,
+    test_v2_get_by_id,
+);
+
+/// Where the test servers keep the repositories of the projects.
+fn git_workspace_dir() -> std::path::PathBuf {
+    return std::env::temp_dir().join("unsaferust_test_projects");
 }
 
-async fn create_provider(db: &PgPool) -> bool {
+async fn create_provider(db: &AnyPool) -> bool {
     let result = sqlx::query("insert into providers (url) values ('https://github.com')")
         .execute(db)
         .await
@@ -78,7 +183,7 @@ async fn create_provider(db: &PgPool) -> bool {
     return result.rows_affected() == 2;
 }
 
-async fn create_project(db: &PgPool) -> bool {
+async fn create_project(db: &AnyPool) -> bool {
     let result = sqlx::query(
         "
         insert into projects (provider_id, name, namespace)
@@ -93,7 +198,7 @@ async fn create_project(db: &PgPool) -> bool {
     return result.rows_affected() == 2;
 }
 
-async fn create_project_stats(db: &PgPool) -> bool {
+async fn create_project_stats(db: &AnyPool) -> bool {
     let result = sqlx::query(
         "
         insert into project_stats (project_id, code_lines, unsafe_lines, created_at)
@@ -110,6 +215,116 @@ async fn create_project_stats(db: &PgPool) -> bool {
     return result.rows_affected() == 4;
 }
 
+/// Stores a new token and returns its secret.
+async fn create_api_token(db: &AnyPool, scope: ApiTokenScope) -> String {
+    let token = generateToken();
+    database_service(db)
+        .await
+        .createApiToken(
+            &format!("test_{}", Uuid::new_v4()),
+            scope,
+            &hashToken(&token),
+        )
+        .await
+        .expect("Failed to create the token");
+    return token;
+}
+
+/*********/
+/* admin */
+/*********/
+async fn test_admin_authentication(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let databaseService = database_service(&db).await;
+    let adminToken = create_api_token(&db, ApiTokenScope::Admin).await;
+    let updateToken = create_api_token(&db, ApiTokenScope::Update).await;
+    let flushUrl = format!("{}/api/v1/admin/redis/flush", &address);
+
+    // Positive assertion
+    let response = CLIENT
+        .post(&flushUrl)
+        .bearer_auth(&adminToken)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    let tokens = databaseService.getApiTokens().await.unwrap();
+    assert!(tokens.iter().any(|v| v.last_used_at.is_some()));
+
+    // An update token is enough for the updates.
+    let response = CLIENT
+        .post(format!("{}/api/v1/admin/project-stats/update", &address))
+        .bearer_auth(&updateToken)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    // Negative assertion
+    let response = CLIENT
+        .post(&flushUrl)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+    let response = CLIENT
+        .post(&flushUrl)
+        .bearer_auth(generateToken())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = CLIENT
+        .post(&flushUrl)
+        .header("Authorization", format!("Basic {adminToken}"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The scope of an update token is too narrow.
+    let response = CLIENT
+        .post(&flushUrl)
+        .bearer_auth(&updateToken)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // A revoked token is rejected.
+    let name = &tokens.iter().find(|v| v.scope == "admin").unwrap().name;
+    assert!(databaseService.revokeApiToken(name).await.unwrap());
+    assert!(!databaseService.revokeApiToken(name).await.unwrap());
+    let response = CLIENT
+        .post(&flushUrl)
+        .bearer_auth(&adminToken)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The mutating endpoints are not reachable with GET anymore.
+    for path in [
+        "api/redis/flush",
+        "api/v1/project-stats/update",
+        "api/v1/projects/import",
+        "api/v1/admin/redis/flush",
+    ] {
+        let response = CLIENT
+            .get(format!("{}/{}", &address, path))
+            .bearer_auth(&updateToken)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert!(response.status().is_client_error());
+    }
+}
+
 /*****************/
 /* project-stats */
 /*****************/
@@ -123,7 +338,7 @@ async fn create_project_stats(db: &PgPool) -> bool {
 //
 //     // Positive assertion
 //     let response = CLIENT
-//         .get(format!("{}/api/v1/project-stats/update", &address))
+//         .post(format!("{}/api/v1/admin/project-stats/update", &address))
 //         .send()
 //         .await
 //         .expect("Failed to execute request.");
@@ -133,195 +348,1339 @@ async fn create_project_stats(db: &PgPool) -> bool {
 //     assert!(response.status().is_success());
 // }
 
-/************/
-/* projects */
-/************/
-#[tokio::test]
-async fn test_projects_get_all() {
-    let (address, db) = spawn_app().await;
+/********/
+/* jobs */
+/********/
+/// Polls a job until all its projects are done.
+async fn wait_for_job(address: &str, id: i32) -> JobDTO {
+    for _ in 0..100 {
+        let response = CLIENT
+            .get(format!("{address}/api/v1/jobs/{id}"))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert!(response.status().is_success());
+        let job: JobDTO = response.json().await.unwrap();
+        if job.job.status == "completed" {
+            return job;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("Job {id} did not complete");
+}
 
-    // Create an entry with sqlx since we dont have an endpoint.
-    let _result = create_provider(&db).await;
-    let _result = create_project(&db).await;
+async fn test_jobs_update_projects_stats(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let token = create_api_token(&db, ApiTokenScope::Update).await;
+    let name = format!("job_{}", Uuid::new_v4());
+    let remote = std::env::temp_dir().join(format!("{name}_remote"));
+    create_tagged_repository(&remote.join("test").join(format!("{name}.git")));
+    sqlx::query("insert into providers (url) values ($1)")
+        .bind(format!("file://{}", remote.display()))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+    sqlx::query(
+        "insert into projects (provider_id, namespace, name) values (1, 'test', $1), (1, 'test', $2)",
+    )
+    .bind(&name)
+    .bind(format!("{name}_missing"))
+    .execute(&db)
+    .await
+    .expect("Failed to create entry");
 
     // Positive assertion
     let response = CLIENT
-        .get(format!("{}/api/v1/projects", &address))
+        .post(format!("{}/api/v1/admin/project-stats/update", &address))
+        .bearer_auth(&token)
         .send()
         .await
         .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let job: JobDTO = response.json().await.unwrap();
+    assert_eq!(job.job.progress.total, 2);
+
+    let job = wait_for_job(&address, job.job.id).await;
+    assert_eq!(
+        (job.job.progress.succeeded, job.job.progress.failed),
+        (1, 1)
+    );
+    assert!(job.job.finished_at.is_some());
+    assert_eq!(job.projects[0].status, "succeeded");
+    assert_eq!(job.projects[1].status, "failed");
+    // Every attempt failed, and the last error is kept.
+    assert_eq!(job.projects[1].attempts, 2);
+    assert!(job.projects[1]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("Failed to clone"));
 
-    // Assert
-    assert!(response.status().is_success());
-    let projects: Vec<Project> = response.json().await.unwrap();
-    assert_eq!(projects.len(), 2);
-    assert_eq!(projects[1].namespace, "actix-web");
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+    assert_eq!(project_stats.len(), 1);
+    assert_eq!(project_stats[0].unsafe_lines, 3);
+
+    // HEAD has not moved, so the second update skips the project.
+    let response = CLIENT
+        .post(format!("{}/api/v1/admin/project-stats/update", &address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let job: JobDTO = response.json().await.unwrap();
+    let job = wait_for_job(&address, job.job.id).await;
+    assert_eq!((job.job.progress.skipped, job.job.progress.failed), (1, 1));
 
     // Negative assertion
     let response = CLIENT
-        .get(format!("{}/api/v1/projects/3", &address))
+        .get(format!("{}/api/v1/jobs/100", &address))
         .send()
         .await
         .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 
-    // Assert
-    assert!(response.status().is_success());
-    let projects: Vec<Project> = response.json().await.unwrap();
-    assert_eq!(projects.len(), 0);
+    let _ = std::fs::remove_dir_all(&remote);
+    let _ = std::fs::remove_dir_all(git_workspace_dir().join("test").join(&name));
 }
 
-#[tokio::test]
-async fn test_projects_get_by_id() {
-    let (address, db) = spawn_app().await;
+/// Reads Server-Sent Events until the `completed` one, and returns their names and data.
+async fn read_job_events(mut response: reqwest::Response) -> Vec<(String, Value)> {
+    let mut body = String::new();
+    let read = async {
+        while !body.contains("event:completed") {
+            match response.chunk().await.unwrap() {
+                Some(chunk) => body.push_str(&String::from_utf8_lossy(&chunk)),
+                None => break,
+            }
+        }
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(30), read)
+        .await
+        .expect("The job did not complete");
+
+    let mut events = Vec::new();
+    for message in body.split("\n\n") {
+        let field = |name: &str| {
+            return message
+                .lines()
+                .find_map(|v| v.strip_prefix(&format!("{name}:")))
+                .map(|v| v.strip_prefix(' ').unwrap_or(v).to_owned());
+        };
+        if let (Some(event), Some(data)) = (field("event"), field("data")) {
+            events.push((event, serde_json::from_str(&data).unwrap()));
+        }
+    }
+    return events;
+}
 
-    // Create an entry with sqlx since we dont have an endpoint.
-    let _result = create_provider(&db).await;
-    let _result = create_project(&db).await;
+async fn test_jobs_events(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let name = format!("events_{}", Uuid::new_v4());
+    let remote = std::env::temp_dir().join(format!("{name}_remote"));
+    create_tagged_repository(&remote.join("test").join(format!("{name}.git")));
+    sqlx::query("insert into providers (url) values ($1)")
+        .bind(format!("file://{}", remote.display()))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+    sqlx::query("insert into projects (provider_id, namespace, name) values (1, 'test', $1)")
+        .bind(&name)
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+    // The project is due in a second, so the stream is open before it starts.
+    let (job_id,): (i32,) =
+        sqlx::query_as("insert into jobs (kind) values ('update_projects_stats') returning id")
+            .fetch_one(&db)
+            .await
+            .expect("Failed to create entry");
+    sqlx::query(&format!(
+        "insert into job_projects (job_id, project_id, run_after) values ($1, 1, {})",
+        seconds_from_now(&db, 1)
+    ))
+    .bind(job_id)
+    .execute(&db)
+    .await
+    .expect("Failed to create entry");
 
     // Positive assertion
     let response = CLIENT
-        .get(format!("{}/api/v1/projects/2", &address))
+        .get(format!("{}/api/v1/jobs/{}/events", &address, job_id))
         .send()
         .await
         .expect("Failed to execute request.");
-
-    // Assert
     assert!(response.status().is_success());
-    let projects: Vec<Project> = response.json().await.unwrap();
-    assert_eq!(projects[0].namespace, "actix-web");
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+    let events = read_job_events(response).await;
+    let names: Vec<&str> = events.iter().map(|v| v.0.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["progress", "cloning", "analyzing", "stored", "completed"]
+    );
+    assert_eq!(events[0].1["total"], 1);
+    assert_eq!(events[0].1["queued"], 1);
+    assert_eq!(events[1].1["name"], name.as_str());
+    assert_eq!(events[3].1["status"], "succeeded");
+    assert_eq!(events[3].1["succeeded"], 1);
 
-    // Negative assertion
+    // A completed job only has its final progress.
     let response = CLIENT
-        .get(format!("{}/api/v1/projects/3", &address))
+        .get(format!("{}/api/v1/jobs/{}/events", &address, job_id))
         .send()
         .await
         .expect("Failed to execute request.");
+    let events = read_job_events(response).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, "completed");
+    assert_eq!(events[0].1["succeeded"], 1);
 
-    // Assert
-    assert!(response.status().is_success());
-    let projects: Vec<Project> = response.json().await.unwrap();
-    assert_eq!(projects.len(), 0);
-}
-
-#[tokio::test]
-async fn test_health_check() {
-    let (address, _db) = spawn_app().await;
+    // Negative assertion
     let response = CLIENT
-        .get(format!("{}/api/health_check", &address))
+        .get(format!("{}/api/v1/jobs/100/events", &address))
         .send()
         .await
         .expect("Failed to execute request.");
-    assert!(response.status().is_success());
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let _ = std::fs::remove_dir_all(&remote);
+    let _ = std::fs::remove_dir_all(git_workspace_dir().join("test").join(&name));
 }
 
-#[tokio::test]
-async fn test_project_stats_pagination() {
-    let (address, db) = spawn_app().await;
+async fn test_jobs_limits(backend: DatabaseBackend) {
+    let (address, db) = spawn_app_with(
+        backend,
+        JobOptions {
+            workers: 1,
+            maxAttempts: 3,
+            retryDelay: std::time::Duration::from_millis(100),
+            maxRetryDelay: std::time::Duration::from_secs(1),
+            pollInterval: std::time::Duration::from_millis(100),
+            staleAfter: std::time::Duration::from_secs(3600),
+            scheduleInterval: None,
+            analysisTimeout: None,
+            maxRepositoryBytes: Some(100),
+            maxFiles: None,
+        },
+    )
+    .await;
 
     // Setup
-    let _result = create_provider(&db).await;
-    for i in 1..10 {
-        let _ = sqlx::query(&format!(
-            "
-            insert into projects (provider_id, name, namespace)
-            values (1, 'name_{i}', 'namespace_{i}')"
-        ))
+    let token = create_api_token(&db, ApiTokenScope::Update).await;
+    let name = format!("limits_{}", Uuid::new_v4());
+    let remote = std::env::temp_dir().join(format!("{name}_remote"));
+    create_tagged_repository(&remote.join("test").join(format!("{name}.git")));
+    sqlx::query("insert into providers (url) values ($1)")
+        .bind(format!("file://{}", remote.display()))
         .execute(&db)
         .await
         .expect("Failed to create entry");
-
-        let _ = sqlx::query(&format!(
-            "
-            insert into project_stats (project_id, code_lines, unsafe_lines)
-            values ({i}, '{i}', '{i}')"
-        ))
+    sqlx::query("insert into projects (provider_id, namespace, name) values (1, 'test', $1)")
+        .bind(&name)
         .execute(&db)
         .await
         .expect("Failed to create entry");
-    }
 
-    // Start requesting
-    // 1. Get all items.
-    redis_flush(&address).await;
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats?page=1&limit=25", &address))
+        .post(format!("{}/api/v1/admin/project-stats/update", &address))
+        .bearer_auth(&token)
         .send()
         .await
         .expect("Failed to execute request.");
+    let job: JobDTO = response.json().await.unwrap();
+    let job = wait_for_job(&address, job.job.id).await;
 
     // Assert
-    assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 9);
+    assert_eq!(job.job.progress.failed, 1);
+    assert_eq!(job.projects[0].status, "too_large");
+    // A limit is not retried.
+    assert_eq!(job.projects[0].attempts, 1);
+    assert!(job.projects[0]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("larger than 100 bytes"));
+    let (stats,): (i64,) = sqlx::query_as("select count(*) from project_stats")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(stats, 0);
 
-    // 1. Get 3 items per page (page 1).
+    let _ = std::fs::remove_dir_all(&remote);
+}
+
+async fn test_projects_schedule(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let remote = std::env::temp_dir().join(format!("schedule_{}_remote", Uuid::new_v4()));
+    sqlx::query("insert into providers (url) values ($1)")
+        .bind(format!("file://{}", remote.display()))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+    sqlx::query(&format!(
+        "
+        insert into projects (provider_id, namespace, name, update_interval_hours, next_run_at)
+        values (1, 'test', 'daily', 24, {}), (1, 'test', 'later', 168, {})",
+        seconds_from_now(&db, 0),
+        seconds_from_now(&db, 24 * 3600)
+    ))
+    .execute(&db)
+    .await
+    .expect("Failed to create entry");
+
+    // Two instances schedule at the same time, and only one queues the due project.
+    let databaseService = database_service(&db).await;
+    let (first, second) = tokio::join!(
+        databaseService.scheduleDueProjects("scheduled_update"),
+        databaseService.scheduleDueProjects("scheduled_update"),
+    );
+    let jobs: Vec<i32> = [first.unwrap(), second.unwrap()]
+        .into_iter()
+        .flatten()
+        .collect();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(
+        databaseService
+            .scheduleDueProjects("scheduled_update")
+            .await
+            .unwrap(),
+        None
+    );
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats?page=1&limit=3", &address))
+        .get(format!("{}/api/v1/jobs/{}", &address, jobs[0]))
         .send()
         .await
         .expect("Failed to execute request.");
+    let job: JobDTO = response.json().await.unwrap();
+    assert_eq!(job.job.kind, "scheduled_update");
+    let names: Vec<&str> = job.projects.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(names, vec!["daily"]);
 
-    // Assert
+    // Positive assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/schedule", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
     assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 3);
-    assert_eq!(response.projectStats[0].name, "name_1");
-    assert_eq!(response.projectStats[1].name, "name_2");
-    assert_eq!(response.projectStats[2].name, "name_3");
+    let schedule: Vec<ProjectSchedule> = response.json().await.unwrap();
+    assert_eq!(schedule.len(), 2);
+    let daily = schedule.iter().find(|v| v.name == "daily").unwrap();
+    assert!(daily.last_run_at.is_some());
+    let (next_run_at,): (bool,) = sqlx::query_as(&format!(
+        "select next_run_at between {} and {} from projects where name = 'daily'",
+        seconds_from_now(&db, 23 * 3600),
+        seconds_from_now(&db, 25 * 3600)
+    ))
+    .fetch_one(&db)
+    .await
+    .unwrap();
+    assert!(next_run_at);
 
-    // 1. Get 3 items per page (page 3).
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats?page=3&limit=3", &address))
+        .get(format!("{}/api/v1/projects/2/schedule", &address))
         .send()
         .await
         .expect("Failed to execute request.");
-
-    // Assert
     assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 3);
-    assert_eq!(response.projectStats[0].name, "name_7");
-    assert_eq!(response.projectStats[1].name, "name_8");
-    assert_eq!(response.projectStats[2].name, "name_9");
+    let later: ProjectSchedule = response.json().await.unwrap();
+    assert_eq!(later.update_interval_hours, 168);
+    assert_eq!(later.last_run_at, None);
+    assert_eq!(later.last_status, None);
 
-    // 1. Get 4 items per page (page 2).
+    // Negative assertion
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats?page=2&limit=4", &address))
+        .get(format!("{}/api/v1/projects/100/schedule", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/************/
+/* projects */
+/************/
+async fn test_projects_get_all(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Create an entry with sqlx since we dont have an endpoint.
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+
+    // Positive assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects", &address))
         .send()
         .await
         .expect("Failed to execute request.");
 
     // Assert
     assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 4);
-    assert_eq!(response.projectStats[0].name, "name_5");
-    assert_eq!(response.projectStats[1].name, "name_6");
-    assert_eq!(response.projectStats[2].name, "name_7");
-    assert_eq!(response.projectStats[3].name, "name_8");
+    let projects: Vec<Project> = response.json().await.unwrap();
+    assert_eq!(projects.len(), 2);
+    assert_eq!(projects[1].namespace, "actix-web");
 
-    // 1. Get 8 items per page (page 2).
+    // Negative assertion
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats?page=2&limit=8", &address))
+        .get(format!("{}/api/v1/projects/3", &address))
         .send()
         .await
         .expect("Failed to execute request.");
 
     // Assert
     assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 1);
-    assert_eq!(response.projectStats[0].name, "name_9");
+    let projects: Vec<Project> = response.json().await.unwrap();
+    assert_eq!(projects.len(), 0);
 }
 
-#[tokio::test]
-async fn testProjectStatsPaginationWithName() {
-    let (address, db) = spawn_app().await;
+async fn test_projects_create_with_sql_metacharacters(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+    let databaseService = database_service(&db).await;
+
+    // Setup
+    let providerUrl = "https://git'hub.com";
+    let names = [
+        "o'brien",
+        "x'); drop table projects; --",
+        "$do$ begin end $do$",
+        "100%_done",
+        "back\\slash\"quote",
+    ];
+    databaseService.createProvider(providerUrl).await.unwrap();
+    databaseService.createProvider(providerUrl).await.unwrap();
+    for name in names {
+        databaseService
+            .createProject(providerUrl, "name'space", name)
+            .await
+            .unwrap();
+        // A second import of the same project is ignored.
+        databaseService
+            .createProject(providerUrl, "name'space", name)
+            .await
+            .unwrap();
+    }
+    sqlx::query("insert into project_stats (project_id, code_lines, unsafe_lines) select id, 10, 1 from projects")
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+
+    // Positive assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let projects: Vec<Project> = response.json().await.unwrap();
+    let mut stored: Vec<&str> = projects.iter().map(|v| v.name.as_str()).collect();
+    let mut expected = names.to_vec();
+    stored.sort();
+    expected.sort();
+    assert_eq!(stored, expected);
+    assert!(projects.iter().all(|v| v.namespace == "name'space"));
+
+    // The name filter matches the name literally, without wildcards.
+    redis_flush(&address, &db).await;
+    for (filter, expected) in [
+        ("'", vec!["o'brien", "x'); drop table projects; --"]),
+        ("%", vec!["100%_done"]),
+        ("_", vec!["100%_done"]),
+        ("$do$", vec!["$do$ begin end $do$"]),
+        ("\\", vec!["back\\slash\"quote"]),
+        ("drop table", vec!["x'); drop table projects; --"]),
+    ] {
+        let response = CLIENT
+            .get(format!("{}/api/v1/project-stats", &address))
+            .query(&[("name", filter)])
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert!(response.status().is_success(), "{filter}");
+        let result: ProjectStatsWithMeta = response.json().await.unwrap();
+        let mut names: Vec<String> = result.projectStats.into_iter().map(|v| v.name).collect();
+        names.sort();
+        assert_eq!(names, expected, "{filter}");
+    }
+
+    // Negative assertion
+    let result = databaseService
+        .createProject("https://unknown.org", "namespace", "unknown")
+        .await;
+    assert!(matches!(result, Err(DatabaseError::Other(_))));
+}
+
+async fn test_projects_get_by_id(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Create an entry with sqlx since we dont have an endpoint.
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+
+    // Positive assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/2", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let projects: Vec<Project> = response.json().await.unwrap();
+    assert_eq!(projects[0].namespace, "actix-web");
+
+    // Negative assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/3", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let projects: Vec<Project> = response.json().await.unwrap();
+    assert_eq!(projects.len(), 0);
+}
+
+async fn test_projects_crud(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let token = create_api_token(&db, ApiTokenScope::Admin).await;
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let _result = create_project_stats(&db).await;
+
+    // Positive assertion
+    let response = CLIENT
+        .post(format!("{}/api/v1/projects", &address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"provider_id": 1, "namespace": "tokio-rs", "name": "tokio"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let project: Value = response.json().await.unwrap();
+    assert_eq!(project["id"], 3);
+    assert_eq!(project["name"], "tokio");
+
+    let response = CLIENT
+        .put(format!("{}/api/v1/projects/3", &address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"provider_id": 1, "namespace": "tokio-rs", "name": "mio"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = CLIENT
+        .patch(format!("{}/api/v1/projects/3", &address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"namespace": "tokio"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    let project: Project = response.json().await.unwrap();
+    assert_eq!(
+        (
+            project.provider_id,
+            project.namespace.as_str(),
+            project.name.as_str()
+        ),
+        (1, "tokio", "mio")
+    );
+
+    // The stats of a deleted project are deleted too.
+    let response = CLIENT
+        .delete(format!("{}/api/v1/projects/1", &address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let (stats,): (i64,) =
+        sqlx::query_as("select count(*) from project_stats where project_id = 1")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(stats, 0);
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let projects: Vec<Project> = response.json().await.unwrap();
+    let names: Vec<&str> = projects.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(names, vec!["actix", "mio"]);
+
+    // Negative assertion
+    let cases = [
+        // The name is taken.
+        (
+            serde_json::json!({"provider_id": 1, "namespace": "foo", "name": "actix"}),
+            StatusCode::CONFLICT,
+        ),
+        // The provider does not exist.
+        (
+            serde_json::json!({"provider_id": 100, "namespace": "foo", "name": "bar"}),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (
+            serde_json::json!({"provider_id": 1, "namespace": "foo", "name": "../bar"}),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        // A field is missing, or unknown.
+        (
+            serde_json::json!({"provider_id": 1, "name": "bar"}),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (
+            serde_json::json!({"provider_id": 1, "namespace": "foo", "name": "bar", "id": 1}),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+    ];
+    for (body, status) in cases {
+        let response = CLIENT
+            .post(format!("{}/api/v1/projects", &address))
+            .bearer_auth(&token)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status(), status, "{body}");
+    }
+
+    let response = CLIENT
+        .patch(format!("{}/api/v1/projects/3", &address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"name": "actix"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    for method in [reqwest::Method::PUT, reqwest::Method::PATCH] {
+        let response = CLIENT
+            .request(method, format!("{}/api/v1/projects/100", &address))
+            .bearer_auth(&token)
+            .json(&serde_json::json!({"provider_id": 1, "namespace": "foo", "name": "bar"}))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    let response = CLIENT
+        .delete(format!("{}/api/v1/projects/1", &address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The changes need an admin token.
+    let response = CLIENT
+        .delete(format!("{}/api/v1/projects/2", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+async fn test_projects_get_unsafe_sites(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let unsafe_site = |file_path: &str, start_line, category| ProjectUnsafeSite {
+        file_path: file_path.to_owned(),
+        category,
+        site: UnsafeSite {
+            kind: UnsafeKind::UnsafeBlock,
+            start_line,
+            end_line: start_line + 2,
+            item_path: "Foo::bar".to_owned(),
+            documented: Some(true),
+        },
+    };
+    let mut analysis = ProjectAnalysis {
+        unsafe_sites: vec![
+            unsafe_site("src/lib.rs", 10, Category::Source),
+            unsafe_site("tests/it.rs", 5, Category::Tests),
+        ],
+        commit_sha: Some("0123abcd".to_owned()),
+        ..Default::default()
+    };
+    let databaseService = database_service(&db).await;
+    databaseService
+        .updateProjectStats(1, &analysis)
+        .await
+        .unwrap();
+
+    // Positive assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/1/unsafe-sites", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let sites: Vec<UnsafeSiteDTO> = response.json().await.unwrap();
+    assert_eq!(sites.len(), 2);
+    assert_eq!(sites[0].kind, "unsafe_block");
+    assert_eq!(sites[0].item_path, "Foo::bar");
+    assert_eq!(sites[0].category, "source");
+    assert_eq!(
+        sites[0].permalink,
+        "https://github.com/seanmonstar/warp/blob/0123abcd/src/lib.rs#L10-L12"
+    );
+    assert_eq!(sites[1].file_path, "tests/it.rs");
+    assert_eq!(sites[1].category, "tests");
+
+    // The sites of the previous analysis are replaced.
+    analysis.unsafe_sites.pop();
+    databaseService
+        .updateProjectStats(1, &analysis)
+        .await
+        .unwrap();
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/1/unsafe-sites", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let sites: Vec<UnsafeSiteDTO> = response.json().await.unwrap();
+    assert_eq!(sites.len(), 1);
+
+    // Negative assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/2/unsafe-sites", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let sites: Vec<UnsafeSiteDTO> = response.json().await.unwrap();
+    assert_eq!(sites.len(), 0);
+}
+
+async fn test_projects_get_safety_coverage(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let unsafe_site = |file_path: &str, kind, documented| ProjectUnsafeSite {
+        file_path: file_path.to_owned(),
+        category: Category::Source,
+        site: UnsafeSite {
+            kind,
+            start_line: 1,
+            end_line: 1,
+            item_path: "".to_owned(),
+            documented,
+        },
+    };
+    let analysis = ProjectAnalysis {
+        unsafe_usage: UnsafeUsage {
+            unsafe_blocks: 3,
+            unsafe_functions: 1,
+            documented_unsafe: 1,
+            undocumented_unsafe: 3,
+            ..Default::default()
+        },
+        unsafe_sites: vec![
+            unsafe_site("src/a.rs", UnsafeKind::UnsafeBlock, Some(true)),
+            unsafe_site("src/a.rs", UnsafeKind::UnsafeFunction, Some(false)),
+            unsafe_site("src/a.rs", UnsafeKind::UnsafeImpl, None),
+            unsafe_site("src/b.rs", UnsafeKind::UnsafeBlock, Some(false)),
+            unsafe_site("src/b.rs", UnsafeKind::UnsafeBlock, Some(false)),
+            unsafe_site("src/c.rs", UnsafeKind::ExternBlock, None),
+        ],
+        ..Default::default()
+    };
+    let databaseService = database_service(&db).await;
+    databaseService
+        .updateProjectStats(1, &analysis)
+        .await
+        .unwrap();
+
+    // Per project
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+    assert_eq!(project_stats[0].unsafe_usage.documented_unsafe, 1);
+    assert_eq!(project_stats[0].unsafe_usage.undocumented_unsafe, 3);
+    assert_eq!(project_stats[0].safety_coverage, Some(25.0));
+
+    // Per file
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/1/safety-coverage", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let coverage: Vec<FileSafetyCoverage> = response.json().await.unwrap();
+    assert_eq!(coverage.len(), 2);
+    assert_eq!(coverage[0].file_path, "src/a.rs");
+    assert_eq!(coverage[0].documented_unsafe, 1);
+    assert_eq!(coverage[0].undocumented_unsafe, 1);
+    assert_eq!(coverage[0].safety_coverage, Some(50.0));
+    assert_eq!(coverage[1].file_path, "src/b.rs");
+    assert_eq!(coverage[1].safety_coverage, Some(0.0));
+
+    // Negative assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/2/safety-coverage", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let coverage: Vec<FileSafetyCoverage> = response.json().await.unwrap();
+    assert_eq!(coverage.len(), 0);
+}
+
+async fn test_project_stats_unsafe_code_lint(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let databaseService = database_service(&db).await;
+    let forbidden = ProjectAnalysis {
+        unsafe_code_lint: UnsafeCodeLint::Forbid,
+        crates: vec![CrateAnalysis {
+            name: "warp".to_owned(),
+            path: "".to_owned(),
+            analysis: ProjectAnalysis {
+                unsafe_code_lint: UnsafeCodeLint::Forbid,
+                ..Default::default()
+            },
+        }],
+        ..Default::default()
+    };
+    databaseService
+        .updateProjectStats(1, &forbidden)
+        .await
+        .unwrap();
+    databaseService
+        .updateProjectStats(2, &ProjectAnalysis::default())
+        .await
+        .unwrap();
+
+    // By id
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+    assert_eq!(project_stats[0].unsafe_code_lint, "forbid");
+
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/1/crates", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let crates: Vec<ProjectCrate> = response.json().await.unwrap();
+    assert_eq!(crates[0].unsafe_code_lint, "forbid");
+
+    // Filtered
+    let response = CLIENT
+        .get(format!(
+            "{}/api/v1/project-stats?unsafe_code_lint=forbid",
+            &address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let result: ProjectStatsWithMeta = response.json().await.unwrap();
+    assert_eq!(result.meta, 1);
+    assert_eq!(result.projectStats[0].name, "warp");
+    assert_eq!(result.projectStats[0].unsafe_code_lint, "forbid");
+
+    // Negative assertion
+    let response = CLIENT
+        .get(format!(
+            "{}/api/v1/project-stats?unsafe_code_lint=forbidden",
+            &address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+async fn test_project_stats_commit(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let databaseService = database_service(&db).await;
+    assert_eq!(
+        databaseService.getLatestCommitShaByProjectId(1).await,
+        Ok(None)
+    );
+    let analysis = ProjectAnalysis {
+        commit_sha: Some("0123456789abcdef0123456789abcdef01234567".to_owned()),
+        commit_branch: Some("master".to_owned()),
+        commit_date: Some("2026-10-01T12:30:00+00:00".to_owned()),
+        ..Default::default()
+    };
+    databaseService
+        .updateProjectStats(1, &analysis)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        databaseService.getLatestCommitShaByProjectId(1).await,
+        Ok(analysis.commit_sha.clone())
+    );
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+    assert_eq!(project_stats[0].commit_sha, analysis.commit_sha);
+    assert_eq!(project_stats[0].commit_branch.as_deref(), Some("master"));
+    assert!(project_stats[0]
+        .commit_date
+        .as_deref()
+        .unwrap()
+        .starts_with("2026-10-01 12:30:00"));
+}
+
+/// Creates a git repository with a tag per year, each with one more unsafe block
+/// until 2022, and a 2023 release that only adds a comment.
+fn create_tagged_repository(dir: &std::path::Path) {
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let git = |args: &[&str], date: &str| {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    };
+    git(&["init", "-q", "-b", "main"], "2020-01-01T00:00:00Z");
+    std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"history\"\n").unwrap();
+    for (i, year) in [2020, 2021, 2022, 2023].iter().enumerate() {
+        let mut source = "fn f() { unsafe {} }\n".repeat(i.min(2) + 1);
+        if *year == 2023 {
+            source.push_str("// The same unsafe code as in 2022.\n");
+        }
+        std::fs::write(dir.join("src/lib.rs"), source).unwrap();
+        let date = format!("{year}-06-01T12:00:00Z");
+        git(&["add", "."], &date);
+        git(&["commit", "-q", "-m", &format!("Release {year}")], &date);
+        git(&["tag", &format!("v{year}")], &date);
+    }
+}
+
+async fn test_project_stats_backfill(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let token = create_api_token(&db, ApiTokenScope::Update).await;
+    let name = format!("backfill_{}", Uuid::new_v4());
+    let remote = std::env::temp_dir().join(format!("{name}_remote"));
+    create_tagged_repository(&remote.join("test").join(format!("{name}.git")));
+    sqlx::query("insert into providers (url) values ($1)")
+        .bind(format!("file://{}", remote.display()))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+    sqlx::query("insert into projects (provider_id, namespace, name) values (1, 'test', $1)")
+        .bind(&name)
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+
+    let response = CLIENT
+        .post(format!(
+            "{}/api/v1/admin/project-stats/1/backfill?limit=2",
+            &address
+        ))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let job: JobDTO = response.json().await.unwrap();
+    assert_eq!(job.job.kind, "backfill_project_stats");
+    let job = wait_for_job(&address, job.job.id).await;
+    assert_eq!(job.projects[0].status, "succeeded");
+
+    // Both releases are kept, although they have the same unsafe_lines.
+    let history = |address: String| async move {
+        let response = CLIENT
+            .get(format!("{}/api/v1/project-stats/1", &address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert!(response.status().is_success());
+        let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+        return project_stats
+            .into_iter()
+            .map(|v| (v.created_at, v.unsafe_lines))
+            .collect::<Vec<(String, i32)>>();
+    };
+    let expected = |rows: &[(&str, i32)]| {
+        return rows
+            .iter()
+            .map(|(date, unsafeLines)| (date.to_string(), *unsafeLines))
+            .collect::<Vec<(String, i32)>>();
+    };
+    assert_eq!(
+        history(address.clone()).await,
+        expected(&[("2023-06-01", 3), ("2022-06-01", 3)])
+    );
+
+    // Every commit, the stats of v2022 and v2023 are kept.
+    let response = CLIENT
+        .post(format!(
+            "{}/api/v1/admin/project-stats/1/backfill?every=1",
+            &address
+        ))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let job: JobDTO = response.json().await.unwrap();
+    wait_for_job(&address, job.job.id).await;
+    assert_eq!(
+        history(address.clone()).await,
+        expected(&[
+            ("2023-06-01", 3),
+            ("2022-06-01", 3),
+            ("2021-06-01", 2),
+            ("2020-06-01", 1)
+        ])
+    );
+
+    // The current stats are listed, although the tags of the backfill are newer.
+    sqlx::query(
+        "insert into project_stats (project_id, code_lines, unsafe_lines, created_at, historical)
+        values (1, 10, 5, '2022-01-01', false)",
+    )
+    .execute(&db)
+    .await
+    .expect("Failed to create entry");
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats", &address))
+        .query(&[("name", &name)])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let result: ProjectStatsWithMeta = response.json().await.unwrap();
+    let listed: Vec<i32> = result.projectStats.iter().map(|v| v.unsafe_lines).collect();
+    assert_eq!(listed, vec![5]);
+    // The tags are only in the history.
+    assert_eq!(
+        history(address.clone()).await,
+        expected(&[
+            ("2023-06-01", 3),
+            ("2022-06-01", 3),
+            ("2022-01-01", 5),
+            ("2021-06-01", 2),
+            ("2020-06-01", 1)
+        ])
+    );
+
+    // Negative assertion
+    let response = CLIENT
+        .post(format!(
+            "{}/api/v1/admin/project-stats/2/backfill",
+            &address
+        ))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let _ = std::fs::remove_dir_all(&remote);
+    let _ = std::fs::remove_dir_all(git_workspace_dir().join("test").join(&name));
+}
+
+async fn test_projects_get_crates(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let crate_analysis = |name: &str, path: &str, unsafe_blocks| CrateAnalysis {
+        name: name.to_owned(),
+        path: path.to_owned(),
+        analysis: ProjectAnalysis {
+            unsafe_usage: UnsafeUsage {
+                unsafe_blocks,
+                unsafe_block_lines: unsafe_blocks,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    };
+    let mut analysis = ProjectAnalysis {
+        crates: vec![
+            crate_analysis("tokio", "tokio", 10),
+            crate_analysis("tokio-macros", "tokio-macros", 0),
+        ],
+        ..Default::default()
+    };
+    let databaseService = database_service(&db).await;
+    databaseService
+        .updateProjectStats(1, &analysis)
+        .await
+        .unwrap();
+
+    // Positive assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/1/crates", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let crates: Vec<ProjectCrate> = response.json().await.unwrap();
+    assert_eq!(crates.len(), 2);
+    assert_eq!(crates[0].name, "tokio");
+    assert_eq!(crates[0].unsafe_lines, 10);
+    assert_eq!(crates[1].path, "tokio-macros");
+
+    // Crates that are no longer workspace members are removed.
+    analysis.crates.pop();
+    databaseService
+        .updateProjectStats(1, &analysis)
+        .await
+        .unwrap();
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/1/crates", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let crates: Vec<ProjectCrate> = response.json().await.unwrap();
+    assert_eq!(crates.len(), 1);
+    assert_eq!(crates[0].name, "tokio");
+
+    // Negative assertion
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/2/crates", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let crates: Vec<ProjectCrate> = response.json().await.unwrap();
+    assert_eq!(crates.len(), 0);
+}
+
+async fn test_health_check(backend: DatabaseBackend) {
+    let (address, _db) = spawn_app(backend).await;
+    let response = CLIENT
+        .get(format!("{}/api/health_check", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+}
+
+async fn test_cors_preflight(backend: DatabaseBackend) {
+    let (address, _db) = spawn_app(backend).await;
+
+    // Positive assertion
+    let response = CLIENT
+        .request(
+            reqwest::Method::OPTIONS,
+            format!("{}/api/v1/projects/1", &address),
+        )
+        .header("Origin", "https://unsaferust.org")
+        .header("Access-Control-Request-Method", "PATCH")
+        .header(
+            "Access-Control-Request-Headers",
+            "authorization,content-type",
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let headers = response.headers();
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("PATCH"));
+    assert!(methods.contains("DELETE"));
+    let allowed = headers["access-control-allow-headers"].to_str().unwrap();
+    assert!(allowed.contains("authorization"));
+    assert!(allowed.contains("content-type"));
+
+    // Negative assertion
+    let response = CLIENT
+        .request(
+            reqwest::Method::OPTIONS,
+            format!("{}/api/v1/projects/1", &address),
+        )
+        .header("Origin", "https://example.com")
+        .header("Access-Control-Request-Method", "PATCH")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+}
+
+async fn test_project_stats_pagination(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    for i in 1..10 {
+        let _ = sqlx::query(&format!(
+            "
+            insert into projects (provider_id, name, namespace)
+            values (1, 'name_{i}', 'namespace_{i}')"
+        ))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+
+        let _ = sqlx::query(&format!(
+            "
+            insert into project_stats (project_id, code_lines, unsafe_lines)
+            values ({i}, '{i}', '{i}')"
+        ))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+    }
+
+    // Start requesting
+    // 1. Get all items.
+    redis_flush(&address, &db).await;
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats?page=1&limit=25", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 9);
+
+    // 1. Get 3 items per page (page 1).
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats?page=1&limit=3", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 3);
+    assert_eq!(response.projectStats[0].name, "name_1");
+    assert_eq!(response.projectStats[1].name, "name_2");
+    assert_eq!(response.projectStats[2].name, "name_3");
+
+    // 1. Get 3 items per page (page 3).
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats?page=3&limit=3", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 3);
+    assert_eq!(response.projectStats[0].name, "name_7");
+    assert_eq!(response.projectStats[1].name, "name_8");
+    assert_eq!(response.projectStats[2].name, "name_9");
+
+    // 1. Get 4 items per page (page 2).
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats?page=2&limit=4", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 4);
+    assert_eq!(response.projectStats[0].name, "name_5");
+    assert_eq!(response.projectStats[1].name, "name_6");
+    assert_eq!(response.projectStats[2].name, "name_7");
+    assert_eq!(response.projectStats[3].name, "name_8");
+
+    // 1. Get 8 items per page (page 2).
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats?page=2&limit=8", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 1);
+    assert_eq!(response.projectStats[0].name, "name_9");
+}
+
+async fn testProjectStatsPaginationWithName(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
 
     // Setup
     let _result = create_provider(&db).await;
@@ -332,233 +1691,823 @@ async fn testProjectStatsPaginationWithName() {
             insert into projects (id, provider_id, name, namespace)
             values ({i}, 1, 'foo_{i}', 'namespace_{i}')"
         ))
-        .execute(&db)
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+
+        let _ = sqlx::query(&format!(
+            "
+            insert into project_stats (project_id, code_lines, unsafe_lines)
+            values ({i}, '{i}', '{i}')
+            "
+        ))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+    }
+
+    // Insert bar
+    for i in 1..10 {
+        let _ = sqlx::query(&format!(
+            "
+            insert into projects (id, provider_id, name, namespace)
+            values (({i} + 9), 1, 'bar_{i}', 'namespace_{i}')"
+        ))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+
+        let _ = sqlx::query(&format!(
+            "
+            insert into project_stats (project_id, code_lines, unsafe_lines)
+            values (({i} + 9), '{i}', '{i}')
+            "
+        ))
+        .execute(&db)
+        .await
+        .expect("Failed to create entry");
+    }
+
+    // Start requesting
+    // 1. Get all items.
+    redis_flush(&address, &db).await;
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats?page=1&limit=25", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 18);
+
+    // 1. Get 3 foo items per page (page 1).
+    let response = CLIENT
+        .get(format!(
+            "{}/api/v1/project-stats?page=1&limit=3&name=foo",
+            &address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 3);
+    assert_eq!(response.projectStats[0].name, "foo_1");
+    assert_eq!(response.projectStats[1].name, "foo_2");
+    assert_eq!(response.projectStats[2].name, "foo_3");
+
+    // 1. Get 3 foo items per page (page 3).
+    let response = CLIENT
+        .get(format!(
+            "{}/api/v1/project-stats?page=3&limit=3&name=foo",
+            &address
+        ))
+        .send()
         .await
-        .expect("Failed to create entry");
+        .expect("Failed to execute request.");
 
-        let _ = sqlx::query(&format!(
-            "
-            insert into project_stats (project_id, code_lines, unsafe_lines)
-            values ({i}, '{i}', '{i}')
-            "
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 3);
+    assert_eq!(response.projectStats[0].name, "foo_7");
+    assert_eq!(response.projectStats[1].name, "foo_8");
+    assert_eq!(response.projectStats[2].name, "foo_9");
+
+    // 1. Get 4 bar items per page (page 2).
+    let response = CLIENT
+        .get(format!(
+            "{}/api/v1/project-stats?page=2&limit=4&name=bar",
+            &address
         ))
-        .execute(&db)
+        .send()
         .await
-        .expect("Failed to create entry");
-    }
+        .expect("Failed to execute request.");
 
-    // Insert bar
-    for i in 1..10 {
-        let _ = sqlx::query(&format!(
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 4);
+    assert_eq!(response.projectStats[0].name, "bar_5");
+    assert_eq!(response.projectStats[1].name, "bar_6");
+    assert_eq!(response.projectStats[2].name, "bar_7");
+    assert_eq!(response.projectStats[3].name, "bar_8");
+
+    // 1. Get 8 bar items per page (page 2).
+    let response = CLIENT
+        .get(format!(
+            "{}/api/v1/project-stats?page=2&limit=8&name=bar",
+            &address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 1);
+    assert_eq!(response.projectStats[0].name, "bar_9");
+
+    // Get a non existent item
+    let response = CLIENT
+        .get(format!(
+            "{}/api/v1/project-stats?page=2&limit=8&name=hello",
+            &address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 0);
+}
+
+async fn test_project_stats_sorting_and_filters(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    sqlx::query(
+        "insert into providers (url) values ('https://github.com'), ('https://gitlab.com')",
+    )
+    .execute(&db)
+    .await
+    .expect("Failed to create entry");
+    // name, provider, code lines, unsafe lines, updated at
+    let projects = [
+        ("small", 1, 100, 5, "2024-01-01"),
+        ("large", 1, 10000, 200, "2024-03-01"),
+        ("safe", 1, 20000, 100, "2024-02-01"),
+        ("other", 2, 5000, 150, "2024-04-01"),
+        ("empty", 1, 0, 0, "2024-05-01"),
+    ];
+    for (i, (name, providerId, codeLines, unsafeLines, updatedAt)) in projects.iter().enumerate() {
+        sqlx::query("insert into projects (provider_id, namespace, name) values ($1, 'test', $2)")
+            .bind(providerId)
+            .bind(name)
+            .execute(&db)
+            .await
+            .expect("Failed to create entry");
+        sqlx::query(&format!(
             "
-            insert into projects (id, provider_id, name, namespace)
-            values (({i} + 9), 1, 'bar_{i}', 'namespace_{i}')"
+            insert into project_stats (project_id, code_lines, unsafe_lines, updated_at)
+            values ($1, $2, $3, '{updatedAt}')"
         ))
+        .bind(i as i32 + 1)
+        .bind(codeLines)
+        .bind(unsafeLines)
         .execute(&db)
         .await
         .expect("Failed to create entry");
+    }
+    redis_flush(&address, &db).await;
+
+    let names = |query: &'static str| {
+        let address = address.clone();
+        return async move {
+            let response = CLIENT
+                .get(format!("{}/api/v1/project-stats?{}", &address, query))
+                .send()
+                .await
+                .expect("Failed to execute request.");
+            assert!(response.status().is_success(), "{query}");
+            let result: ProjectStatsWithMeta = response.json().await.unwrap();
+            return result
+                .projectStats
+                .into_iter()
+                .map(|v| v.name)
+                .collect::<Vec<String>>();
+        };
+    };
 
-        let _ = sqlx::query(&format!(
-            "
-            insert into project_stats (project_id, code_lines, unsafe_lines)
-            values (({i} + 9), '{i}', '{i}')
-            "
-        ))
+    // Positive assertion
+    assert_eq!(
+        names("sort=code_lines&order=desc").await,
+        vec!["safe", "large", "other", "small", "empty"]
+    );
+    assert_eq!(
+        names("sort=unsafe_lines").await,
+        vec!["empty", "small", "safe", "other", "large"]
+    );
+    // The projects without code come last.
+    assert_eq!(
+        names("sort=unsafe_ratio&order=desc").await,
+        vec!["small", "other", "large", "safe", "empty"]
+    );
+    assert_eq!(
+        names("sort=updated_at&order=desc&limit=2").await,
+        vec!["empty", "other"]
+    );
+    // The largest crates with more than 1% unsafe.
+    assert_eq!(
+        names("min_unsafe_ratio=1&min_code_lines=1000&sort=code_lines&order=desc").await,
+        vec!["large", "other"]
+    );
+    assert_eq!(
+        names("max_unsafe_ratio=2&min_code_lines=1").await,
+        vec!["large", "safe"]
+    );
+    assert_eq!(names("provider_id=2").await, vec!["other"]);
+    assert_eq!(
+        names("updated_since=2024-03-01&sort=updated_at").await,
+        vec!["large", "other", "empty"]
+    );
+
+    // Negative assertion
+    for query in [
+        "sort=unsafe_lines%3Bdrop%20table%20projects",
+        "order=random",
+        "min_unsafe_ratio=-1",
+        "updated_since=2024-13-01",
+        "min_code_lines=many",
+    ] {
+        let response = CLIENT
+            .get(format!("{}/api/v1/project-stats?{}", &address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+    }
+}
+
+async fn test_project_stats_cache_invalidation(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    // The name keeps the cached pages apart from the ones of the other tests.
+    let prefix = format!("cached_{}", Uuid::new_v4().simple());
+    let token = create_api_token(&db, ApiTokenScope::Admin).await;
+    sqlx::query(
+        "insert into providers (url) values ('https://github.com'), ('https://gitlab.com')",
+    )
+    .execute(&db)
+    .await
+    .expect("Failed to create entry");
+    for providerId in [1, 2] {
+        sqlx::query("insert into projects (provider_id, namespace, name) values ($1, 'test', $2)")
+            .bind(providerId)
+            .bind(format!("{prefix}_{providerId}"))
+            .execute(&db)
+            .await
+            .expect("Failed to create entry");
+        sqlx::query(
+            "insert into project_stats (project_id, code_lines, unsafe_lines) values ($1, 100, 1)",
+        )
+        .bind(providerId)
         .execute(&db)
         .await
         .expect("Failed to create entry");
     }
+    let getPage = |filter: String| {
+        let url = format!(
+            "{}/api/v1/project-stats?name={}{}",
+            &address, &prefix, filter
+        );
+        return async move {
+            let response = CLIENT
+                .get(url)
+                .send()
+                .await
+                .expect("Failed to execute request.");
+            assert!(response.status().is_success());
+            let page: Value = response.json().await.unwrap();
+            return page["projectStats"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| {
+                    (
+                        v["name"].as_str().unwrap().to_owned(),
+                        v["unsafe_lines"].clone(),
+                    )
+                })
+                .collect::<Vec<_>>();
+        };
+    };
+    assert_eq!(getPage("".to_owned()).await.len(), 2);
+    assert_eq!(getPage("&provider_id=1".to_owned()).await.len(), 1);
+    assert_eq!(getPage("&provider_id=2".to_owned()).await.len(), 1);
+    // A change that bypasses the API, so that only the invalidated pages show it.
+    sqlx::query("update project_stats set unsafe_lines = 2 where project_id = 2")
+        .execute(&db)
+        .await
+        .expect("Failed to update entry");
 
-    // Start requesting
-    // 1. Get all items.
-    redis_flush(&address).await;
+    // Positive assertion
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats?page=1&limit=25", &address))
+        .patch(format!("{}/api/v1/projects/1", &address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"name": format!("{prefix}_renamed")}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        getPage("&provider_id=1".to_owned()).await,
+        vec![(format!("{prefix}_renamed"), Value::from(1))]
+    );
+    assert_eq!(
+        getPage("".to_owned()).await,
+        vec![
+            (format!("{prefix}_2"), Value::from(2)),
+            (format!("{prefix}_renamed"), Value::from(1)),
+        ]
+    );
+
+    // Negative assertion
+    // The pages of the other provider are still cached.
+    assert_eq!(
+        getPage("&provider_id=2".to_owned()).await,
+        vec![(format!("{prefix}_2"), Value::from(1))]
+    );
+}
+
+async fn redis_flush(address: &String, db: &AnyPool) {
+    let token = create_api_token(db, ApiTokenScope::Admin).await;
+    let _purge_result = CLIENT
+        .post(format!("{}/api/v1/admin/redis/flush", &address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(_purge_result.status(), 200);
+}
+
+async fn test_project_stats_get_all(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let _result = create_project_stats(&db).await;
+
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
+    assert_eq!(response.projectStats.len(), 2);
+    assert_eq!(response.projectStats[0].unsafe_lines, 11);
+    assert_eq!(response.projectStats[0].created_at, "2021-01-01");
+    assert_eq!(response.projectStats[1].unsafe_lines, 11);
+    assert_eq!(response.projectStats[1].created_at, "2021-01-01");
+    assert_eq!(response.meta, 2);
+
+    // Todo: Add testing for the meta property.
+}
+
+async fn test_project_stats_get_by_id(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let _result = create_project_stats(&db).await;
+
+    // Testing with project id 1.
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert (we must get 2 records for id 1 in created_at desc)
+    assert!(response.status().is_success());
+    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+    assert_eq!(project_stats.len(), 2);
+    assert_eq!(project_stats[0].unsafe_lines, 11);
+    assert_eq!(project_stats[0].created_at, "2021-01-01");
+    assert_eq!(project_stats[1].unsafe_lines, 10);
+    assert_eq!(project_stats[1].created_at, "2020-01-01");
+
+    // Testing with project id 2.
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/2", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert (we must get 2 records for id 2 in created_at desc)
+    assert!(response.status().is_success());
+    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+    assert_eq!(project_stats.len(), 2);
+    assert_eq!(project_stats[0].unsafe_lines, 11);
+    assert_eq!(project_stats[0].created_at, "2021-01-01");
+    assert_eq!(project_stats[1].unsafe_lines, 20);
+    assert_eq!(project_stats[1].created_at, "2020-01-01");
+}
+
+async fn test_project_stats_breakdown(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let _ = sqlx::query(
+        "
+        insert into project_stats (
+            project_id, code_lines, comment_lines, doc_comment_lines, blank_lines, unsafe_lines,
+            unsafe_blocks, unsafe_functions, unsafe_impls, unsafe_traits,
+            extern_blocks, no_mangle_items, unsafe_block_lines
+        )
+        values (1, 100, 20, 30, 15, 10, 4, 3, 2, 1, 5, 6, 40)
+    ",
+    )
+    .execute(&db)
+    .await
+    .expect("Failed to create entry");
+
+    // By id
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+    assert_eq!(project_stats.len(), 1);
+    let line_counts = project_stats[0].line_counts;
+    assert_eq!(line_counts.code_lines, 100);
+    assert_eq!(line_counts.comment_lines, 20);
+    assert_eq!(line_counts.doc_comment_lines, 30);
+    assert_eq!(line_counts.blank_lines, 15);
+    let unsafe_usage = project_stats[0].unsafe_usage;
+    assert_eq!(unsafe_usage.unsafe_blocks, 4);
+    assert_eq!(unsafe_usage.unsafe_functions, 3);
+    assert_eq!(unsafe_usage.unsafe_impls, 2);
+    assert_eq!(unsafe_usage.unsafe_traits, 1);
+    assert_eq!(unsafe_usage.extern_blocks, 5);
+    assert_eq!(unsafe_usage.no_mangle_items, 6);
+    assert_eq!(unsafe_usage.unsafe_block_lines, 40);
+
+    // All (the fields are flattened in the json)
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats?name=warp", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let result: Value = response.json().await.unwrap();
+    assert_eq!(result["projectStats"][0]["code_lines"], 100);
+    assert_eq!(result["projectStats"][0]["doc_comment_lines"], 30);
+    assert_eq!(result["projectStats"][0]["unsafe_blocks"], 4);
+    assert_eq!(result["projectStats"][0]["extern_blocks"], 5);
+    assert_eq!(result["projectStats"][0]["no_mangle_items"], 6);
+    assert_eq!(result["projectStats"][0]["unsafe_block_lines"], 40);
+}
+
+async fn test_project_stats_categories(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let source = CategoryStats {
+        line_counts: LineCounts {
+            code_lines: 100,
+            ..Default::default()
+        },
+        unsafe_usage: UnsafeUsage {
+            unsafe_blocks: 2,
+            unsafe_block_lines: 2,
+            ..Default::default()
+        },
+    };
+    let tests = CategoryStats {
+        line_counts: LineCounts {
+            code_lines: 50,
+            ..Default::default()
+        },
+        unsafe_usage: UnsafeUsage {
+            unsafe_blocks: 3,
+            unsafe_functions: 1,
+            unsafe_block_lines: 5,
+            ..Default::default()
+        },
+    };
+    let analysis = ProjectAnalysis {
+        unsafe_usage: UnsafeUsage {
+            unsafe_blocks: 5,
+            unsafe_functions: 1,
+            unsafe_block_lines: 7,
+            ..Default::default()
+        },
+        line_counts: LineCounts {
+            code_lines: 150,
+            ..Default::default()
+        },
+        categories: [(Category::Source, source), (Category::Tests, tests)].into(),
+        ..Default::default()
+    };
+    // Storing the same analysis twice must update the existing row.
+    let databaseService = database_service(&db).await;
+    databaseService
+        .updateProjectStats(1, &analysis)
+        .await
+        .unwrap();
+    databaseService
+        .updateProjectStats(1, &analysis)
+        .await
+        .unwrap();
+
+    // Totals and production figures
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
+    assert_eq!(project_stats.len(), 1);
+    assert_eq!(project_stats[0].unsafe_lines, 7);
+    assert_eq!(project_stats[0].unsafe_usage.unsafe_blocks, 5);
+    assert_eq!(project_stats[0].line_counts.code_lines, 150);
+    assert_eq!(project_stats[0].production_unsafe_lines, 2);
+    assert_eq!(project_stats[0].production_code_lines, 100);
+
+    // Categories
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats/1/categories", &address))
         .send()
         .await
         .expect("Failed to execute request.");
 
     // Assert
     assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 18);
+    let categories: Vec<ProjectCategoryStats> = response.json().await.unwrap();
+    assert_eq!(categories.len(), 2);
+    assert_eq!(categories[0].category, "source");
+    assert_eq!(categories[0].unsafe_lines, 2);
+    assert_eq!(categories[1].category, "tests");
+    assert_eq!(categories[1].unsafe_lines, 5);
+    assert_eq!(categories[1].line_counts.code_lines, 50);
 
-    // 1. Get 3 foo items per page (page 1).
+    // Negative assertion
     let response = CLIENT
-        .get(format!(
-            "{}/api/v1/project-stats?page=1&limit=3&name=foo",
-            &address
-        ))
+        .get(format!("{}/api/v1/project-stats/2/categories", &address))
         .send()
         .await
         .expect("Failed to execute request.");
 
     // Assert
     assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 3);
-    assert_eq!(response.projectStats[0].name, "foo_1");
-    assert_eq!(response.projectStats[1].name, "foo_2");
-    assert_eq!(response.projectStats[2].name, "foo_3");
+    let categories: Vec<ProjectCategoryStats> = response.json().await.unwrap();
+    assert_eq!(categories.len(), 0);
+}
 
-    // 1. Get 3 foo items per page (page 3).
+async fn test_error_responses(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let token = create_api_token(&db, ApiTokenScope::Admin).await;
+
+    // Client errors are problem details, with what went wrong.
     let response = CLIENT
-        .get(format!(
-            "{}/api/v1/project-stats?page=3&limit=3&name=foo",
-            &address
-        ))
+        .get(format!("{}/api/v1/jobs/100", &address))
         .send()
         .await
         .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+    let problem: Problem = response.json().await.unwrap();
+    assert_eq!(problem.type_, "about:blank");
+    assert_eq!(problem.detail, "Job 100 does not exist");
 
-    // Assert
-    assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 3);
-    assert_eq!(response.projectStats[0].name, "foo_7");
-    assert_eq!(response.projectStats[1].name, "foo_8");
-    assert_eq!(response.projectStats[2].name, "foo_9");
-
-    // 1. Get 4 bar items per page (page 2).
     let response = CLIENT
         .get(format!(
-            "{}/api/v1/project-stats?page=2&limit=4&name=bar",
+            "{}/api/v1/project-stats?unsafe_code_lint=foo",
             &address
         ))
         .send()
         .await
         .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let problem: Problem = response.json().await.unwrap();
+    assert!(problem.detail.contains("foo"));
 
-    // Assert
-    assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 4);
-    assert_eq!(response.projectStats[0].name, "bar_5");
-    assert_eq!(response.projectStats[1].name, "bar_6");
-    assert_eq!(response.projectStats[2].name, "bar_7");
-    assert_eq!(response.projectStats[3].name, "bar_8");
-
-    // 1. Get 8 bar items per page (page 2).
     let response = CLIENT
-        .get(format!(
-            "{}/api/v1/project-stats?page=2&limit=8&name=bar",
-            &address
-        ))
+        .post(format!("{}/api/v1/providers", &address))
+        .bearer_auth(&token)
+        .header("content-type", "application/json")
+        .body("{\"url\":")
         .send()
         .await
         .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let problem: Problem = response.json().await.unwrap();
+    assert_eq!(problem.title, "Bad Request");
 
-    // Assert
-    assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 1);
-    assert_eq!(response.projectStats[0].name, "bar_9");
-
-    // Get a non existent item
     let response = CLIENT
-        .get(format!(
-            "{}/api/v1/project-stats?page=2&limit=8&name=hello",
-            &address
-        ))
+        .post(format!("{}/api/v1/admin/redis/flush", &address))
         .send()
         .await
         .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let problem: Problem = response.json().await.unwrap();
+    assert_eq!(problem.detail, "A valid bearer token is required");
 
-    // Assert
-    assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 0);
-}
-
-async fn redis_flush(address: &String) {
-    let _purge_result = CLIENT
-        .get(format!("{}/api/redis/flush", &address))
+    // Server errors are logged, and their details are kept from the client.
+    sqlx::query("drop table project_crates")
+        .execute(&db)
+        .await
+        .expect("Failed to drop the table");
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/1/crates", &address))
         .send()
         .await
         .expect("Failed to execute request.");
-    assert_eq!(_purge_result.status(), 200);
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let problem: Problem = response.json().await.unwrap();
+    assert_eq!(problem.detail, "The database query failed");
+    let (error,): (String,) =
+        sqlx::query_as("select error from error_log order by id desc limit 1")
+            .fetch_one(&db)
+            .await
+            .expect("Failed to read the error log");
+    assert!(error.starts_with("GET /api/v1/projects/1/crates: "));
+    assert!(error.contains("project_crates"));
 }
 
 #[tokio::test]
-async fn test_project_stats_get_all() {
-    let (address, db) = spawn_app().await;
-
+async fn test_read_only_server() {
     // Setup
-    let _result = create_provider(&db).await;
-    let _result = create_project(&db).await;
-    let _result = create_project_stats(&db).await;
+    let (address, repository) =
+        spawn_cached_app(Arc::new(InMemoryCache::new(100, Duration::from_secs(60)))).await;
+    let analysis = ProjectAnalysis {
+        line_counts: LineCounts {
+            code_lines: 100,
+            ..Default::default()
+        },
+        unsafe_usage: UnsafeUsage {
+            unsafe_blocks: 2,
+            unsafe_block_lines: 2,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    repository.updateProjectStats(1, &analysis).await.unwrap();
 
+    // Positive assertion
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats", &address))
+        .get(format!("{}/api/v1/providers", &address))
         .send()
         .await
         .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let providers: Vec<Provider> = response.json().await.unwrap();
+    assert_eq!(providers[0].url, "https://github.com");
 
-    // Assert
+    let response = CLIENT
+        .get(format!("{}/api/v1/project-stats?name=clap", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
     assert!(response.status().is_success());
-    let result: Value = response.json().await.unwrap();
-    let response: ProjectStatsWithMeta = serde_json::from_value(result).unwrap();
-    assert_eq!(response.projectStats.len(), 2);
-    assert_eq!(response.projectStats[0].unsafe_lines, 11);
-    assert_eq!(response.projectStats[0].created_at, "2021-01-01");
-    assert_eq!(response.projectStats[1].unsafe_lines, 11);
-    assert_eq!(response.projectStats[1].created_at, "2021-01-01");
-    assert_eq!(response.meta, 2);
+    let result: ProjectStatsWithMeta = response.json().await.unwrap();
+    assert_eq!(result.meta, 1);
+    assert_eq!(result.projectStats[0].unsafe_lines, 2);
 
-    // Todo: Add testing for the meta property.
-}
+    let response = CLIENT
+        .get(format!("{}/api/v2/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let history: ProjectStatsHistory = response.json().await.unwrap();
+    assert_eq!(history.project.name, "clap");
+    assert_eq!(history.stats.len(), 1);
 
-#[tokio::test]
-async fn test_project_stats_get_by_id() {
-    let (address, db) = spawn_app().await;
+    // Negative assertion
+    let response = CLIENT
+        .get(format!("{}/api/v2/projects/2", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    // The jobs and the admin endpoints need a job store and a token store.
+    let response = CLIENT
+        .post(format!("{}/api/v1/admin/project-stats/update", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
 
-    // Setup
-    let _result = create_provider(&db).await;
-    let _result = create_project(&db).await;
-    let _result = create_project_stats(&db).await;
+/// Serves the read-only API of an in-memory repository with a cache.
+async fn spawn_cached_app(cache: Arc<dyn Cache>) -> (String, Arc<InMemoryRepository>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    let address = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let repository = Arc::new(InMemoryRepository::new());
+    let server = unsaferust::runReadOnly(listener, cache, repository.clone())
+        .expect("Failed to bind address");
+    tokio::spawn(server);
+    repository
+        .createProvider("https://github.com")
+        .await
+        .unwrap();
+    repository
+        .createProject("https://github.com", "namespace", "clap")
+        .await
+        .unwrap();
+    return (address, repository);
+}
 
-    // Testing with project id 1.
+async fn get_project_stats_meta(address: &str) -> i64 {
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats/1", &address))
+        .get(format!("{}/api/v1/project-stats", address))
         .send()
         .await
         .expect("Failed to execute request.");
-
-    // Assert (we must get 2 records for id 1 in created_at desc)
     assert!(response.status().is_success());
-    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
-    assert_eq!(project_stats.len(), 2);
-    assert_eq!(project_stats[0].unsafe_lines, 11);
-    assert_eq!(project_stats[0].created_at, "2021-01-01");
-    assert_eq!(project_stats[1].unsafe_lines, 10);
-    assert_eq!(project_stats[1].created_at, "2020-01-01");
+    let result: ProjectStatsWithMeta = response.json().await.unwrap();
+    return result.meta;
+}
 
-    // Testing with project id 2.
+#[tokio::test]
+async fn test_project_stats_with_in_memory_cache() {
+    // Setup
+    let (address, repository) =
+        spawn_cached_app(Arc::new(InMemoryCache::new(100, Duration::from_secs(60)))).await;
+    let analysis = ProjectAnalysis::default();
+
+    // Positive assertion
+    assert_eq!(get_project_stats_meta(&address).await, 0);
+    repository.updateProjectStats(1, &analysis).await.unwrap();
+    // The page is cached, so the new stats are not seen yet.
+    assert_eq!(get_project_stats_meta(&address).await, 0);
     let response = CLIENT
-        .get(format!("{}/api/v1/project-stats/2", &address))
+        .get(format!("{}/api/v1/project-stats?limit=5", &address))
         .send()
         .await
         .expect("Failed to execute request.");
+    let result: ProjectStatsWithMeta = response.json().await.unwrap();
+    assert_eq!(result.meta, 1);
 
-    // Assert (we must get 2 records for id 2 in created_at desc)
-    assert!(response.status().is_success());
-    let project_stats: Vec<ProjectStats> = response.json().await.unwrap();
-    assert_eq!(project_stats.len(), 2);
-    assert_eq!(project_stats[0].unsafe_lines, 11);
-    assert_eq!(project_stats[0].created_at, "2021-01-01");
-    assert_eq!(project_stats[1].unsafe_lines, 20);
-    assert_eq!(project_stats[1].created_at, "2020-01-01");
+    // Negative assertion
+    // A miss is not an error.
+    assert!(repository.errors().is_empty());
 }
 
 #[tokio::test]
-async fn test_non_existing_routes() {
-    let (address, _db) = spawn_app().await;
+async fn test_project_stats_without_redis() {
+    // Setup
+    // A Redis that closes the connection, and then goes away.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("redis://{}", listener.local_addr().unwrap());
+    let redis = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        drop(socket);
+    });
+    let redisService = RedisService::connect(&url, Duration::from_secs(60))
+        .await
+        .unwrap();
+    redis.await.unwrap();
+    let (address, repository) = spawn_cached_app(Arc::new(redisService)).await;
+    repository
+        .updateProjectStats(1, &ProjectAnalysis::default())
+        .await
+        .unwrap();
+
+    // Positive assertion
+    // The stats are queried without the cache.
+    assert_eq!(get_project_stats_meta(&address).await, 1);
+    assert_eq!(get_project_stats_meta(&address).await, 1);
+
+    // Negative assertion
+    // Only the lost connection is logged, not every request while Redis is away.
+    let errors = repository.errors();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(errors[0].contains("Cache::getKey()"));
+}
+
+async fn test_non_existing_routes(backend: DatabaseBackend) {
+    let (address, _db) = spawn_app(backend).await;
 
     let arbitrary_paths = vec!["", "foo", "foo/bar", "foo/bar/baz"];
     for arbitrary_path in arbitrary_paths {
@@ -577,9 +2526,8 @@ async fn test_non_existing_routes() {
 /*************/
 /* providers */
 /*************/
-#[tokio::test]
-async fn test_providers_get_all() {
-    let (address, db) = spawn_app().await;
+async fn test_providers_get_all(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
 
     // Create an entry with sqlx since we dont have an endpoint.
     let _ = create_provider(&db).await;
@@ -597,9 +2545,8 @@ async fn test_providers_get_all() {
     assert_eq!(providers[0].url, "https://github.com");
 }
 
-#[tokio::test]
-async fn test_providers_get_by_id() {
-    let (address, db) = spawn_app().await;
+async fn test_providers_get_by_id(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
 
     // Create an entry with sqlx since we dont have an endpoint.
     let _result = create_provider(&db).await;
@@ -628,3 +2575,189 @@ async fn test_providers_get_by_id() {
     let providers: Vec<Provider> = response.json().await.unwrap();
     assert_eq!(providers.len(), 0);
 }
+
+async fn test_providers_crud(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let token = create_api_token(&db, ApiTokenScope::Admin).await;
+    let updateToken = create_api_token(&db, ApiTokenScope::Update).await;
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+
+    // Positive assertion
+    let response = CLIENT
+        .post(format!("{}/api/v1/providers", &address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"url": "https://gitlab.com"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let provider: Value = response.json().await.unwrap();
+    assert_eq!(
+        provider,
+        serde_json::json!({"id": 2, "url": "https://gitlab.com"})
+    );
+
+    for method in [reqwest::Method::PUT, reqwest::Method::PATCH] {
+        let response = CLIENT
+            .request(method, format!("{}/api/v1/providers/2", &address))
+            .bearer_auth(&token)
+            .json(&serde_json::json!({"url": "https://codeberg.org"}))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status(), StatusCode::OK);
+        let provider: Provider = response.json().await.unwrap();
+        assert_eq!(provider.url, "https://codeberg.org");
+    }
+
+    let response = CLIENT
+        .delete(format!("{}/api/v1/providers/2", &address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // Negative assertion
+    let response = CLIENT
+        .post(format!("{}/api/v1/providers", &address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"url": "https://github.com"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = CLIENT
+        .post(format!("{}/api/v1/providers", &address))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"url": "ftp://github.com"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // A provider with projects is kept.
+    let response = CLIENT
+        .delete(format!("{}/api/v1/providers/1", &address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = CLIENT
+        .delete(format!("{}/api/v1/providers/2", &address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = CLIENT
+        .post(format!("{}/api/v1/providers", &address))
+        .bearer_auth(&updateToken)
+        .json(&serde_json::json!({"url": "https://gitlab.com"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+/******/
+/* v2 */
+/******/
+async fn test_v2_get_by_id(backend: DatabaseBackend) {
+    let (address, db) = spawn_app(backend).await;
+
+    // Setup
+    let _result = create_provider(&db).await;
+    let _result = create_project(&db).await;
+    let _result = create_project_stats(&db).await;
+    sqlx::query(
+        "insert into projects (provider_id, namespace, name) values (1, 'tokio-rs', 'tokio')",
+    )
+    .execute(&db)
+    .await
+    .expect("Failed to create entry");
+
+    // Positive assertion
+    let response = CLIENT
+        .get(format!("{}/api/v2/providers/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    let provider: Provider = response.json().await.unwrap();
+    assert_eq!(provider.url, "https://github.com");
+
+    let response = CLIENT
+        .get(format!("{}/api/v2/projects/2", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    let project: Project = response.json().await.unwrap();
+    assert_eq!(project.namespace, "actix-web");
+
+    let response = CLIENT
+        .get(format!("{}/api/v2/project-stats/1", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    let history: ProjectStatsHistory = response.json().await.unwrap();
+    assert_eq!(history.project.name, "warp");
+    let unsafeLines: Vec<i32> = history.stats.iter().map(|v| v.unsafe_lines).collect();
+    assert_eq!(unsafeLines, vec![11, 10]);
+
+    // A project without stats has an empty history.
+    let response = CLIENT
+        .get(format!("{}/api/v2/project-stats/3", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    let history: ProjectStatsHistory = response.json().await.unwrap();
+    assert!(history.stats.is_empty());
+
+    // Negative assertion
+    for (path, detail) in [
+        ("providers/100", "Provider 100 does not exist"),
+        ("projects/100", "Project 100 does not exist"),
+        ("project-stats/100", "Project 100 does not exist"),
+    ] {
+        let response = CLIENT
+            .get(format!("{}/api/v2/{}", &address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/problem+json"
+        );
+        let problem: Problem = response.json().await.unwrap();
+        assert_eq!(
+            (
+                problem.status,
+                problem.title.as_str(),
+                problem.detail.as_str()
+            ),
+            (404, "Not Found", detail)
+        );
+    }
+
+    // v1 keeps its arrays.
+    let response = CLIENT
+        .get(format!("{}/api/v1/projects/100", &address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    let projects: Vec<Project> = response.json().await.unwrap();
+    assert!(projects.is_empty());
+}
@@ -0,0 +1,646 @@
+#![allow(non_snake_case, clippy::needless_return)]
+
+//! The same checks against every `Repository`: in memory, and the backends of `common`.
+
+mod common;
+
+use sqlx::{PgPool, SqlitePool};
+use std::sync::Arc;
+use unsaferust::analysis::{
+    category::Category,
+    lines::LineCounts,
+    site::{ProjectUnsafeSite, UnsafeKind, UnsafeSite},
+    CategoryStats, CrateAnalysis, ProjectAnalysis, UnsafeUsage,
+};
+use unsaferust::models::configuration::DatabaseBackend;
+use unsaferust::models::pagination::{ProjectStatsQuery, ProjectStatsSort};
+use unsaferust::models::project::ProjectInput;
+use unsaferust::models::provider::ProviderInput;
+use unsaferust::services::memory::InMemoryRepository;
+use unsaferust::services::postgres::{DatabaseError, PostgresService};
+use unsaferust::services::repository::Repository;
+use unsaferust::services::sqlite::SqliteService;
+
+/// A new, empty repository of the in-memory backend and of every backend of the tests.
+async fn repositories() -> Vec<(&'static str, Arc<dyn Repository>)> {
+    let mut repositories: Vec<(&'static str, Arc<dyn Repository>)> =
+        vec![("memory", Arc::new(InMemoryRepository::new()))];
+    for backend in common::test_backends() {
+        let url = common::create_database(backend).await;
+        match backend {
+            DatabaseBackend::Postgres => {
+                let connection_pool = PgPool::connect(&url)
+                    .await
+                    .expect("Failed to connect to Postgres.");
+                let repository = PostgresService::new(Some(connection_pool)).await;
+                repositories.push(("postgres", Arc::new(repository)));
+            }
+            DatabaseBackend::Sqlite => {
+                let connection_pool = SqlitePool::connect(&url)
+                    .await
+                    .expect("Failed to open SQLite");
+                let repository = SqliteService::new(Some(connection_pool)).await;
+                repositories.push(("sqlite", Arc::new(repository)));
+            }
+        }
+    }
+    return repositories;
+}
+
+fn providerInput(url: &str) -> ProviderInput {
+    return ProviderInput {
+        url: url.to_owned(),
+    };
+}
+
+fn projectInput(provider_id: i32, name: &str) -> ProjectInput {
+    return ProjectInput {
+        provider_id,
+        namespace: "namespace".to_owned(),
+        name: name.to_owned(),
+    };
+}
+
+fn analysis(code_lines: i32, unsafe_blocks: i32, documented_unsafe: i32) -> ProjectAnalysis {
+    return ProjectAnalysis {
+        line_counts: LineCounts {
+            code_lines,
+            ..Default::default()
+        },
+        unsafe_usage: UnsafeUsage {
+            unsafe_blocks,
+            unsafe_block_lines: unsafe_blocks,
+            documented_unsafe,
+            undocumented_unsafe: unsafe_blocks - documented_unsafe,
+            ..Default::default()
+        },
+        commit_sha: Some("0123456789abcdef0123456789abcdef01234567".to_owned()),
+        ..Default::default()
+    };
+}
+
+/// An analysis of a commit with two crates and three documented or undocumented sites.
+fn detailedAnalysis(commitSha: &str) -> ProjectAnalysis {
+    let usage = UnsafeUsage {
+        unsafe_blocks: 3,
+        unsafe_block_lines: 3,
+        documented_unsafe: 2,
+        undocumented_unsafe: 1,
+        ..Default::default()
+    };
+    let lineCounts = |code_lines| LineCounts {
+        code_lines,
+        ..Default::default()
+    };
+    let crateAnalysis = |name: &str, code_lines| CrateAnalysis {
+        name: name.to_owned(),
+        path: format!("crates/{name}"),
+        analysis: ProjectAnalysis {
+            line_counts: lineCounts(code_lines),
+            unsafe_usage: usage,
+            ..Default::default()
+        },
+    };
+    let site = |file_path: &str, start_line, documented| ProjectUnsafeSite {
+        file_path: file_path.to_owned(),
+        category: Category::Source,
+        site: UnsafeSite {
+            kind: UnsafeKind::UnsafeBlock,
+            start_line,
+            end_line: start_line + 2,
+            item_path: "main".to_owned(),
+            documented: Some(documented),
+        },
+    };
+    return ProjectAnalysis {
+        line_counts: lineCounts(100),
+        unsafe_usage: usage,
+        categories: [(
+            Category::Source,
+            CategoryStats {
+                line_counts: lineCounts(100),
+                unsafe_usage: usage,
+            },
+        )]
+        .into(),
+        crates: vec![crateAnalysis("b", 60), crateAnalysis("a", 40)],
+        unsafe_sites: vec![
+            site("src/main.rs", 10, true),
+            site("src/lib.rs", 5, false),
+            site("src/lib.rs", 1, true),
+        ],
+        commit_sha: Some(commitSha.to_owned()),
+        commit_date: Some("2020-05-01T12:00:00+00:00".to_owned()),
+        ..Default::default()
+    };
+}
+
+#[tokio::test]
+async fn test_repository_providers() {
+    for (backend, repository) in repositories().await {
+        // Setup
+        let github = repository
+            .insertProvider(&providerInput("https://github.com"))
+            .await
+            .unwrap();
+        repository
+            .insertProvider(&providerInput("https://gitlab.com"))
+            .await
+            .unwrap();
+        repository
+            .insertProject(&projectInput(1, "clap"))
+            .await
+            .unwrap();
+
+        // Positive assertion
+        assert_eq!(github.url, "https://github.com", "{backend}");
+        repository
+            .createProvider("https://codeberg.org")
+            .await
+            .unwrap();
+        repository
+            .createProvider("https://github.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            repository.getProviders().await.unwrap().len(),
+            3,
+            "{backend}"
+        );
+        let updated = repository
+            .updateProvider(2, &providerInput("https://gitlab.org"))
+            .await
+            .unwrap();
+        assert_eq!(updated.unwrap().url, "https://gitlab.org", "{backend}");
+        assert!(repository.deleteProvider(2).await.unwrap(), "{backend}");
+        assert!(repository.getProviderById(2).await.unwrap().is_empty());
+
+        // Negative assertion
+        let result = repository
+            .insertProvider(&providerInput("https://github.com"))
+            .await;
+        assert!(
+            matches!(result, Err(DatabaseError::Duplicate(_))),
+            "{backend}: {result:?}"
+        );
+        let result = repository
+            .updateProvider(3, &providerInput("https://github.com"))
+            .await;
+        assert!(
+            matches!(result, Err(DatabaseError::Duplicate(_))),
+            "{backend}: {result:?}"
+        );
+        let result = repository
+            .updateProvider(100, &providerInput("https://example.com"))
+            .await;
+        assert!(matches!(result, Ok(None)), "{backend}: {result:?}");
+        let result = repository.deleteProvider(1).await;
+        assert!(
+            matches!(result, Err(DatabaseError::Reference(_))),
+            "{backend}: {result:?}"
+        );
+        assert!(!repository.deleteProvider(100).await.unwrap(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn test_repository_projects() {
+    for (backend, repository) in repositories().await {
+        // Setup
+        repository
+            .createProvider("https://github.com")
+            .await
+            .unwrap();
+        let names = ["clap", "o'brien", "x'); drop table projects; --"];
+        for name in names {
+            repository
+                .createProject("https://github.com", "namespace", name)
+                .await
+                .unwrap();
+        }
+
+        // Positive assertion
+        repository
+            .createProject("https://github.com", "namespace", "clap")
+            .await
+            .unwrap();
+        let projects = repository.getProjects().await.unwrap();
+        let stored: Vec<&str> = projects.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(stored, names, "{backend}");
+        let withUrl = repository.getProjectsWithUrl().await.unwrap();
+        assert_eq!(withUrl.len(), 3, "{backend}");
+        let project = repository
+            .updateProject(2, &projectInput(1, "serde"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(project.name, "serde", "{backend}");
+        assert_eq!(
+            repository.getProjectById(2).await.unwrap()[0].name,
+            "serde",
+            "{backend}"
+        );
+        repository
+            .updateProjectStats(3, &analysis(100, 1, 0))
+            .await
+            .unwrap();
+        assert!(repository.deleteProject(3).await.unwrap(), "{backend}");
+        assert!(repository.getProjectsStatsById(3).await.unwrap().is_empty());
+
+        // Negative assertion
+        let result = repository.insertProject(&projectInput(1, "clap")).await;
+        assert!(
+            matches!(result, Err(DatabaseError::Duplicate(_))),
+            "{backend}: {result:?}"
+        );
+        let result = repository.insertProject(&projectInput(100, "tokio")).await;
+        assert!(
+            matches!(result, Err(DatabaseError::Reference(_))),
+            "{backend}: {result:?}"
+        );
+        let result = repository.updateProject(2, &projectInput(1, "clap")).await;
+        assert!(
+            matches!(result, Err(DatabaseError::Duplicate(_))),
+            "{backend}: {result:?}"
+        );
+        let result = repository
+            .updateProject(100, &projectInput(1, "tokio"))
+            .await;
+        assert!(matches!(result, Ok(None)), "{backend}: {result:?}");
+        let result = repository
+            .createProject("https://unknown.org", "namespace", "unknown")
+            .await;
+        assert!(
+            matches!(result, Err(DatabaseError::Other(_))),
+            "{backend}: {result:?}"
+        );
+        assert!(!repository.deleteProject(3).await.unwrap(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn test_repository_project_stats() {
+    for (backend, repository) in repositories().await {
+        // Setup
+        repository
+            .createProvider("https://github.com")
+            .await
+            .unwrap();
+        repository
+            .createProvider("https://gitlab.com")
+            .await
+            .unwrap();
+        for (providerId, name) in [(1, "a"), (1, "b"), (2, "c"), (1, "d")] {
+            repository
+                .insertProject(&projectInput(providerId, name))
+                .await
+                .unwrap();
+        }
+        // code lines, unsafe blocks, documented unsafe
+        let stats = [(100, 5, 5), (1000, 4, 1), (5000, 10, 0), (0, 0, 0)];
+        for (id, (codeLines, unsafeBlocks, documentedUnsafe)) in stats.into_iter().enumerate() {
+            repository
+                .updateProjectStats(
+                    id as i32 + 1,
+                    &analysis(codeLines, unsafeBlocks, documentedUnsafe),
+                )
+                .await
+                .unwrap();
+        }
+        // The same unsafe lines update the stats instead of adding to the history.
+        repository
+            .updateProjectStats(1, &analysis(200, 5, 4))
+            .await
+            .unwrap();
+
+        // Positive assertion
+        let history = repository.getProjectsStatsById(1).await.unwrap();
+        assert_eq!(history.len(), 1, "{backend}");
+        assert_eq!(history[0].line_counts.code_lines, 200, "{backend}");
+        assert_eq!(history[0].unsafe_lines, 5, "{backend}");
+        assert_eq!(history[0].safety_coverage, Some(80.0), "{backend}");
+        assert_eq!(
+            history[0].commit_sha.as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567"),
+            "{backend}"
+        );
+        assert_eq!(history[0].created_at.len(), 10, "{backend}");
+
+        let names = |query: ProjectStatsQuery| {
+            let repository = repository.clone();
+            return async move {
+                let result = repository.getProjectsStats(&query).await.unwrap();
+                let names: Vec<String> = result.projectStats.into_iter().map(|v| v.name).collect();
+                return (names, result.meta);
+            };
+        };
+        let query = ProjectStatsQuery {
+            limit: 50,
+            ..Default::default()
+        };
+        assert_eq!(
+            names(query.clone()).await,
+            (vec!["a".into(), "b".into(), "c".into(), "d".into()], 4),
+            "{backend}"
+        );
+        assert_eq!(
+            names(ProjectStatsQuery {
+                sort: ProjectStatsSort::UnsafeRatio,
+                descending: true,
+                ..query.clone()
+            })
+            .await,
+            (vec!["a".into(), "b".into(), "c".into(), "d".into()], 4),
+            "{backend}"
+        );
+        assert_eq!(
+            names(ProjectStatsQuery {
+                sort: ProjectStatsSort::CodeLines,
+                descending: true,
+                limit: 2,
+                page: 1,
+                ..query.clone()
+            })
+            .await,
+            (vec!["a".into(), "d".into()], 4),
+            "{backend}"
+        );
+        assert_eq!(
+            names(ProjectStatsQuery {
+                min_unsafe_ratio: Some(0.3),
+                min_code_lines: Some(1000),
+                ..query.clone()
+            })
+            .await,
+            (vec!["b".into()], 1),
+            "{backend}"
+        );
+        assert_eq!(
+            names(ProjectStatsQuery {
+                provider_id: Some(2),
+                name: Some("C".to_owned()),
+                ..query.clone()
+            })
+            .await,
+            (vec!["c".into()], 1),
+            "{backend}"
+        );
+        assert_eq!(
+            names(ProjectStatsQuery {
+                unsafe_code_lint: Some("allow"),
+                updated_since: Some("2000-01-01".to_owned()),
+                ..query.clone()
+            })
+            .await
+            .1,
+            4,
+            "{backend}"
+        );
+
+        // Negative assertion
+        assert_eq!(
+            names(ProjectStatsQuery {
+                unsafe_code_lint: Some("forbid"),
+                ..query.clone()
+            })
+            .await,
+            (vec![], 0),
+            "{backend}"
+        );
+        assert_eq!(
+            names(ProjectStatsQuery {
+                name: Some("%".to_owned()),
+                ..query.clone()
+            })
+            .await,
+            (vec![], 0),
+            "{backend}"
+        );
+        let result = repository
+            .updateProjectStats(100, &analysis(100, 1, 0))
+            .await;
+        assert!(
+            matches!(result, Err(DatabaseError::Reference(_))),
+            "{backend}: {result:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_repository_log_error() {
+    for (backend, repository) in repositories().await {
+        // Positive assertion
+        let result = repository.logError("Something 'failed'").await;
+        assert!(result.is_ok(), "{backend}: {result:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_repository_project_analysis() {
+    for (backend, repository) in repositories().await {
+        // Setup
+        repository
+            .createProvider("https://github.com")
+            .await
+            .unwrap();
+        repository
+            .createProject("https://github.com", "namespace", "a")
+            .await
+            .unwrap();
+        let current = "a".repeat(40);
+        let past = "b".repeat(40);
+        assert_eq!(
+            repository.getLatestCommitShaByProjectId(1).await.unwrap(),
+            None,
+            "{backend}"
+        );
+        repository
+            .updateProjectStats(1, &detailedAnalysis(&current))
+            .await
+            .unwrap();
+
+        // Positive assertion
+        let categories = repository.getProjectCategoryStatsById(1).await.unwrap();
+        assert_eq!(categories.len(), 1, "{backend}");
+        assert_eq!(categories[0].category, "source", "{backend}");
+        assert_eq!(categories[0].unsafe_lines, 3, "{backend}");
+        assert_eq!(categories[0].line_counts.code_lines, 100, "{backend}");
+
+        let crates = repository.getProjectCratesById(1).await.unwrap();
+        let names: Vec<&str> = crates.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"], "{backend}");
+        assert_eq!(crates[0].path, "crates/a", "{backend}");
+        assert_eq!(crates[0].safety_coverage, Some(66.67), "{backend}");
+        assert_eq!(crates[0].unsafe_code_lint, "allow", "{backend}");
+
+        let sites = repository.getUnsafeSitesByProjectId(1).await.unwrap();
+        let lines: Vec<(&str, i32)> = sites
+            .iter()
+            .map(|v| (v.file_path.as_str(), v.start_line))
+            .collect();
+        assert_eq!(
+            lines,
+            [("src/lib.rs", 1), ("src/lib.rs", 5), ("src/main.rs", 10)],
+            "{backend}"
+        );
+        assert_eq!(sites[0].kind, "unsafe_block", "{backend}");
+        assert_eq!(sites[1].documented, Some(false), "{backend}");
+        assert_eq!(
+            sites[0].permalink,
+            format!("https://github.com/namespace/a/blob/{current}/src/lib.rs#L1-L3"),
+            "{backend}"
+        );
+
+        let coverage = repository.getSafetyCoverageByProjectId(1).await.unwrap();
+        let files: Vec<(&str, i32, i32, Option<f64>)> = coverage
+            .iter()
+            .map(|v| {
+                return (
+                    v.file_path.as_str(),
+                    v.documented_unsafe,
+                    v.undocumented_unsafe,
+                    v.safety_coverage,
+                );
+            })
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("src/lib.rs", 1, 1, Some(50.0)),
+                ("src/main.rs", 1, 0, Some(100.0))
+            ],
+            "{backend}"
+        );
+
+        // A past commit is dated by the commit, once, and does not replace the latest stats.
+        assert!(
+            repository
+                .insertHistoricalProjectStats(1, &detailedAnalysis(&past))
+                .await
+                .unwrap(),
+            "{backend}"
+        );
+        assert!(
+            !repository
+                .insertHistoricalProjectStats(1, &detailedAnalysis(&past))
+                .await
+                .unwrap(),
+            "{backend}"
+        );
+        let history = repository.getProjectsStatsById(1).await.unwrap();
+        let dates: Vec<&str> = history.iter().map(|v| v.created_at.as_str()).collect();
+        assert_eq!(dates.len(), 2, "{backend}");
+        assert_eq!(dates[1], "2020-05-01", "{backend}");
+        assert_eq!(
+            repository.getLatestCommitShaByProjectId(1).await.unwrap(),
+            Some(current.clone()),
+            "{backend}"
+        );
+        let query = ProjectStatsQuery {
+            limit: 50,
+            ..Default::default()
+        };
+        let latest = repository.getProjectsStats(&query).await.unwrap();
+        assert_eq!(latest.meta, 1, "{backend}");
+        assert_eq!(
+            latest.projectStats[0].commit_sha,
+            Some(current.clone()),
+            "{backend}"
+        );
+
+        // The crates and the sites follow the latest analysis.
+        let analysis = ProjectAnalysis {
+            crates: detailedAnalysis(&current).crates.split_off(1),
+            unsafe_sites: Vec::new(),
+            ..detailedAnalysis(&current)
+        };
+        repository.updateProjectStats(1, &analysis).await.unwrap();
+        let crates = repository.getProjectCratesById(1).await.unwrap();
+        assert_eq!(crates.len(), 1, "{backend}");
+        assert_eq!(crates[0].name, "a", "{backend}");
+        assert!(repository
+            .getSafetyCoverageByProjectId(1)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Negative assertion
+        let result = repository
+            .insertHistoricalProjectStats(100, &detailedAnalysis(&past))
+            .await;
+        assert!(
+            matches!(result, Err(DatabaseError::Reference(_))),
+            "{backend}: {result:?}"
+        );
+        assert!(repository
+            .getProjectCategoryStatsById(100)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(repository
+            .getUnsafeSitesByProjectId(100)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(repository.deleteProject(1).await.unwrap(), "{backend}");
+        assert!(repository.getProjectCratesById(1).await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_repository_historical_project_stats() {
+    for (backend, repository) in repositories().await {
+        // Setup
+        repository
+            .createProvider("https://github.com")
+            .await
+            .unwrap();
+        repository
+            .createProject("https://github.com", "namespace", "a")
+            .await
+            .unwrap();
+        repository
+            .updateProjectStats(1, &detailedAnalysis(&"a".repeat(40)))
+            .await
+            .unwrap();
+        // A tag that is backfilled later, and is newer than the current stats.
+        let tag = ProjectAnalysis {
+            commit_sha: Some("b".repeat(40)),
+            commit_date: Some("2099-01-01T12:00:00+00:00".to_owned()),
+            ..analysis(500, 7, 0)
+        };
+        assert!(
+            repository
+                .insertHistoricalProjectStats(1, &tag)
+                .await
+                .unwrap(),
+            "{backend}"
+        );
+
+        // Positive assertion
+        let query = ProjectStatsQuery {
+            limit: 50,
+            ..Default::default()
+        };
+        let latest = repository.getProjectsStats(&query).await.unwrap();
+        assert_eq!(latest.meta, 1, "{backend}");
+        assert_eq!(latest.projectStats[0].unsafe_lines, 3, "{backend}");
+        let categories = repository.getProjectCategoryStatsById(1).await.unwrap();
+        assert_eq!(categories.len(), 1, "{backend}");
+        assert_eq!(categories[0].unsafe_lines, 3, "{backend}");
+        // The tag is in the history.
+        let history = repository.getProjectsStatsById(1).await.unwrap();
+        let unsafeLines: Vec<i32> = history.iter().map(|v| v.unsafe_lines).collect();
+        assert_eq!(unsafeLines, [7, 3], "{backend}");
+
+        // Negative assertion
+        // The date of the tag does not stand for the project.
+        let query = ProjectStatsQuery {
+            limit: 50,
+            updated_since: Some("2098-01-01".to_owned()),
+            ..Default::default()
+        };
+        let latest = repository.getProjectsStats(&query).await.unwrap();
+        assert_eq!(latest.meta, 0, "{backend}");
+    }
+}